@@ -1,13 +1,56 @@
+pub const AMM_PAIRS_KEY: &[u8] = b"amm_pairs";
 pub const BLOCK_SIZE: usize = 256;
+pub const COMMITMENTS_COUNT_KEY: &[u8] = b"commitments_count";
+pub const CONFIG_CHANGES_COUNT_KEY: &[u8] = b"config_changes_count";
 pub const CONFIG_KEY: &[u8] = b"config";
+pub const CONTRACT_ADDRESS_KEY: &[u8] = b"contract_address";
+pub const CONTRACT_CODE_HASH_KEY: &[u8] = b"contract_code_hash";
+pub const DEFAULT_CANDLE_INTERVALS: [u64; 2] = [3_600, 86_400];
+pub const DEFAULT_CONFIG_CHANGE_DELAY_IN_BLOCKS: u64 = 14_400;
+pub const DEFAULT_PAGE_SIZE: u128 = 10;
+pub const FILLERS_COUNT_KEY: &[u8] = b"fillers_count";
+pub const IMPORT_COMPLETE_KEY: &[u8] = b"import_complete";
+pub const MAX_CANDLE_INTERVALS: usize = 5;
+pub const MAX_PAGE_SIZE: u128 = 100;
+pub const MAX_ROUTE_CANDIDATES: usize = 50;
+pub const ROUTE_HOPS: usize = 2;
+pub const ROUTE_ORDERS_PER_PAIR: usize = 5;
 pub const MOCK_AMOUNT: u128 = 1_000_000_000_000;
 pub const MOCK_BUTT_ADDRESS: &str = "mock-butt-address";
+pub const MOCK_SSCRT_ADDRESS: &str = "mock-sscrt-address";
 pub const MOCK_TOKEN_ADDRESS: &str = "mock-token-address";
+pub const PAIR_TOKEN_ADDRESSES_KEY: &[u8] = b"pair_token_addresses";
+pub const PREFIX_FORCE_CANCEL_CURSORS: &[u8] = b"force_cancel_cursors";
+pub const PREFIX_OPEN_COMMITMENTS: &[u8] = b"open_commitments";
+pub const PREFIX_OPEN_ORDERS: &[u8] = b"open_orders";
 pub const PREFIX_ORDERS: &[u8] = b"orders";
 pub const PREFIX_ORDERS_COUNT: &[u8] = b"orders_count";
+pub const PREFIX_ORDER_ACTIVITY_RECORDS: &[u8] = b"order_activity_records";
+pub const PREFIX_ACTIVITY_RECORDS: &[u8] = b"activity_records";
+pub const PREFIX_ACTIVITY_RECORDS_COUNT: &[u8] = b"activity_records_count";
+pub const PREFIX_ACTIVITY_RECORDS_INDEXES: &[u8] = b"activity_records_indexes";
+pub const PREFIX_ACTIVITY_RECORDS_INDEXES_COUNT: &[u8] = b"activity_records_indexes_count";
+pub const PREFIX_CANDLES: &[u8] = b"candles";
+pub const PREFIX_CANDLES_COUNT: &[u8] = b"candles_count";
+pub const PREFIX_COMMITMENTS: &[u8] = b"commitments";
 pub const PREFIX_CANCEL_RECORDS: &[u8] = b"cancel_records";
 pub const PREFIX_CANCEL_RECORDS_COUNT: &[u8] = b"cancel_records_count";
+pub const PREFIX_FILLERS: &[u8] = b"fillers";
+pub const PREFIX_FILLERS_ALLOWED: &[u8] = b"allowed";
+pub const PREFIX_FILLER_ADDRESSES: &[u8] = b"filler_addresses";
 pub const PREFIX_FILL_RECORDS: &[u8] = b"fill_records";
+pub const PREFIX_INDEXES: &[u8] = b"indexes";
 pub const PREFIX_FILL_RECORDS_COUNT: &[u8] = b"fill_records_count";
+pub const PREFIX_PAIRS: &[u8] = b"pairs";
+pub const PREFIX_PAIR_STATS: &[u8] = b"pair_stats";
+pub const PREFIX_PENDING_CONFIG_CHANGES: &[u8] = b"pending_config_changes";
 pub const PREFIX_REGISTERED_TOKENS: &[u8] = b"registered_tokens";
+pub const PREFIX_REVOKED_PERMITS: &str = "revoked_permits";
+pub const PREFIX_ROLES: &[u8] = b"roles";
+pub const PREFIX_TOKEN_TOTALS: &[u8] = b"token_totals";
+pub const PRICE_SCALE: u128 = 1_000_000_000_000_000_000;
+pub const REGISTERED_TOKEN_ADDRESSES_KEY: &[u8] = b"registered_token_addresses";
+pub const REVEAL_DELAY_IN_BLOCKS: u64 = 3;
+pub const ROLLING_VOLUME_PERIOD_IN_SECONDS: u64 = 86_400;
+pub const ROLLING_VOLUME_BUCKET_IN_SECONDS: u64 = 3_600;
 pub const ROUTE_STATE_KEY: &[u8] = b"route_state";
@@ -1,23 +1,49 @@
 use crate::constants::{
-    BLOCK_SIZE, CONFIG_KEY, MOCK_AMOUNT, MOCK_BUTT_ADDRESS, MOCK_TOKEN_ADDRESS,
-    PREFIX_CANCEL_RECORDS, PREFIX_CANCEL_RECORDS_COUNT, PREFIX_FILL_RECORDS,
-    PREFIX_FILL_RECORDS_COUNT, PREFIX_ORDERS, PREFIX_ORDERS_COUNT,
+    BLOCK_SIZE, CONFIG_KEY, DEFAULT_CANDLE_INTERVALS, DEFAULT_CONFIG_CHANGE_DELAY_IN_BLOCKS,
+    MAX_CANDLE_INTERVALS, MAX_PAGE_SIZE, MAX_ROUTE_CANDIDATES, MOCK_AMOUNT, MOCK_BUTT_ADDRESS,
+    MOCK_SSCRT_ADDRESS, MOCK_TOKEN_ADDRESS, PREFIX_ACTIVITY_RECORDS, PREFIX_ACTIVITY_RECORDS_COUNT,
+    PREFIX_ACTIVITY_RECORDS_INDEXES, PREFIX_ACTIVITY_RECORDS_INDEXES_COUNT, PREFIX_CANCEL_RECORDS,
+    PREFIX_CANCEL_RECORDS_COUNT, PREFIX_FILL_RECORDS, PREFIX_FILL_RECORDS_COUNT,
+    PREFIX_FORCE_CANCEL_CURSORS, PREFIX_ORDERS, PREFIX_ORDERS_COUNT, PREFIX_ORDER_ACTIVITY_RECORDS,
+    PREFIX_REVOKED_PERMITS, REVEAL_DELAY_IN_BLOCKS, ROUTE_HOPS, ROUTE_ORDERS_PER_PAIR,
 };
-use crate::msg::{HandleMsg, InitMsg, QueryAnswer, QueryMsg, ReceiveMsg, Snip20Swap};
+use crate::msg::{
+    ActivityFilters, AmmAsset, AmmAssetInfo, AmmPairQueryMsg, AmmSimulationResponse, HandleAnswer,
+    HandleMsg, HopSimulation, InitMsg, Pagination, QueryAnswer, QueryMsg, QueryWithPermit,
+    ReceiveMsg, RouteQuote, Snip20Swap, SortOrder, StatePage, StateSection, TokenMetadata,
+    TokenPair, TokenSolvency,
+};
+use crate::pagination::Page;
 use crate::state::{
-    delete_route_state, read_registered_token, read_route_state, store_route_state,
-    write_registered_token, ActivityRecord, Config, Hop, HumanizedOrder, Order, RegisteredToken,
-    RouteState, SecretContract,
+    add_volume, calculate_price, commitment_hash, delete_route_state, insert_open_commitment,
+    insert_open_order, read_amm_pairs, read_candle, read_candles_count, read_commitment,
+    read_commitments_count, read_config_changes_count, read_contract_address,
+    read_contract_code_hash, read_filler, read_filler_address, read_filler_allowed,
+    read_fillers_count, read_import_complete, read_open_commitment_positions,
+    read_open_commitments_count, read_open_order_positions, read_open_orders_count, read_pair,
+    read_pair_stats, read_pair_token_addresses, read_pending_config_change,
+    read_pending_config_change_ids, read_pending_config_changes_count, read_registered_token,
+    read_registered_token_addresses, read_role_addresses, read_route_state, read_token_totals,
+    remove_open_commitment, remove_open_order, remove_pending_config_change, store_route_state,
+    write_amm_pairs, write_candle, write_candles_count, write_commitment, write_commitments_count,
+    write_config_changes_count, write_contract_address, write_contract_code_hash, write_filler,
+    write_filler_address, write_filler_allowed, write_fillers_count, write_import_complete,
+    write_pair, write_pair_stats, write_pair_token_addresses, write_pending_config_change,
+    write_registered_token, write_registered_token_addresses, write_role_addresses,
+    write_token_totals, ActivityKind, ActivityRecord, AmmPair, BookOrder, Candle, Commitment,
+    Config, Fill, Filler, Hop, HumanizedOrder, OpenFilling, Order, Pair, PairStats, PauseFlags,
+    PendingConfigChange, RegisteredToken, Role, RouteState, SecretContract, TokenAmount,
+    TokenTotals,
 };
-use crate::validations::{authorize, validate_human_addr, validate_uint128};
+use crate::validations::{authorize, validate_human_addr, validate_not_paused, validate_uint128};
 use cosmwasm_std::{
-    from_binary, to_binary, Api, BalanceResponse, BankMsg, BankQuery, Binary, CanonicalAddr, Coin,
-    CosmosMsg, Env, Extern, HandleResponse, HumanAddr, InitResponse, Querier, QueryRequest,
-    ReadonlyStorage, StdError, StdResult, Storage, Uint128, WasmMsg,
+    from_binary, to_binary, to_vec, Api, BalanceResponse, BankMsg, BankQuery, Binary,
+    CanonicalAddr, Coin, CosmosMsg, Env, Extern, HandleResponse, HumanAddr, InitResponse, Querier,
+    QueryRequest, ReadonlyStorage, StdError, StdResult, Storage, Uint128, WasmMsg, WasmQuery,
 };
 use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
-use primitive_types::U256;
-use secret_toolkit::snip20;
+use secret_toolkit::permit::{self, Permit, RevokedPermits, TokenPermissions};
+use secret_toolkit::snip20::{self, TokenInfo};
 use secret_toolkit::storage::{TypedStore, TypedStoreMut};
 use std::collections::VecDeque;
 
@@ -26,15 +52,31 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: InitMsg,
 ) -> StdResult<InitResponse> {
-    let mut config_store = TypedStoreMut::attach(&mut deps.storage);
-    let config: Config = Config {
-        addresses_allowed_to_fill: vec![env.message.sender.clone(), env.contract.address],
-        admin: env.message.sender,
+    write_contract_address(&mut deps.storage, &env.contract.address)?;
+    write_contract_code_hash(&mut deps.storage, &env.contract_code_hash)?;
+    let mut config: Config = Config {
+        addresses_allowed_to_fill: vec![],
+        admin: env.message.sender.clone(),
         butt: msg.butt,
+        candle_intervals: DEFAULT_CANDLE_INTERVALS.to_vec(),
+        config_change_delay_in_blocks: msg
+            .config_change_delay_in_blocks
+            .unwrap_or(DEFAULT_CONFIG_CHANGE_DELAY_IN_BLOCKS),
         execution_fee: msg.execution_fee,
+        open_filling: OpenFilling::default(),
+        pause_flags: PauseFlags::default(),
+        pending_admin: None,
         sscrt: msg.sscrt,
     };
-    config_store.store(CONFIG_KEY, &config)?;
+    set_addresses_allowed_to_fill(
+        &mut deps.storage,
+        &deps.api,
+        &mut config,
+        vec![env.message.sender, env.contract.address],
+    )?;
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+    // An instance that state is imported into can't take orders until the import is complete
+    write_import_complete(&mut deps.storage, !msg.importing.unwrap_or(false))?;
 
     Ok(InitResponse {
         messages: vec![],
@@ -48,19 +90,50 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     msg: HandleMsg,
 ) -> StdResult<HandleResponse> {
     match msg {
+        HandleMsg::AcceptAdmin {} => accept_admin(deps, &env),
+        HandleMsg::CancelConfigChange { id } => cancel_config_change(deps, &env, id),
         HandleMsg::CancelOrder {
             from_token_address,
             position,
         } => cancel_order(deps, &env, from_token_address, position.u128()),
+        HandleMsg::CompleteImport { key } => complete_import(deps, &env, key),
+        HandleMsg::DisableToken { address } => disable_token(deps, &env, address),
+        HandleMsg::HandOverEscrow { key, recipient } => {
+            hand_over_escrow(deps, &env, key, recipient)
+        }
         HandleMsg::HandleFirstHop {
             borrow_amount,
             hops,
             minimum_acceptable_amount,
         } => handle_first_hop(deps, &env, borrow_amount, hops, minimum_acceptable_amount),
+        HandleMsg::ImportState { state_page } => import_state(deps, &env, state_page),
+        HandleMsg::ExecuteConfigChange { id } => execute_config_change(deps, &env, id),
         HandleMsg::FinalizeRoute {} => finalize_route(deps, &env),
+        HandleMsg::ForceCancelOrders { token, limit } => {
+            force_cancel_orders(deps, &env, token, limit.u128())
+        }
+        HandleMsg::GrantRole { role, address } => grant_role(deps, &env, role, address),
+        HandleMsg::ProposeAdmin { address } => propose_admin(deps, &env, address),
+        HandleMsg::ProposeConfigChange {
+            addresses_allowed_to_fill,
+            config_change_delay_in_blocks,
+            execution_fee,
+            open_filling,
+        } => propose_config_change(
+            deps,
+            &env,
+            addresses_allowed_to_fill,
+            config_change_delay_in_blocks,
+            execution_fee,
+            open_filling,
+        ),
         HandleMsg::Receive {
             from, amount, msg, ..
         } => receive(deps, env, from, amount, msg),
+        HandleMsg::ReclaimCommitment { position } => {
+            reclaim_commitment(deps, &env, position.u128())
+        }
+        HandleMsg::RefreshTokenMetadata { address } => refresh_token_metadata(deps, &env, address),
         HandleMsg::RegisterTokens {
             tokens,
             viewing_key,
@@ -70,10 +143,33 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
             key,
             token_address,
         } => rescue_tokens(deps, &env, denom, key, token_address),
-        HandleMsg::UpdateConfig {
-            addresses_allowed_to_fill,
-            execution_fee,
-        } => update_config(deps, &env, addresses_allowed_to_fill, execution_fee),
+        HandleMsg::RevealOrder {
+            position,
+            to_amount,
+            to_token,
+            salt,
+        } => reveal_order(deps, &env, position.u128(), to_amount, to_token, salt),
+        HandleMsg::RevokePermit { permit_name } => revoke_permit(deps, &env, permit_name),
+        HandleMsg::RevokeRole { role, address } => revoke_role(deps, &env, role, address),
+        HandleMsg::SetFillerAddressPublic { address_public } => {
+            set_filler_address_public(deps, &env, address_public)
+        }
+        HandleMsg::SetFillerDisabled { address, disabled } => {
+            set_filler_disabled(deps, &env, address, disabled)
+        }
+        HandleMsg::SetAmmPairs { amm_pairs } => set_amm_pairs(deps, &env, amm_pairs),
+        HandleMsg::SetCandleIntervals { candle_intervals } => {
+            set_candle_intervals(deps, &env, candle_intervals)
+        }
+        HandleMsg::SetPairs { pairs } => set_pairs(deps, &env, pairs),
+        HandleMsg::SetPauseFlags { pause_flags } => set_pause_flags(deps, &env, pause_flags),
+        HandleMsg::UpdateToken {
+            address,
+            contract_hash,
+            decimals,
+        } => update_token(deps, &env, address, contract_hash, decimals),
+        HandleMsg::UnbondFiller { amount } => unbond_filler(deps, &env, amount),
+        HandleMsg::WithdrawFillerBond {} => withdraw_filler_bond(deps, &env),
     }
 }
 
@@ -81,45 +177,95 @@ pub fn query<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     msg: QueryMsg,
 ) -> StdResult<Binary> {
-    match msg {
+    let bucketed: bool = msg
+        .pagination()
+        .and_then(|pagination| pagination.bucketed)
+        .unwrap_or(false);
+    let result = match msg {
+        QueryMsg::BestRoute {
+            token,
+            borrow_amount,
+            max_hops,
+        } => best_route(deps, token, borrow_amount, max_hops),
         QueryMsg::CancelRecords {
             key,
-            page,
-            page_size,
-        } => activity_records(
-            deps,
+            filters,
+            pagination,
+        } => activity_records(deps, key, filters, pagination, PREFIX_CANCEL_RECORDS),
+        QueryMsg::Candles {
+            pair,
+            interval,
+            from_time,
+            to_time,
+            pagination,
+        } => candles(deps, pair, interval, from_time, to_time, pagination),
+        QueryMsg::Commitments {
+            address,
             key,
-            page.u128(),
-            page_size.u128(),
-            PREFIX_CANCEL_RECORDS,
-        ),
+            pagination,
+        } => commitments(deps, address, key, pagination),
         QueryMsg::FillRecords {
             key,
-            page,
-            page_size,
-        } => activity_records(
-            deps,
-            key,
-            page.u128(),
-            page_size.u128(),
-            PREFIX_FILL_RECORDS,
-        ),
+            filters,
+            pagination,
+        } => activity_records(deps, key, filters, pagination, PREFIX_FILL_RECORDS),
         QueryMsg::Config {} => {
             let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
-            Ok(to_binary(&config)?)
+            let tokens: Vec<TokenMetadata> = token_metadata(
+                &deps.storage,
+                &deps.api,
+                vec![&config.butt.address, &config.sscrt.address],
+            )?;
+            Ok(to_binary(&QueryAnswer::Config { config, tokens })?)
         }
+        QueryMsg::ExportState {
+            key,
+            section,
+            pagination,
+        } => export_state(deps, key, section, pagination),
+        QueryMsg::FillerStats { address } => filler_stats(deps, address),
+        QueryMsg::Fillers { pagination } => fillers(deps, pagination),
+        QueryMsg::MyActivity {
+            address,
+            key,
+            filters,
+            pagination,
+        } => my_activity(deps, address, key, filters, pagination),
         QueryMsg::Orders {
             address,
             key,
-            page,
-            page_size,
-        } => orders(deps, address, key, page.u128(), page_size.u128()),
+            pagination,
+        } => orders(deps, address, key, pagination),
+        QueryMsg::OrderBook {
+            pair,
+            significant_figures,
+            pagination,
+        } => order_book(deps, pair, significant_figures, pagination),
         QueryMsg::OrdersByPositions {
             address,
             key,
             positions,
         } => orders_by_positions(deps, address, key, positions),
-    }
+        QueryMsg::PairStats {
+            from_token,
+            to_token,
+        } => pair_stats(deps, from_token, to_token),
+        QueryMsg::Pairs { pagination } => pairs(deps, pagination),
+        QueryMsg::PendingConfigChanges { pagination } => pending_config_changes(deps, pagination),
+        QueryMsg::RegisteredTokens { pagination } => registered_tokens(deps, pagination),
+        QueryMsg::Role {
+            role: role_to_query,
+        } => role(deps, role_to_query),
+        QueryMsg::SimulateFill { position, amount } => simulate_fill(deps, position.u128(), amount),
+        QueryMsg::SimulateRoute {
+            borrow_amount,
+            hops,
+            minimum_acceptable_amount,
+        } => simulate_route(deps, borrow_amount, hops, minimum_acceptable_amount),
+        QueryMsg::Solvency { key, viewing_key } => solvency(deps, key, viewing_key),
+        QueryMsg::WithPermit { permit, query } => permit_queries(deps, permit, query),
+    };
+    pad_query_result(result, bucketed)
 }
 
 fn receive<S: Storage, A: Api, Q: Querier>(
@@ -132,13 +278,18 @@ fn receive<S: Storage, A: Api, Q: Querier>(
     let response = if let Some(msg_unwrapped) = msg {
         let msg: ReceiveMsg = from_binary(&msg_unwrapped)?;
         match msg {
+            ReceiveMsg::BondFiller {} => bond_filler(deps, &env, from, amount),
             ReceiveMsg::SetExecutionFeeForOrder {} => {
                 set_execution_fee_for_order(deps, &env, from, amount)
             }
+            ReceiveMsg::CommitOrder { hash } => commit_order(deps, &env, from, amount, hash),
             ReceiveMsg::CreateOrder {
                 to_amount,
                 to_token,
-            } => create_order(deps, &env, from, amount, to_amount, to_token),
+            } => {
+                let from_token: HumanAddr = env.message.sender.clone();
+                create_order(deps, &env, from, from_token, amount, to_amount, to_token)
+            }
             ReceiveMsg::FillOrder { position } => {
                 fill_order(deps, &env, from, amount, position.u128())
             }
@@ -152,8 +303,8 @@ fn receive<S: Storage, A: Api, Q: Querier>(
 fn activity_records<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     key: String,
-    page: u128,
-    page_size: u128,
+    filters: Option<ActivityFilters>,
+    pagination: Option<Pagination>,
     storage_prefix: &[u8],
 ) -> StdResult<Binary> {
     let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
@@ -161,11 +312,28 @@ fn activity_records<S: Storage, A: Api, Q: Querier>(
     query_balance_of_token(deps, config.admin.clone(), config.butt, key)?;
 
     let address = deps.api.canonical_address(&config.admin)?;
-    let (activity_records, total) =
-        get_activity_records(&deps.storage, &address, page, page_size, storage_prefix)?;
+    let page: Page = Page::new(pagination, SortOrder::Descending);
+    let (activity_records, total, next_start_after) = match filters {
+        Some(filters) => {
+            get_filtered_activity_records(&deps.storage, &address, &filters, &page, storage_prefix)?
+        }
+        None => get_activity_records(&deps.storage, &address, &page, storage_prefix)?,
+    };
+    let tokens: Vec<TokenMetadata> = token_metadata(
+        &deps.storage,
+        &deps.api,
+        activity_records
+            .iter()
+            .flat_map(|activity_record| {
+                vec![&activity_record.from_token, &activity_record.to_token]
+            })
+            .collect(),
+    )?;
     let result = QueryAnswer::ActivityRecords {
         activity_records,
         total: Some(Uint128(total)),
+        next_start_after: next_start_after.map(Uint128),
+        tokens,
     };
     to_binary(&result)
 }
@@ -173,11 +341,110 @@ fn activity_records<S: Storage, A: Api, Q: Querier>(
 fn prefix_activity_records_count(activity_records_storage_prefix: &[u8]) -> &[u8] {
     if activity_records_storage_prefix == PREFIX_CANCEL_RECORDS {
         PREFIX_CANCEL_RECORDS_COUNT
+    } else if activity_records_storage_prefix == PREFIX_ACTIVITY_RECORDS {
+        PREFIX_ACTIVITY_RECORDS_COUNT
     } else {
         PREFIX_FILL_RECORDS_COUNT
     }
 }
 
+// Activity records are indexed by kind, by pair and by both, so that filtering on them pages over
+// the index instead of scanning every record. The admin's records are also indexed by order, a
+// user's are listed by order_activity_record_positions. Each index lists record positions in order.
+fn activity_records_index_key(
+    activity: Option<ActivityKind>,
+    pair: Option<(&HumanAddr, &HumanAddr)>,
+    order_position: Option<Uint128>,
+) -> StdResult<Vec<u8>> {
+    to_vec(&(activity, pair, order_position))
+}
+
+fn activity_records_index_count<S: ReadonlyStorage>(
+    storage: &S,
+    for_address: &CanonicalAddr,
+    storage_prefix: &[u8],
+    key: &[u8],
+) -> u128 {
+    let store = ReadonlyPrefixedStorage::multilevel(
+        &[
+            PREFIX_ACTIVITY_RECORDS_INDEXES_COUNT,
+            storage_prefix,
+            for_address.as_slice(),
+        ],
+        storage,
+    );
+    TypedStore::<u128, _>::attach(&store)
+        .may_load(key)
+        .unwrap()
+        .unwrap_or(0)
+}
+
+fn activity_records_index_position<S: ReadonlyStorage>(
+    storage: &S,
+    for_address: &CanonicalAddr,
+    storage_prefix: &[u8],
+    key: &[u8],
+    index_position: u128,
+) -> StdResult<u128> {
+    let store = ReadonlyPrefixedStorage::multilevel(
+        &[
+            PREFIX_ACTIVITY_RECORDS_INDEXES,
+            storage_prefix,
+            for_address.as_slice(),
+            key,
+        ],
+        storage,
+    );
+    TypedStore::<u128, _>::attach(&store).load(&index_position.to_le_bytes())
+}
+
+fn append_to_activity_records_indexes<S: Storage>(
+    storage: &mut S,
+    activity_record: &ActivityRecord,
+    for_address: &CanonicalAddr,
+    storage_prefix: &[u8],
+) -> StdResult<()> {
+    let pair: (&HumanAddr, &HumanAddr) = (&activity_record.from_token, &activity_record.to_token);
+    let mut keys: Vec<Vec<u8>> = vec![
+        activity_records_index_key(Some(activity_record.activity), None, None)?,
+        activity_records_index_key(None, Some(pair), None)?,
+        activity_records_index_key(Some(activity_record.activity), Some(pair), None)?,
+    ];
+    if storage_prefix != PREFIX_ACTIVITY_RECORDS {
+        keys.push(activity_records_index_key(
+            None,
+            None,
+            Some(activity_record.order_position),
+        )?);
+    }
+    for key in keys.iter() {
+        let index_count: u128 =
+            activity_records_index_count(storage, for_address, storage_prefix, key);
+        let mut store = PrefixedStorage::multilevel(
+            &[
+                PREFIX_ACTIVITY_RECORDS_INDEXES,
+                storage_prefix,
+                for_address.as_slice(),
+                key,
+            ],
+            storage,
+        );
+        TypedStoreMut::<u128, _>::attach(&mut store)
+            .store(&index_count.to_le_bytes(), &activity_record.position.u128())?;
+        let mut count_store = PrefixedStorage::multilevel(
+            &[
+                PREFIX_ACTIVITY_RECORDS_INDEXES_COUNT,
+                storage_prefix,
+                for_address.as_slice(),
+            ],
+            storage,
+        );
+        TypedStoreMut::<u128, _>::attach(&mut count_store).store(key, &(index_count + 1))?;
+    }
+
+    Ok(())
+}
+
 fn set_execution_fee_for_order<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
@@ -185,6 +452,10 @@ fn set_execution_fee_for_order<S: Storage, A: Api, Q: Querier>(
     amount: Uint128,
 ) -> StdResult<HandleResponse> {
     let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    validate_not_paused(
+        config.pause_flags.set_execution_fee_paused(),
+        "Setting execution fee is paused.",
+    )?;
     validate_human_addr(
         &config.sscrt.address,
         &env.message.sender,
@@ -233,14 +504,73 @@ fn set_execution_fee_for_order<S: Storage, A: Api, Q: Querier>(
         creator_order.clone(),
         &contract_canonical_address,
     )?;
+    append_user_activity_record(
+        &mut deps.storage,
+        &user_canonical_address,
+        ActivityRecord {
+            execution_fee: Some(amount),
+            ..ActivityRecord::new(
+                ActivityKind::FeeSet,
+                creator_order.position,
+                creator_order.from_token.clone(),
+                creator_order.to_token.clone(),
+                env.block.height,
+                env.block.time,
+            )
+        },
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&humanize_order(
+            &deps.api,
+            &deps.storage,
+            creator_order,
+        )?)?),
+    })
+}
+
+fn accept_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+) -> StdResult<HandleResponse> {
+    let mut config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    match config.pending_admin.clone() {
+        Some(pending_admin) => authorize(vec![pending_admin], &env.message.sender)?,
+        None => return Err(StdError::generic_err("No admin has been proposed.")),
+    }
+
+    // The admin is always allowed to fill, so swap the old admin out for the new one
+    let old_admin: HumanAddr = config.admin.clone();
+    let mut addresses_allowed_to_fill: Vec<HumanAddr> = config.addresses_allowed_to_fill.clone();
+    addresses_allowed_to_fill.retain(|address| address != &old_admin);
+    if !addresses_allowed_to_fill.contains(&env.message.sender) {
+        addresses_allowed_to_fill.push(env.message.sender.clone())
+    }
+    set_addresses_allowed_to_fill(
+        &mut deps.storage,
+        &deps.api,
+        &mut config,
+        addresses_allowed_to_fill,
+    )?;
+    config.admin = env.message.sender.clone();
+    config.pending_admin = None;
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![],
-        data: Some(to_binary(&creator_order.into_humanized(&deps.api)?)?),
+        data: None,
     })
 }
 
+fn addresses_with_role<S: Storage>(storage: &S, config: &Config, role: Role) -> Vec<HumanAddr> {
+    let mut addresses: Vec<HumanAddr> = read_role_addresses(storage, role);
+    addresses.push(config.admin.clone());
+    addresses
+}
+
 fn append_activity_record<S: Storage>(
     store: &mut S,
     activity_record: &ActivityRecord,
@@ -254,6 +584,7 @@ fn append_activity_record<S: Storage>(
         &activity_record.position.u128().to_le_bytes(),
         activity_record,
     )?;
+    append_to_activity_records_indexes(store, activity_record, for_address, storage_prefix)?;
     set_count(
         store,
         for_address,
@@ -270,6 +601,38 @@ fn append_activity_record<S: Storage>(
     )
 }
 
+// Records for a user are kept in one list, with the positions of each order's records indexed
+fn append_user_activity_record<S: Storage>(
+    store: &mut S,
+    for_address: &CanonicalAddr,
+    mut activity_record: ActivityRecord,
+) -> StdResult<()> {
+    activity_record.position = Uint128(storage_count(
+        store,
+        for_address,
+        PREFIX_ACTIVITY_RECORDS_COUNT,
+    )?);
+    append_activity_record(
+        store,
+        &activity_record,
+        for_address,
+        PREFIX_ACTIVITY_RECORDS,
+    )?;
+
+    let mut positions: Vec<u128> =
+        order_activity_record_positions(store, for_address, activity_record.order_position.u128());
+    positions.push(activity_record.position.u128());
+    let mut prefixed_store = PrefixedStorage::multilevel(
+        &[PREFIX_ORDER_ACTIVITY_RECORDS, for_address.as_slice()],
+        store,
+    );
+    let mut positions_store = TypedStoreMut::<Vec<u128>, _>::attach(&mut prefixed_store);
+    positions_store.store(
+        &activity_record.order_position.u128().to_le_bytes(),
+        &positions,
+    )
+}
+
 fn set_count<S: Storage>(
     store: &mut S,
     for_address: &CanonicalAddr,
@@ -302,14 +665,356 @@ fn append_order<S: Storage>(
     )
 }
 
+// Config changes are authorized by the role that manages each parameter being changed
+fn authorize_config_change<S: Storage>(
+    storage: &S,
+    config: &Config,
+    addresses_allowed_to_fill: &Option<Vec<HumanAddr>>,
+    config_change_delay_in_blocks: &Option<u64>,
+    execution_fee: &Option<Uint128>,
+    open_filling: &Option<OpenFilling>,
+    sender: &HumanAddr,
+) -> StdResult<()> {
+    if config_change_delay_in_blocks.is_some() {
+        authorize(vec![config.admin.clone()], sender)?;
+    }
+    if addresses_allowed_to_fill.is_some() || open_filling.is_some() {
+        authorize(
+            addresses_with_role(storage, config, Role::FillerManager),
+            sender,
+        )?;
+    }
+    if execution_fee.is_some() {
+        authorize(
+            addresses_with_role(storage, config, Role::FeeManager),
+            sender,
+        )?;
+    }
+
+    Ok(())
+}
+
+// Whitelisted fillers can always fill.
+// When open filling is enabled, fillers with enough BUTT bonded can fill unless disabled.
+fn authorize_filler<S: Storage, A: Api>(
+    storage: &S,
+    api: &A,
+    config: &Config,
+    address: &HumanAddr,
+) -> StdResult<()> {
+    let address_canonical: CanonicalAddr = api.canonical_address(address)?;
+    if read_filler_allowed(storage, &address_canonical) {
+        return Ok(());
+    }
+    if config.open_filling.enabled {
+        if let Some(filler) = read_filler(storage, &address_canonical) {
+            if !filler.disabled && filler.bonded_amount >= config.open_filling.bond_amount {
+                return Ok(());
+            }
+        }
+    }
+
+    Err(StdError::Unauthorized { backtrace: None })
+}
+
+// The config's addresses_allowed_to_fill is kept for listing, while fills are authorized through
+// the keyed allowed fillers store
+fn set_addresses_allowed_to_fill<S: Storage, A: Api>(
+    storage: &mut S,
+    api: &A,
+    config: &mut Config,
+    addresses_allowed_to_fill: Vec<HumanAddr>,
+) -> StdResult<()> {
+    for address in config.addresses_allowed_to_fill.iter() {
+        write_filler_allowed(storage, &api.canonical_address(address)?, false)?;
+    }
+    for address in addresses_allowed_to_fill.iter() {
+        write_filler_allowed(storage, &api.canonical_address(address)?, true)?;
+    }
+    config.addresses_allowed_to_fill = addresses_allowed_to_fill;
+    Ok(())
+}
+
+// Searches cycles from token back to itself through open contract orders and AMM pairs and quotes the one that pays the filler the most. The filler gets the excess
+// unless every hop is a limit order, in which case it goes to the creator of the newest order,
+// and the execution fees of the orders it fills, which are paid in SSCRT. Routes are always
+// ROUTE_HOPS long, as handle_first_hop only takes routes of that length. Only the
+// ROUTE_ORDERS_PER_PAIR best priced of the oldest MAX_PAGE_SIZE open orders of each pair are
+// tried and at most MAX_ROUTE_CANDIDATES routes are simulated, so that the query stays cheap.
+fn best_route<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token: HumanAddr,
+    borrow_amount: Uint128,
+    max_hops: Option<u8>,
+) -> StdResult<Binary> {
+    if matches!(max_hops, Some(max_hops) if usize::from(max_hops) != ROUTE_HOPS) {
+        return Err(StdError::generic_err(format!(
+            "Routes must be {} hops.",
+            ROUTE_HOPS
+        )));
+    }
+    let registered_tokens: Vec<RegisteredToken> = read_registered_token_addresses(&deps.storage)
+        .iter()
+        .map(|address| {
+            Ok(
+                read_registered_token(&deps.storage, &deps.api.canonical_address(address)?)
+                    .unwrap(),
+            )
+        })
+        .collect::<StdResult<Vec<RegisteredToken>>>()?;
+    let token_contract = |address: &HumanAddr| -> Option<SecretContract> {
+        registered_tokens
+            .iter()
+            .find(|registered_token| &registered_token.address == address)
+            .map(|registered_token| SecretContract {
+                address: registered_token.address.clone(),
+                contract_hash: registered_token.contract_hash.clone(),
+            })
+    };
+    if token_contract(&token).is_none() {
+        return Err(StdError::generic_err("Token is not registered."));
+    }
+
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    let contract: SecretContract = SecretContract {
+        address: read_contract_address(&deps.storage)?,
+        contract_hash: read_contract_code_hash(&deps.storage)?,
+    };
+    let contract_canonical_address: CanonicalAddr =
+        deps.api.canonical_address(&contract.address)?;
+    let pair_token_addresses: Vec<(HumanAddr, HumanAddr)> =
+        read_pair_token_addresses(&deps.storage);
+    let amm_pairs: Vec<AmmPair> = read_amm_pairs(&deps.storage);
+    // The hops that can be taken with a token and the token each one pays out. Orders are taken
+    // from the open orders of the pairs that trade to the token, cheapest first.
+    let next_hops = |from_token: &HumanAddr,
+                     paying_out: Option<&HumanAddr>|
+     -> StdResult<Vec<(Hop, HumanAddr)>> {
+        let from_token_contract: SecretContract = match token_contract(from_token) {
+            Some(from_token_contract) => from_token_contract,
+            None => return Ok(vec![]),
+        };
+        let mut next_hops: Vec<(Hop, HumanAddr)> = vec![];
+        for (order_from_token, order_to_token) in pair_token_addresses.iter() {
+            if order_to_token != from_token
+                || matches!(paying_out, Some(paying_out) if paying_out != order_from_token)
+            {
+                continue;
+            }
+            let (positions, _) = read_open_order_positions(
+                &deps.storage,
+                Some((order_from_token, order_to_token)),
+                &Page {
+                    start_after: None,
+                    limit: MAX_PAGE_SIZE,
+                    order: SortOrder::Ascending,
+                },
+            );
+            let mut orders: Vec<Order> = positions
+                .into_iter()
+                .map(|position| {
+                    order_at_position(&deps.storage, &contract_canonical_address, position)
+                })
+                .collect::<StdResult<Vec<Order>>>()?;
+            // The price is what the filler pays per from token received
+            orders.sort_by_key(|order| {
+                calculate_price(
+                    order.unfilled_amount(),
+                    Uint128(order.net_to_amount.u128() - order.net_to_amount_filled.u128()),
+                )
+                .map_or(u128::MAX, |price| price.u128())
+            });
+            for order in orders.into_iter().take(ROUTE_ORDERS_PER_PAIR) {
+                next_hops.push((
+                    Hop {
+                        from_token: from_token_contract.clone(),
+                        trade_smart_contract: contract.clone(),
+                        position: Some(order.position),
+                    },
+                    order_from_token.clone(),
+                ));
+            }
+        }
+        for amm_pair in amm_pairs.iter() {
+            let to_token: &HumanAddr = if &amm_pair.token_a == from_token {
+                &amm_pair.token_b
+            } else if &amm_pair.token_b == from_token {
+                &amm_pair.token_a
+            } else {
+                continue;
+            };
+            if matches!(paying_out, Some(paying_out) if paying_out != to_token) {
+                continue;
+            }
+            next_hops.push((
+                Hop {
+                    from_token: from_token_contract.clone(),
+                    trade_smart_contract: amm_pair.contract.clone(),
+                    position: None,
+                },
+                to_token.clone(),
+            ));
+        }
+        Ok(next_hops)
+    };
+
+    // The second hop has to pay out the token the route started with. Its hops are looked up once
+    // for each token in the middle of a route.
+    let mut routes: Vec<VecDeque<Hop>> = vec![];
+    let mut second_hops: Vec<(HumanAddr, Vec<(Hop, HumanAddr)>)> = vec![];
+    'routes: for (first_hop, middle_token) in next_hops(&token, None)? {
+        if !second_hops
+            .iter()
+            .any(|(second_hops_token, _)| second_hops_token == &middle_token)
+        {
+            second_hops.push((
+                middle_token.clone(),
+                next_hops(&middle_token, Some(&token))?,
+            ));
+        }
+        let (_, middle_token_hops) = second_hops
+            .iter()
+            .find(|(second_hops_token, _)| second_hops_token == &middle_token)
+            .unwrap();
+        for (second_hop, _) in middle_token_hops {
+            if routes.len() == MAX_ROUTE_CANDIDATES {
+                break 'routes;
+            }
+            routes.push(
+                vec![first_hop.clone(), second_hop.clone()]
+                    .into_iter()
+                    .collect(),
+            );
+        }
+    }
+
+    // Each route is checked the same way as SimulateRoute, so routes that would revert are skipped
+    let mut best: Option<(RouteQuote, (Uint128, Uint128))> = None;
+    for hops in routes {
+        let mut hop_simulations: Vec<HopSimulation> = vec![];
+        let final_amount: Uint128 =
+            match simulate_route_amount(deps, borrow_amount, &hops, None, &mut hop_simulations) {
+                Ok(final_amount) => final_amount,
+                Err(_) => continue,
+            };
+        let excess: Uint128 = (final_amount - borrow_amount)?;
+        let excess_to_filler: Uint128 = if hops.iter().all(|hop| {
+            hop.position.is_some() && hop.trade_smart_contract.address == contract.address
+        }) {
+            Uint128(0)
+        } else {
+            excess
+        };
+        let execution_fees: Uint128 = hop_simulations
+            .iter()
+            .filter_map(|hop_simulation| hop_simulation.execution_fee)
+            .fold(Uint128(0), |execution_fees, execution_fee| {
+                execution_fees + execution_fee
+            });
+        // Payouts in the same token are added up, otherwise the excess counts first
+        let payout: (Uint128, Uint128) = if token == config.sscrt.address {
+            (excess_to_filler + execution_fees, Uint128(0))
+        } else {
+            (excess_to_filler, execution_fees)
+        };
+        if let Some((_, best_payout)) = &best {
+            if *best_payout >= payout {
+                continue;
+            }
+        }
+        let tokens: Vec<TokenMetadata> = route_token_metadata(deps, &hops, &hop_simulations)?;
+        best = Some((
+            RouteQuote {
+                hops: hop_simulations,
+                final_amount,
+                excess,
+                excess_to_filler,
+                tokens,
+                handle_msg: HandleMsg::HandleFirstHop {
+                    borrow_amount,
+                    hops,
+                    minimum_acceptable_amount: None,
+                },
+            },
+            payout,
+        ));
+    }
+
+    to_binary(&QueryAnswer::BestRoute {
+        route: best.map(|(route, _)| route),
+    })
+}
+
+fn bond_filler<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    amount: Uint128,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    validate_human_addr(
+        &config.butt.address,
+        &env.message.sender,
+        "Bonds must be made in BUTT.",
+    )?;
+
+    // Bonds are included in the sum balance so that they can't be rescued
+    let butt_address_canonical: CanonicalAddr = deps.api.canonical_address(&config.butt.address)?;
+    let mut butt_registered_token: RegisteredToken =
+        match read_registered_token(&deps.storage, &butt_address_canonical) {
+            Some(registered_token) => registered_token,
+            None => return Err(StdError::generic_err("BUTT is not registered.")),
+        };
+    butt_registered_token.sum_balance += amount;
+    write_registered_token(
+        &mut deps.storage,
+        &butt_address_canonical,
+        &butt_registered_token,
+    )?;
+
+    let mut butt_totals: TokenTotals = read_token_totals(&deps.storage, &config.butt.address);
+    butt_totals.bonded_amount += amount;
+    write_token_totals(&mut deps.storage, &config.butt.address, &butt_totals)?;
+
+    let filler_address_canonical: CanonicalAddr = deps.api.canonical_address(&from)?;
+    let mut filler: Filler =
+        read_or_register_filler(&mut deps.storage, &filler_address_canonical, from)?;
+    filler.bonded_amount += amount;
+    write_filler(&mut deps.storage, &filler_address_canonical, &filler)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn cancel_config_change<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    id: Uint128,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(vec![config.admin], &env.message.sender)?;
+
+    if read_pending_config_change(&deps.storage, id.u128()).is_none() {
+        return Err(StdError::generic_err("Config change does not exist."));
+    }
+    remove_pending_config_change(&mut deps.storage, id.u128())?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
 fn cancel_order<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
     from_token_address: HumanAddr,
     position: u128,
 ) -> StdResult<HandleResponse> {
-    let contract_canonical_address: CanonicalAddr =
-        deps.api.canonical_address(&env.contract.address)?;
     let mut creator_order = order_at_position(
         &deps.storage,
         &deps.api.canonical_address(&env.message.sender)?,
@@ -327,102 +1032,359 @@ fn cancel_order<S: Storage, A: Api, Q: Querier>(
         return Err(StdError::generic_err("Order already filled."));
     }
 
-    let from_token_address_canonical: CanonicalAddr =
-        deps.api.canonical_address(&creator_order.from_token)?;
-    let mut from_registered_token: RegisteredToken =
-        read_registered_token(&deps.storage, &from_token_address_canonical).unwrap();
-    let unfilled_amount: Uint128 = (creator_order.from_amount - creator_order.from_amount_filled)?;
+    let messages: Vec<CosmosMsg> = refund_and_cancel_order(deps, env, &mut creator_order, false)?;
 
-    // Update from_registered_token balance
-    from_registered_token.sum_balance = (from_registered_token.sum_balance - unfilled_amount)?;
-    write_registered_token(
+    pad_response(Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&humanize_order(
+            &deps.api,
+            &deps.storage,
+            creator_order,
+        )?)?),
+    }))
+}
+
+// Candles are stored in time order, so the range is found with a binary search on start_time
+fn candles<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pair: TokenPair,
+    interval: u64,
+    from_time: u64,
+    to_time: u64,
+    pagination: Option<Pagination>,
+) -> StdResult<Binary> {
+    let from_token_canonical_address: CanonicalAddr =
+        deps.api.canonical_address(&pair.from_token)?;
+    let to_token_canonical_address: CanonicalAddr = deps.api.canonical_address(&pair.to_token)?;
+    if read_pair(
+        &deps.storage,
+        &from_token_canonical_address,
+        &to_token_canonical_address,
+    )
+    .is_none()
+    {
+        return Err(StdError::generic_err("Pair is not registered."));
+    }
+
+    let count: u64 = read_candles_count(
+        &deps.storage,
+        &from_token_canonical_address,
+        &to_token_canonical_address,
+        interval,
+    );
+    let first_position_after = |time: u64| -> u64 {
+        let mut low: u64 = 0;
+        let mut high: u64 = count;
+        while low < high {
+            let middle: u64 = low + (high - low) / 2;
+            let candle: Candle = read_candle(
+                &deps.storage,
+                &from_token_canonical_address,
+                &to_token_canonical_address,
+                interval,
+                middle,
+            )
+            .unwrap();
+            if candle.start_time < time {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        low
+    };
+    let range_start: u64 = first_position_after(from_time);
+    let range_end: u64 = first_position_after(to_time.saturating_add(1)).max(range_start);
+    let total: u128 = u128::from(range_end - range_start);
+    let (positions, next_start_after) = Page::new(pagination, SortOrder::Ascending)
+        .positions(u128::from(range_start), u128::from(range_end));
+    let mut candles: Vec<Candle> = vec![];
+    for position in positions {
+        candles.push(
+            read_candle(
+                &deps.storage,
+                &from_token_canonical_address,
+                &to_token_canonical_address,
+                interval,
+                position as u64,
+            )
+            .unwrap(),
+        );
+    }
+
+    let tokens: Vec<TokenMetadata> = token_metadata(
+        &deps.storage,
+        &deps.api,
+        vec![&pair.from_token, &pair.to_token],
+    )?;
+    let result = QueryAnswer::Candles {
+        candles,
+        total: Some(Uint128(total)),
+        next_start_after: next_start_after.map(Uint128),
+        tokens,
+    };
+    to_binary(&result)
+}
+
+fn clamp_to_unfilled_amount<S: Storage>(
+    storage: &S,
+    contract_canonical_address: &CanonicalAddr,
+    position: u128,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    let order: Order = order_at_position(storage, contract_canonical_address, position)?;
+    let unfilled_amount: Uint128 = (order.net_to_amount - order.net_to_amount_filled)?;
+
+    Ok(amount.min(unfilled_amount))
+}
+
+// The escrowed amount leaves the commitment, either back to the creator or into an order
+fn close_commitment<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    mut commitment: Commitment,
+) -> StdResult<RegisteredToken> {
+    commitment.closed = true;
+    write_commitment(&mut deps.storage, &commitment)?;
+    remove_open_commitment(&mut deps.storage, &commitment)?;
+    let mut from_token_totals: TokenTotals =
+        read_token_totals(&deps.storage, &commitment.from_token);
+    from_token_totals.committed_amount =
+        (from_token_totals.committed_amount - commitment.from_amount)?;
+    write_token_totals(
+        &mut deps.storage,
+        &commitment.from_token,
+        &from_token_totals,
+    )?;
+    let from_token_address_canonical = deps.api.canonical_address(&commitment.from_token)?;
+    let mut from_token_details: RegisteredToken =
+        read_registered_token(&deps.storage, &from_token_address_canonical).unwrap();
+    from_token_details.sum_balance = (from_token_details.sum_balance - commitment.from_amount)?;
+    write_registered_token(
         &mut deps.storage,
         &from_token_address_canonical,
-        &from_registered_token,
+        &from_token_details,
     )?;
+    Ok(from_token_details)
+}
 
-    // Send refund to the creator
-    let mut messages: Vec<CosmosMsg> = vec![];
-    messages.push(snip20::transfer_msg(
-        env.message.sender.clone(),
-        unfilled_amount,
-        None,
-        BLOCK_SIZE,
-        from_registered_token.contract_hash,
-        from_registered_token.address,
-    )?);
+// Escrows the from amount of an order whose to token and to amount stay hidden until it is
+// revealed, so that the order can't be seen before it is fillable
+fn commit_order<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    amount: Uint128,
+    hash: Binary,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    validate_not_paused(
+        config.pause_flags.create_order_paused(),
+        "Order creation is paused.",
+    )?;
+    if !read_import_complete(&deps.storage) {
+        return Err(StdError::generic_err("Import is not complete."));
+    }
+    if amount.is_zero() {
+        return Err(StdError::generic_err("Amount must be greater than zero."));
+    }
+    if hash.len() != 32 {
+        return Err(StdError::generic_err(
+            "Commitment hash must be a sha256 hash.",
+        ));
+    }
+    let from_token_address_canonical = deps.api.canonical_address(&env.message.sender)?;
+    let mut from_token_details: RegisteredToken =
+        match read_registered_token(&deps.storage, &from_token_address_canonical) {
+            Some(RegisteredToken { disabled: true, .. }) => {
+                return Err(StdError::generic_err("From token is disabled."))
+            }
+            Some(registered_token) => registered_token,
+            None => return Err(StdError::generic_err("From token is not registered.")),
+        };
+    from_token_details.sum_balance += amount;
+    write_registered_token(
+        &mut deps.storage,
+        &from_token_address_canonical,
+        &from_token_details,
+    )?;
 
-    // Update Txs
-    creator_order.cancelled = true;
-    update_creator_order_and_associated_contract_order(
+    let mut from_token_totals: TokenTotals = read_token_totals(&deps.storage, &env.message.sender);
+    from_token_totals.committed_amount += amount;
+    write_token_totals(&mut deps.storage, &env.message.sender, &from_token_totals)?;
+
+    let position: u128 = read_commitments_count(&deps.storage);
+    let commitment: Commitment = Commitment {
+        position: Uint128(position),
+        creator: deps.api.canonical_address(&from)?,
+        from_token: env.message.sender.clone(),
+        from_amount: amount,
+        hash,
+        committed_at_block_height: env.block.height,
+        closed: false,
+    };
+    write_commitment(&mut deps.storage, &commitment)?;
+    insert_open_commitment(&mut deps.storage, &commitment)?;
+    write_commitments_count(
         &mut deps.storage,
-        &creator_order.creator,
-        creator_order.clone(),
-        &contract_canonical_address,
+        position.checked_add(1).ok_or_else(|| {
+            StdError::generic_err("Reached implementation limit for the number of commitments.")
+        })?,
     )?;
-    // Create activity record
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::CommitOrder {
+            position: Uint128(position),
+        })?),
+    })
+}
+
+// Only the creator can reveal or reclaim a commitment, and only once
+fn commitment_for_creator<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+    position: u128,
+) -> StdResult<Commitment> {
+    let commitment: Commitment = match read_commitment(&deps.storage, position) {
+        Some(commitment) => commitment,
+        None => return Err(StdError::generic_err("Commitment not found.")),
+    };
+    authorize(
+        vec![deps.api.human_address(&commitment.creator)?],
+        &env.message.sender,
+    )?;
+    if commitment.closed {
+        return Err(StdError::generic_err("Commitment already closed."));
+    }
+
+    Ok(commitment)
+}
+
+fn commitments<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    key: String,
+    pagination: Option<Pagination>,
+) -> StdResult<Binary> {
     let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
-    let admin_canonical_address: CanonicalAddr = deps.api.canonical_address(&config.admin)?;
-    let activity_record: ActivityRecord = ActivityRecord {
-        position: Uint128(storage_count(
+    // This is here so that the user can use their viewing key for butt for this
+    query_balance_of_token(deps, address.clone(), config.butt, key)?;
+
+    let creator: CanonicalAddr = deps.api.canonical_address(&address)?;
+    let (positions, next_start_after) = read_open_commitment_positions(
+        &deps.storage,
+        &creator,
+        &Page::new(pagination, SortOrder::Descending),
+    );
+    let commitments: Vec<Commitment> = positions
+        .into_iter()
+        .map(|position| read_commitment(&deps.storage, position).unwrap())
+        .collect();
+
+    let tokens: Vec<TokenMetadata> = token_metadata(
+        &deps.storage,
+        &deps.api,
+        commitments
+            .iter()
+            .map(|commitment| &commitment.from_token)
+            .collect(),
+    )?;
+    let result = QueryAnswer::Commitments {
+        commitments,
+        total: Some(Uint128(read_open_commitments_count(
             &deps.storage,
-            &admin_canonical_address,
-            PREFIX_CANCEL_RECORDS_COUNT,
-        )?),
-        order_position: creator_order.other_storage_position,
-        activity: 0,
-        result_from_amount_filled: None,
-        result_net_to_amount_filled: None,
-        updated_at_block_height: env.block.height,
-        updated_at_block_time: env.block.time,
+            &creator,
+        ))),
+        next_start_after: next_start_after.map(Uint128),
+        tokens,
     };
-    append_activity_record(
-        &mut deps.storage,
-        &activity_record,
-        &admin_canonical_address,
-        PREFIX_CANCEL_RECORDS,
-    )?;
+    to_binary(&result)
+}
 
-    // If order has an execution fee and it has not been spent, send it back to the user
-    if creator_order.from_amount_filled.is_zero() {
-        if let Some(execution_fee_unwrapped) = creator_order.execution_fee {
-            messages.push(snip20::transfer_msg(
-                env.message.sender.clone(),
-                execution_fee_unwrapped,
-                None,
-                BLOCK_SIZE,
-                config.sscrt.contract_hash,
-                config.sscrt.address,
-            )?);
-        }
+// Checks that the escrowed balances moved into this instance cover the imported sum balances
+fn complete_import<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    key: String,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(vec![config.admin], &env.message.sender)?;
+    if read_import_complete(&deps.storage) {
+        return Err(StdError::generic_err("Import is complete."));
     }
 
-    pad_response(Ok(HandleResponse {
-        messages,
+    for address in read_registered_token_addresses(&deps.storage).iter() {
+        let registered_token: RegisteredToken =
+            read_registered_token(&deps.storage, &deps.api.canonical_address(address)?).unwrap();
+        query_escrow_balance(deps, env, &registered_token, key.clone())?;
+    }
+    write_import_complete(&mut deps.storage, true)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
         log: vec![],
-        data: Some(to_binary(&creator_order.into_humanized(&deps.api)?)?),
-    }))
+        data: None,
+    })
 }
 
 fn create_order<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
     from: HumanAddr,
+    from_token: HumanAddr,
     from_amount: Uint128,
     to_amount: Uint128,
     to_token: HumanAddr,
 ) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    validate_not_paused(
+        config.pause_flags.create_order_paused(),
+        "Order creation is paused.",
+    )?;
+    if !read_import_complete(&deps.storage) {
+        return Err(StdError::generic_err("Import is not complete."));
+    }
     let to_token_address_canonical = deps.api.canonical_address(&to_token)?;
     let to_token_details: Option<RegisteredToken> =
         read_registered_token(&deps.storage, &to_token_address_canonical);
-    if to_token_details.is_none() {
-        return Err(StdError::generic_err("To token is not registered."));
+    match to_token_details {
+        None => return Err(StdError::generic_err("To token is not registered.")),
+        Some(RegisteredToken { disabled: true, .. }) => {
+            return Err(StdError::generic_err("To token is disabled."))
+        }
+        _ => {}
     }
 
     // Increase sum balance for from_token
-    let from_token_address_canonical = deps.api.canonical_address(&env.message.sender)?;
+    let from_token_address_canonical = deps.api.canonical_address(&from_token)?;
     let mut from_token_details: RegisteredToken =
         read_registered_token(&deps.storage, &from_token_address_canonical).unwrap();
+    if from_token_details.disabled {
+        return Err(StdError::generic_err("From token is disabled."));
+    }
+    let pair: Pair = match read_pair(
+        &deps.storage,
+        &from_token_address_canonical,
+        &to_token_address_canonical,
+    ) {
+        Some(pair) => pair,
+        None => return Err(StdError::generic_err("Pair is not registered.")),
+    };
+    if !pair.enabled {
+        return Err(StdError::generic_err("Pair is disabled."));
+    }
+    if from_amount < pair.minimum_from_amount {
+        return Err(StdError::generic_err(
+            "From amount is below the pair minimum.",
+        ));
+    }
+    if !pair.is_on_tick(from_amount, to_amount) {
+        return Err(StdError::generic_err(
+            "Price must be a multiple of the pair tick size.",
+        ));
+    }
     from_token_details.sum_balance += from_amount;
     write_registered_token(
         &mut deps.storage,
@@ -441,8 +1403,9 @@ fn create_order<S: Storage, A: Api, Q: Querier>(
     let mut order = Order {
         position: Uint128(contract_order_position),
         execution_fee: None,
+        execution_fee_paid: false,
         other_storage_position: Uint128(creator_order_position),
-        from_token: env.message.sender.clone(),
+        from_token,
         to_token,
         creator: creator_address.clone(),
         from_amount,
@@ -455,15 +1418,125 @@ fn create_order<S: Storage, A: Api, Q: Querier>(
         created_at_block_height: env.block.height,
     };
     append_order(&mut deps.storage, &order, &contract_address)?;
+    insert_open_order(&mut deps.storage, &order)?;
+    update_order_token_totals(&mut deps.storage, None, &order)?;
     // Store creator order next
     order.position = Uint128(creator_order_position);
     order.other_storage_position = Uint128(contract_order_position);
     append_order(&mut deps.storage, &order, &creator_address)?;
+    append_user_activity_record(
+        &mut deps.storage,
+        &creator_address,
+        ActivityRecord::new(
+            ActivityKind::Created,
+            order.position,
+            order.from_token.clone(),
+            order.to_token.clone(),
+            env.block.height,
+            env.block.time,
+        ),
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&humanize_order(
+            &deps.api,
+            &deps.storage,
+            order,
+        )?)?),
+    })
+}
+
+fn disable_token<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    address: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(
+        addresses_with_role(&deps.storage, &config, Role::TokenRegistrar),
+        &env.message.sender,
+    )?;
+
+    let token_address_canonical: CanonicalAddr = deps.api.canonical_address(&address)?;
+    let mut registered_token: RegisteredToken =
+        match read_registered_token(&deps.storage, &token_address_canonical) {
+            Some(registered_token) => registered_token,
+            None => return Err(StdError::generic_err("Token is not registered.")),
+        };
+    registered_token.disabled = true;
+    write_registered_token(
+        &mut deps.storage,
+        &token_address_canonical,
+        &registered_token,
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn execute_config_change<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    id: Uint128,
+) -> StdResult<HandleResponse> {
+    let mut config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    let pending_config_change: PendingConfigChange =
+        match read_pending_config_change(&deps.storage, id.u128()) {
+            Some(pending_config_change) => pending_config_change,
+            None => return Err(StdError::generic_err("Config change does not exist.")),
+        };
+    authorize_config_change(
+        &deps.storage,
+        &config,
+        &pending_config_change.addresses_allowed_to_fill,
+        &pending_config_change.config_change_delay_in_blocks,
+        &pending_config_change.execution_fee,
+        &pending_config_change.open_filling,
+        &env.message.sender,
+    )?;
+    if env.block.height < pending_config_change.executable_at_block_height {
+        return Err(StdError::generic_err("Config change is still timelocked."));
+    }
+
+    if let Some(mut addresses_allowed_to_fill_unwrapped) =
+        pending_config_change.addresses_allowed_to_fill
+    {
+        if !addresses_allowed_to_fill_unwrapped.contains(&env.contract.address) {
+            addresses_allowed_to_fill_unwrapped.push(env.contract.address.clone())
+        }
+        if !addresses_allowed_to_fill_unwrapped.contains(&config.admin) {
+            addresses_allowed_to_fill_unwrapped.push(config.admin.clone())
+        }
+        set_addresses_allowed_to_fill(
+            &mut deps.storage,
+            &deps.api,
+            &mut config,
+            addresses_allowed_to_fill_unwrapped,
+        )?;
+    }
+    if let Some(config_change_delay_in_blocks_unwrapped) =
+        pending_config_change.config_change_delay_in_blocks
+    {
+        config.config_change_delay_in_blocks = config_change_delay_in_blocks_unwrapped;
+    }
+    if let Some(execution_fee_unwrapped) = pending_config_change.execution_fee {
+        config.execution_fee = execution_fee_unwrapped;
+    }
+    if let Some(open_filling_unwrapped) = pending_config_change.open_filling {
+        config.open_filling = open_filling_unwrapped;
+    }
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+    remove_pending_config_change(&mut deps.storage, id.u128())?;
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![],
-        data: Some(to_binary(&order.into_humanized(&deps.api)?)?),
+        data: None,
     })
 }
 
@@ -475,7 +1548,8 @@ fn fill_order<S: Storage, A: Api, Q: Querier>(
     position: u128,
 ) -> StdResult<HandleResponse> {
     let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
-    authorize(config.addresses_allowed_to_fill, &from)?;
+    validate_not_paused(config.pause_flags.fill_order_paused(), "Filling is paused.")?;
+    authorize_filler(&deps.storage, &deps.api, &config, &from)?;
     if amount.is_zero() {
         return Err(StdError::generic_err("Amount must be greater than zero."));
     }
@@ -493,38 +1567,17 @@ fn fill_order<S: Storage, A: Api, Q: Querier>(
         &env.message.sender,
         "To token does not match the token sent in.",
     )?;
-    // Check the amount + filled amount is less than or equal to amount
-    if creator_order.cancelled {
-        return Err(StdError::generic_err("Order already cancelled."));
-    }
-    let unfilled_amount: Uint128 =
-        (creator_order.net_to_amount - creator_order.net_to_amount_filled)?;
-    if amount > unfilled_amount {
-        return Err(StdError::generic_err(
-            "Amount is greater than unfilled amount.",
-        ));
-    }
-
-    let mut address_to_send_execution_fee_to: Option<HumanAddr> = None;
-    if creator_order.from_amount_filled.is_zero() && creator_order.execution_fee.is_some() {
-        address_to_send_execution_fee_to = match read_route_state(&deps.storage)? {
-            Some(RouteState { initiator, .. }) => Some(initiator),
-            None => Some(from.clone()),
-        }
-    }
     // Update net_to_amount_filled and from_amount_filled
-    creator_order.net_to_amount_filled += amount;
-    let from_filled_amount: Uint128 =
-        if creator_order.net_to_amount_filled == creator_order.net_to_amount {
-            (creator_order.from_amount - creator_order.from_amount_filled)?
-        } else {
-            Uint128::from(
-                (U256::from(creator_order.from_amount.u128()) * U256::from(amount.u128())
-                    / U256::from(creator_order.net_to_amount.u128()))
-                .as_u128(),
-            )
-        };
-    creator_order.from_amount_filled += from_filled_amount;
+    let fill: Fill = creator_order.fill(amount)?;
+    let from_filled_amount: Uint128 = fill.from_amount;
+
+    // When filled through a route, the initiator is the filler
+    let (filler_address, routed): (HumanAddr, bool) = match read_route_state(&deps.storage)? {
+        Some(RouteState { initiator, .. }) => (initiator, true),
+        None => (from.clone(), false),
+    };
+    let address_to_send_execution_fee_to: Option<HumanAddr> =
+        fill.execution_fee.map(|_| filler_address.clone());
     update_creator_order_and_associated_contract_order(
         &mut deps.storage,
         &creator_order.creator,
@@ -563,9 +1616,9 @@ fn fill_order<S: Storage, A: Api, Q: Querier>(
             to_registered_token.address,
         )?,
     ];
-    if let Some(address_to_send_execution_fee_to_unwrapped) = address_to_send_execution_fee_to {
+    if let Some(address_to_send_execution_fee_to_unwrapped) = &address_to_send_execution_fee_to {
         messages.push(snip20::transfer_msg(
-            address_to_send_execution_fee_to_unwrapped,
+            address_to_send_execution_fee_to_unwrapped.clone(),
             creator_order.execution_fee.unwrap(),
             None,
             BLOCK_SIZE,
@@ -590,12 +1643,23 @@ fn fill_order<S: Storage, A: Api, Q: Querier>(
             &admin_canonical_address,
             PREFIX_FILL_RECORDS_COUNT,
         )?),
-        order_position: creator_order.other_storage_position,
-        activity: 1,
         result_from_amount_filled: Some(creator_order.from_amount_filled),
         result_net_to_amount_filled: Some(creator_order.net_to_amount_filled),
-        updated_at_block_height: env.block.height,
-        updated_at_block_time: env.block.time,
+        from_amount_filled_delta: Some(from_filled_amount),
+        net_to_amount_filled_delta: Some(amount),
+        price: calculate_price(from_filled_amount, amount),
+        fee: Some(fill.fee),
+        execution_fee_paid: fill.execution_fee,
+        filler: Some(filler_address.clone()),
+        routed,
+        ..ActivityRecord::new(
+            ActivityKind::Filled,
+            creator_order.other_storage_position,
+            creator_order.from_token.clone(),
+            creator_order.to_token.clone(),
+            env.block.height,
+            env.block.time,
+        )
     };
     append_activity_record(
         &mut deps.storage,
@@ -604,11 +1668,345 @@ fn fill_order<S: Storage, A: Api, Q: Querier>(
         PREFIX_FILL_RECORDS,
     )?;
 
-    Ok(HandleResponse {
-        messages,
-        log: vec![],
-        data: None,
-    })
+    // Update filler stats
+    let filler_canonical_address: CanonicalAddr = deps.api.canonical_address(&filler_address)?;
+    let mut filler: Filler =
+        read_or_register_filler(&mut deps.storage, &filler_canonical_address, filler_address)?;
+    filler.total_fills += Uint128(1);
+    filler.last_active_block_height = env.block.height;
+    if address_to_send_execution_fee_to.is_some() {
+        filler.execution_fees_earned += creator_order.execution_fee.unwrap();
+    }
+    filler.add_volume(&creator_order.to_token, amount)?;
+    filler.add_volume(&creator_order.from_token, from_filled_amount)?;
+    write_filler(&mut deps.storage, &filler_canonical_address, &filler)?;
+
+    // Create activity record for the creator
+    append_user_activity_record(
+        &mut deps.storage,
+        &creator_order.creator,
+        ActivityRecord {
+            order_position: creator_order.position,
+            filler: if filler.address_public {
+                activity_record.filler
+            } else {
+                None
+            },
+            ..activity_record
+        },
+    )?;
+
+    // Update pair stats
+    let from_token_canonical_address: CanonicalAddr =
+        deps.api.canonical_address(&creator_order.from_token)?;
+    let to_token_canonical_address: CanonicalAddr =
+        deps.api.canonical_address(&creator_order.to_token)?;
+    let mut pair_stats: PairStats = read_pair_stats(
+        &deps.storage,
+        &from_token_canonical_address,
+        &to_token_canonical_address,
+    )
+    .unwrap_or(PairStats {
+        from_token: creator_order.from_token.clone(),
+        to_token: creator_order.to_token.clone(),
+        ..PairStats::default()
+    });
+    pair_stats.add_fill(from_filled_amount, amount, env.block.time)?;
+    write_pair_stats(
+        &mut deps.storage,
+        &from_token_canonical_address,
+        &to_token_canonical_address,
+        &pair_stats,
+    )?;
+    for interval in config.candle_intervals.iter() {
+        update_candle(
+            &mut deps.storage,
+            &from_token_canonical_address,
+            &to_token_canonical_address,
+            *interval,
+            from_filled_amount,
+            amount,
+            env.block.time,
+        )?;
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+// Pages through the state needed to recreate the contract on a new instance, in position order
+fn export_state<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    key: String,
+    section: StateSection,
+    pagination: Option<Pagination>,
+) -> StdResult<Binary> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    // This is here to check the admin's viewing key
+    query_balance_of_token(deps, config.admin.clone(), config.butt.clone(), key)?;
+
+    let page: Page = Page::new(pagination, SortOrder::Ascending);
+    let (state_page, total, next_start_after): (StatePage, u128, Option<u128>) = match section {
+        StateSection::Activity { .. } | StateSection::CancelRecords | StateSection::FillRecords => {
+            let (for_address, storage_prefix): (HumanAddr, &[u8]) = match &section {
+                StateSection::Activity { address } => (address.clone(), PREFIX_ACTIVITY_RECORDS),
+                StateSection::CancelRecords => (config.admin.clone(), PREFIX_CANCEL_RECORDS),
+                _ => (config.admin.clone(), PREFIX_FILL_RECORDS),
+            };
+            let for_address_canonical: CanonicalAddr = deps.api.canonical_address(&for_address)?;
+            let total: u128 = storage_count(
+                &deps.storage,
+                &for_address_canonical,
+                prefix_activity_records_count(storage_prefix),
+            )?;
+            let (positions, next_start_after) = page.positions(0, total);
+            let store = ReadonlyPrefixedStorage::multilevel(
+                &[storage_prefix, for_address_canonical.as_slice()],
+                &deps.storage,
+            );
+            let store = TypedStore::<ActivityRecord, _>::attach(&store);
+            let mut activity_records: Vec<ActivityRecord> = vec![];
+            for position in positions {
+                activity_records.push(store.load(&position.to_le_bytes())?);
+            }
+            let state_page: StatePage = match section {
+                StateSection::Activity { address } => StatePage::Activity {
+                    address,
+                    activity_records,
+                },
+                StateSection::CancelRecords => StatePage::CancelRecords {
+                    cancel_records: activity_records,
+                },
+                _ => StatePage::FillRecords {
+                    fill_records: activity_records,
+                },
+            };
+            (state_page, total, next_start_after)
+        }
+        StateSection::Candles { pair, interval } => {
+            let from_token_address_canonical: CanonicalAddr =
+                deps.api.canonical_address(&pair.from_token)?;
+            let to_token_address_canonical: CanonicalAddr =
+                deps.api.canonical_address(&pair.to_token)?;
+            let total: u128 = read_candles_count(
+                &deps.storage,
+                &from_token_address_canonical,
+                &to_token_address_canonical,
+                interval,
+            ) as u128;
+            let (positions, next_start_after) = page.positions(0, total);
+            let mut candles: Vec<Candle> = vec![];
+            for position in positions {
+                candles.push(
+                    read_candle(
+                        &deps.storage,
+                        &from_token_address_canonical,
+                        &to_token_address_canonical,
+                        interval,
+                        position as u64,
+                    )
+                    .unwrap(),
+                );
+            }
+            (
+                StatePage::Candles {
+                    pair,
+                    interval,
+                    candles,
+                },
+                total,
+                next_start_after,
+            )
+        }
+        StateSection::Commitments => {
+            let total: u128 = read_commitments_count(&deps.storage);
+            let (positions, next_start_after) = page.positions(0, total);
+            let commitments: Vec<Commitment> = positions
+                .into_iter()
+                .map(|position| read_commitment(&deps.storage, position).unwrap())
+                .collect();
+            (
+                StatePage::Commitments { commitments },
+                total,
+                next_start_after,
+            )
+        }
+        StateSection::Fillers => {
+            let total: u128 = read_fillers_count(&deps.storage);
+            let (positions, next_start_after) = page.positions(0, total);
+            let mut fillers: Vec<Filler> = vec![];
+            for position in positions {
+                let address: HumanAddr = read_filler_address(&deps.storage, position).unwrap();
+                fillers.push(
+                    read_filler(&deps.storage, &deps.api.canonical_address(&address)?).unwrap(),
+                );
+            }
+            (StatePage::Fillers { fillers }, total, next_start_after)
+        }
+        StateSection::Orders => {
+            let contract_canonical_address: CanonicalAddr = deps
+                .api
+                .canonical_address(&read_contract_address(&deps.storage)?)?;
+            let total: u128 = storage_count(
+                &deps.storage,
+                &contract_canonical_address,
+                PREFIX_ORDERS_COUNT,
+            )?;
+            let (positions, next_start_after) = page.positions(0, total);
+            let mut orders: Vec<Order> = vec![];
+            for position in positions {
+                orders.push(order_at_position(
+                    &deps.storage,
+                    &contract_canonical_address,
+                    position,
+                )?);
+            }
+            (StatePage::Orders { orders }, total, next_start_after)
+        }
+        StateSection::PairStats => {
+            let pair_token_addresses: Vec<(HumanAddr, HumanAddr)> =
+                read_pair_token_addresses(&deps.storage);
+            let total: u128 = pair_token_addresses.len() as u128;
+            let (positions, next_start_after) = page.positions(0, total);
+            let mut pair_stats: Vec<PairStats> = vec![];
+            for position in positions {
+                let (from_token, to_token) = &pair_token_addresses[position as usize];
+                pair_stats.push(
+                    read_pair_stats(
+                        &deps.storage,
+                        &deps.api.canonical_address(from_token)?,
+                        &deps.api.canonical_address(to_token)?,
+                    )
+                    .unwrap_or(PairStats {
+                        from_token: from_token.clone(),
+                        to_token: to_token.clone(),
+                        ..PairStats::default()
+                    }),
+                );
+            }
+            (StatePage::PairStats { pair_stats }, total, next_start_after)
+        }
+        StateSection::RegisteredTokens => {
+            let registered_token_addresses: Vec<HumanAddr> =
+                read_registered_token_addresses(&deps.storage);
+            let total: u128 = registered_token_addresses.len() as u128;
+            let (positions, next_start_after) = page.positions(0, total);
+            let mut registered_tokens: Vec<RegisteredToken> = vec![];
+            for position in positions {
+                let address: &HumanAddr = &registered_token_addresses[position as usize];
+                registered_tokens.push(
+                    read_registered_token(&deps.storage, &deps.api.canonical_address(address)?)
+                        .unwrap(),
+                );
+            }
+            (
+                StatePage::RegisteredTokens { registered_tokens },
+                total,
+                next_start_after,
+            )
+        }
+    };
+
+    let token_addresses: Vec<&HumanAddr> = match &state_page {
+        StatePage::Activity {
+            activity_records, ..
+        }
+        | StatePage::CancelRecords {
+            cancel_records: activity_records,
+        }
+        | StatePage::FillRecords {
+            fill_records: activity_records,
+        } => activity_records
+            .iter()
+            .flat_map(|activity_record| {
+                vec![&activity_record.from_token, &activity_record.to_token]
+            })
+            .collect(),
+        StatePage::Candles { pair, .. } => vec![&pair.from_token, &pair.to_token],
+        StatePage::Commitments { commitments } => commitments
+            .iter()
+            .map(|commitment| &commitment.from_token)
+            .collect(),
+        StatePage::Fillers { fillers } => filler_token_addresses(&config, &fillers),
+        StatePage::Orders { orders } => orders
+            .iter()
+            .flat_map(|order| vec![&order.from_token, &order.to_token])
+            .collect(),
+        StatePage::PairStats { pair_stats } => pair_stats
+            .iter()
+            .flat_map(|pair_stats| vec![&pair_stats.from_token, &pair_stats.to_token])
+            .collect(),
+        StatePage::RegisteredTokens { registered_tokens } => registered_tokens
+            .iter()
+            .map(|registered_token| &registered_token.address)
+            .collect(),
+    };
+    let tokens: Vec<TokenMetadata> = token_metadata(&deps.storage, &deps.api, token_addresses)?;
+    let result = QueryAnswer::ExportState {
+        state_page,
+        total: Uint128(total),
+        next_start_after: next_start_after.map(Uint128),
+        tokens,
+    };
+    to_binary(&result)
+}
+
+fn filler_stats<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+) -> StdResult<Binary> {
+    let filler: Filler = match read_filler(&deps.storage, &deps.api.canonical_address(&address)?) {
+        Some(filler) => filler,
+        None => return Err(StdError::generic_err("Filler does not exist.")),
+    };
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    let tokens: Vec<TokenMetadata> = token_metadata(
+        &deps.storage,
+        &deps.api,
+        filler_token_addresses(&config, &[filler.clone()]),
+    )?;
+
+    to_binary(&QueryAnswer::FillerStats { filler, tokens })
+}
+
+// Bonds are in BUTT, execution fees are in SSCRT and volumes are in each token traded
+fn filler_token_addresses<'a>(config: &'a Config, fillers: &'a [Filler]) -> Vec<&'a HumanAddr> {
+    let mut token_addresses: Vec<&HumanAddr> = vec![&config.butt.address, &config.sscrt.address];
+    for filler in fillers {
+        token_addresses.extend(filler.volumes.iter().map(|volume| &volume.token));
+    }
+    token_addresses
+}
+
+fn fillers<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pagination: Option<Pagination>,
+) -> StdResult<Binary> {
+    let total: u128 = read_fillers_count(&deps.storage);
+    let (positions, next_start_after) =
+        Page::new(pagination, SortOrder::Ascending).positions(0, total);
+    let mut fillers: Vec<Filler> = vec![];
+    for position in positions {
+        let address: HumanAddr = read_filler_address(&deps.storage, position).unwrap();
+        fillers.push(read_filler(&deps.storage, &deps.api.canonical_address(&address)?).unwrap());
+    }
+
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    let tokens: Vec<TokenMetadata> = token_metadata(
+        &deps.storage,
+        &deps.api,
+        filler_token_addresses(&config, &fillers),
+    )?;
+    let result = QueryAnswer::Fillers {
+        fillers,
+        total: Some(Uint128(total)),
+        next_start_after: next_start_after.map(Uint128),
+        tokens,
+    };
+    to_binary(&result)
 }
 
 fn finalize_route<S: Storage, A: Api, Q: Querier>(
@@ -637,52 +2035,352 @@ fn finalize_route<S: Storage, A: Api, Q: Querier>(
     }
 }
 
+// Walks the contract's orders from where the last call left off and
+// refunds every open order that trades from or to the token
+fn force_cancel_orders<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    token: HumanAddr,
+    limit: u128,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(vec![config.admin], &env.message.sender)?;
+
+    let token_address_canonical: CanonicalAddr = deps.api.canonical_address(&token)?;
+    let contract_canonical_address: CanonicalAddr =
+        deps.api.canonical_address(&env.contract.address)?;
+    let total: u128 = storage_count(
+        &deps.storage,
+        &contract_canonical_address,
+        PREFIX_ORDERS_COUNT,
+    )?;
+    let start: u128 = storage_count(
+        &deps.storage,
+        &token_address_canonical,
+        PREFIX_FORCE_CANCEL_CURSORS,
+    )?;
+    let end: u128 = start.saturating_add(limit).min(total);
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut cancelled: u128 = 0;
+    for position in start..end {
+        let contract_order: Order =
+            order_at_position(&deps.storage, &contract_canonical_address, position)?;
+        if contract_order.cancelled
+            || contract_order.from_amount == contract_order.from_amount_filled
+            || (contract_order.from_token != token && contract_order.to_token != token)
+        {
+            continue;
+        }
+
+        let mut creator_order: Order = contract_order;
+        creator_order.position = creator_order.other_storage_position;
+        creator_order.other_storage_position = Uint128(position);
+        messages.append(&mut refund_and_cancel_order(
+            deps,
+            env,
+            &mut creator_order,
+            true,
+        )?);
+        cancelled += 1;
+    }
+    set_count(
+        &mut deps.storage,
+        &token_address_canonical,
+        PREFIX_FORCE_CANCEL_CURSORS,
+        end,
+    )?;
+
+    pad_response(Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ForceCancelOrders {
+            cancelled: Uint128(cancelled),
+            next_position: Uint128(end),
+            complete: end == total,
+        })?),
+    }))
+}
+
 fn get_activity_records<S: ReadonlyStorage>(
     storage: &S,
     for_address: &CanonicalAddr,
-    page: u128,
-    page_size: u128,
+    page: &Page,
     storage_prefix: &[u8],
-) -> StdResult<(Vec<ActivityRecord>, u128)> {
+) -> StdResult<(Vec<ActivityRecord>, u128, Option<u128>)> {
     let total: u128 = storage_count(
         storage,
         for_address,
         prefix_activity_records_count(storage_prefix),
     )?;
-    let offset: u128 = page * page_size;
-    let end = total - offset;
-    let start = end.saturating_sub(page_size);
+    let (positions, next_start_after) = page.positions(0, total);
     let store =
         ReadonlyPrefixedStorage::multilevel(&[storage_prefix, for_address.as_slice()], storage);
     let mut activity_records: Vec<ActivityRecord> = Vec::new();
     let store = TypedStore::<ActivityRecord, _>::attach(&store);
-    for position in (start..end).rev() {
+    for position in positions {
         activity_records.push(store.load(&position.to_le_bytes())?);
     }
 
-    Ok((activity_records, total))
+    Ok((activity_records, total, next_start_after))
+}
+
+// Records are appended in block order, so block height and time ranges are found with binary
+// searches. Kind and pair filters page over their index, which also lists positions in order.
+// Records of an order are read through the order's index and checked against the other filters.
+fn get_filtered_activity_records<S: ReadonlyStorage>(
+    storage: &S,
+    for_address: &CanonicalAddr,
+    filters: &ActivityFilters,
+    page: &Page,
+    storage_prefix: &[u8],
+) -> StdResult<(Vec<ActivityRecord>, u128, Option<u128>)> {
+    let count: u128 = storage_count(
+        storage,
+        for_address,
+        prefix_activity_records_count(storage_prefix),
+    )?;
+    let store =
+        ReadonlyPrefixedStorage::multilevel(&[storage_prefix, for_address.as_slice()], storage);
+    let store = TypedStore::<ActivityRecord, _>::attach(&store);
+    let record_partition_point = |is_after: &dyn Fn(&ActivityRecord) -> bool| -> StdResult<u128> {
+        partition_point(0, count, &|position| {
+            Ok(is_after(&store.load(&position.to_le_bytes())?))
+        })
+    };
+    let mut start: u128 = 0;
+    let mut end: u128 = count;
+    if let Some(from_block_height) = filters.from_block_height {
+        start = start.max(record_partition_point(&|record| {
+            record.updated_at_block_height >= from_block_height
+        })?);
+    }
+    if let Some(to_block_height) = filters.to_block_height {
+        end = end.min(record_partition_point(&|record| {
+            record.updated_at_block_height > to_block_height
+        })?);
+    }
+    if let Some(from_block_time) = filters.from_block_time {
+        start = start.max(record_partition_point(&|record| {
+            record.updated_at_block_time >= from_block_time
+        })?);
+    }
+    if let Some(to_block_time) = filters.to_block_time {
+        end = end.min(record_partition_point(&|record| {
+            record.updated_at_block_time > to_block_time
+        })?);
+    }
+    let end: u128 = end.max(start);
+    let mut activity_records: Vec<ActivityRecord> = vec![];
+    if filters.activity.is_none() && filters.order_position.is_none() && filters.pair.is_none() {
+        let (positions, next_start_after) = page.positions(start, end);
+        for position in positions {
+            activity_records.push(store.load(&position.to_le_bytes())?);
+        }
+        return Ok((activity_records, end - start, next_start_after));
+    }
+
+    if filters.order_position.is_none() {
+        let key: Vec<u8> = activity_records_index_key(
+            filters.activity,
+            filters
+                .pair
+                .as_ref()
+                .map(|pair| (&pair.from_token, &pair.to_token)),
+            None,
+        )?;
+        let index_count: u128 =
+            activity_records_index_count(storage, for_address, storage_prefix, &key);
+        let record_position = |index_position: u128| -> StdResult<u128> {
+            activity_records_index_position(
+                storage,
+                for_address,
+                storage_prefix,
+                &key,
+                index_position,
+            )
+        };
+        let index_start: u128 = partition_point(0, index_count, &|index_position| {
+            Ok(record_position(index_position)? >= start)
+        })?;
+        let index_end: u128 = partition_point(index_start, index_count, &|index_position| {
+            Ok(record_position(index_position)? >= end)
+        })?;
+        // The cursor is a record position, so the page starts after it in the index
+        let (mut low, mut high): (u128, u128) = (index_start, index_end);
+        match (page.start_after, page.order) {
+            (Some(start_after), SortOrder::Ascending) => {
+                low = partition_point(low, high, &|index_position| {
+                    Ok(record_position(index_position)? > start_after)
+                })?
+            }
+            (Some(start_after), SortOrder::Descending) => {
+                high = partition_point(low, high, &|index_position| {
+                    Ok(record_position(index_position)? >= start_after)
+                })?
+            }
+            (None, _) => {}
+        }
+        let index_page: Page = Page {
+            start_after: None,
+            ..*page
+        };
+        let (index_positions, next_index_start_after) = index_page.positions(low, high);
+        for index_position in index_positions {
+            activity_records.push(store.load(&record_position(index_position)?.to_le_bytes())?);
+        }
+        let next_start_after: Option<u128> = match next_index_start_after {
+            Some(index_position) => Some(record_position(index_position)?),
+            None => None,
+        };
+        return Ok((activity_records, index_end - index_start, next_start_after));
+    }
+
+    // An order only has a few records, so they are checked against the other filters one by one
+    let order_position: Uint128 = filters.order_position.unwrap();
+    let mut positions: Vec<u128> = if storage_prefix == PREFIX_ACTIVITY_RECORDS {
+        order_activity_record_positions(storage, for_address, order_position.u128())
+    } else {
+        let key: Vec<u8> = activity_records_index_key(None, None, Some(order_position))?;
+        (0..activity_records_index_count(storage, for_address, storage_prefix, &key))
+            .map(|index_position| {
+                activity_records_index_position(
+                    storage,
+                    for_address,
+                    storage_prefix,
+                    &key,
+                    index_position,
+                )
+            })
+            .collect::<StdResult<Vec<u128>>>()?
+    };
+    positions.retain(|position| *position >= start && *position < end);
+    if page.order == SortOrder::Descending {
+        positions.reverse();
+    }
+    let mut total: u128 = 0;
+    let mut last_position: Option<u128> = None;
+    let mut has_more: bool = false;
+    for position in positions {
+        let activity_record: ActivityRecord = store.load(&position.to_le_bytes())?;
+        if filters.activity.is_some() && filters.activity != Some(activity_record.activity) {
+            continue;
+        }
+        if let Some(pair) = &filters.pair {
+            if activity_record.from_token != pair.from_token
+                || activity_record.to_token != pair.to_token
+            {
+                continue;
+            }
+        }
+        total += 1;
+        if !page.is_after_cursor(position) {
+            continue;
+        }
+        if (activity_records.len() as u128) < page.limit {
+            activity_records.push(activity_record);
+            last_position = Some(position);
+        } else {
+            has_more = true;
+        }
+    }
+
+    Ok((activity_records, total, last_position.filter(|_| has_more)))
 }
 
-fn get_orders<A: Api, S: ReadonlyStorage>(
+fn get_orders<A: Api, S: Storage>(
     api: &A,
     storage: &S,
     for_address: &CanonicalAddr,
-    page: u128,
-    page_size: u128,
-) -> StdResult<(Vec<HumanizedOrder>, u128)> {
+    page: &Page,
+) -> StdResult<(Vec<HumanizedOrder>, u128, Option<u128>)> {
     let total: u128 = storage_count(storage, for_address, PREFIX_ORDERS_COUNT)?;
-    let offset: u128 = page * page_size;
-    let end = total - offset;
-    let start = end.saturating_sub(page_size);
+    let (positions, next_start_after) = page.positions(0, total);
     let store =
         ReadonlyPrefixedStorage::multilevel(&[PREFIX_ORDERS, for_address.as_slice()], storage);
     let mut orders: Vec<HumanizedOrder> = Vec::new();
     let store = TypedStore::<Order, _>::attach(&store);
-    for position in (start..end).rev() {
-        orders.push(store.load(&position.to_le_bytes())?.into_humanized(api)?);
+    for position in positions {
+        orders.push(humanize_order(
+            api,
+            storage,
+            store.load(&position.to_le_bytes())?,
+        )?);
+    }
+
+    Ok((orders, total, next_start_after))
+}
+
+fn grant_role<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    role: Role,
+    address: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(vec![config.admin], &env.message.sender)?;
+
+    let mut addresses: Vec<HumanAddr> = read_role_addresses(&deps.storage, role);
+    if !addresses.contains(&address) {
+        addresses.push(address);
+        write_role_addresses(&mut deps.storage, role, &addresses)?;
+    }
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+// Moves the escrowed balances to the new instance once the contract is withdraw only.
+// The whole balance is moved so that escrowed execution fees go with it.
+fn hand_over_escrow<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    key: String,
+    recipient: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(vec![config.admin], &env.message.sender)?;
+    if !config.pause_flags.withdraw_only {
+        return Err(StdError::generic_err("Contract must be withdraw only."));
+    }
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut balances: Vec<TokenAmount> = vec![];
+    for address in read_registered_token_addresses(&deps.storage).iter() {
+        let registered_token: RegisteredToken =
+            read_registered_token(&deps.storage, &deps.api.canonical_address(address)?).unwrap();
+        let balance: Uint128 = query_escrow_balance(deps, env, &registered_token, key.clone())?;
+        if !balance.is_zero() {
+            messages.push(snip20::transfer_msg(
+                recipient.clone(),
+                balance,
+                None,
+                BLOCK_SIZE,
+                registered_token.contract_hash,
+                registered_token.address.clone(),
+            )?);
+        }
+        balances.push(TokenAmount {
+            token: registered_token.address,
+            amount: balance,
+        });
     }
+    let tokens: Vec<TokenMetadata> = token_metadata(
+        &deps.storage,
+        &deps.api,
+        balances.iter().map(|balance| &balance.token).collect(),
+    )?;
 
-    Ok((orders, total))
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::HandOverEscrow {
+            balances,
+            tokens,
+        })?),
+    })
 }
 
 fn handle_first_hop<S: Storage, A: Api, Q: Querier>(
@@ -697,7 +2395,8 @@ fn handle_first_hop<S: Storage, A: Api, Q: Querier>(
     // 2. send `amount` X to pair X/Y
     // 3. call FinalizeRoute to make sure everything went ok, otherwise revert the tx
     let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
-    authorize(config.addresses_allowed_to_fill, &env.message.sender)?;
+    validate_not_paused(config.pause_flags.route_paused(), "Routing is paused.")?;
+    authorize_filler(&deps.storage, &deps.api, &config, &env.message.sender)?;
     if hops.len() != 2 {
         return Err(StdError::generic_err("Route must be 2 hops."));
     }
@@ -809,16 +2508,12 @@ fn handle_hop<S: Storage, A: Api, Q: Querier>(
                 // only send in the unfilled amount
                 // we can rescue the dust later when worthwhile while making gas more predictable
                 if next_hop.trade_smart_contract.address == env.contract.address {
-                    let next_trade_order = order_at_position(
+                    amount = clamp_to_unfilled_amount(
                         &deps.storage,
                         &deps.api.canonical_address(&env.contract.address)?,
                         next_hop.position.unwrap().u128(),
+                        amount,
                     )?;
-                    let unfilled_amount =
-                        (next_trade_order.net_to_amount - next_trade_order.net_to_amount_filled)?;
-                    if amount.gt(&unfilled_amount) {
-                        amount = unfilled_amount
-                    }
                 }
                 messages.push(snip20::send_msg(
                     next_hop.trade_smart_contract.address.clone(),
@@ -862,6 +2557,18 @@ fn handle_hop<S: Storage, A: Api, Q: Querier>(
                         borrow_token.address.clone(),
                     )?);
                 }
+
+                // Update initiator stats
+                let initiator_canonical_address: CanonicalAddr =
+                    deps.api.canonical_address(&initiator)?;
+                let mut filler: Filler = read_or_register_filler(
+                    &mut deps.storage,
+                    &initiator_canonical_address,
+                    initiator.clone(),
+                )?;
+                filler.total_routes += Uint128(1);
+                filler.last_active_block_height = env.block.height;
+                write_filler(&mut deps.storage, &initiator_canonical_address, &filler)?;
             }
             store_route_state(
                 &mut deps.storage,
@@ -886,41 +2593,391 @@ fn handle_hop<S: Storage, A: Api, Q: Querier>(
     }
 }
 
-fn order_at_position<S: Storage>(
-    store: &S,
-    address: &CanonicalAddr,
-    position: u128,
-) -> StdResult<Order> {
-    let store = ReadonlyPrefixedStorage::multilevel(&[PREFIX_ORDERS, address.as_slice()], store);
-    // Try to access the storage of orders for the account.
-    // If it doesn't exist yet, return an empty list of transfers.
-    let store = TypedStore::<Order, _>::attach(&store);
-
-    store.load(&position.to_le_bytes())
+// Orders are humanized with the decimals of their tokens so that the limit price can be given
+fn humanize_order<A: Api, S: Storage>(
+    api: &A,
+    storage: &S,
+    order: Order,
+) -> StdResult<HumanizedOrder> {
+    let token_decimals = |token_address: &HumanAddr| -> StdResult<Option<u8>> {
+        Ok(
+            read_registered_token(storage, &api.canonical_address(token_address)?)
+                .and_then(|registered_token| registered_token.decimals),
+        )
+    };
+    let from_decimals: Option<u8> = token_decimals(&order.from_token)?;
+    let to_decimals: Option<u8> = token_decimals(&order.to_token)?;
+    order.into_humanized(api, from_decimals, to_decimals)
 }
 
-fn orders<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    address: HumanAddr,
-    key: String,
-    page: u128,
-    page_size: u128,
-) -> StdResult<Binary> {
-    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
-    // This is here so that the user can use their viewing key for butt for this
-    query_balance_of_token(deps, address.clone(), config.butt, key)?;
+fn import_activity_records<S: Storage>(
+    storage: &mut S,
+    for_address: &CanonicalAddr,
+    activity_records: &[ActivityRecord],
+    storage_prefix: &[u8],
+) -> StdResult<()> {
+    for activity_record in activity_records.iter() {
+        let next_position: u128 = storage_count(
+            storage,
+            for_address,
+            prefix_activity_records_count(storage_prefix),
+        )?;
+        if activity_record.position.u128() != next_position {
+            return Err(StdError::generic_err(
+                "Activity records must be imported in order.",
+            ));
+        }
+        append_activity_record(storage, activity_record, for_address, storage_prefix)?;
+    }
 
-    let (orders, total) = get_orders(
-        &deps.api,
+    Ok(())
+}
+
+// Imports a page of exported state into a fresh instance.
+// Pages have to be imported in position order so that positions line up.
+fn import_state<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    state_page: StatePage,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(vec![config.admin.clone()], &env.message.sender)?;
+    if read_import_complete(&deps.storage) {
+        return Err(StdError::generic_err("Import is complete."));
+    }
+
+    let admin_canonical_address: CanonicalAddr = deps.api.canonical_address(&config.admin)?;
+    match state_page {
+        StatePage::Activity {
+            address,
+            activity_records,
+        } => {
+            let address_canonical: CanonicalAddr = deps.api.canonical_address(&address)?;
+            for activity_record in activity_records {
+                let next_position: u128 = storage_count(
+                    &deps.storage,
+                    &address_canonical,
+                    PREFIX_ACTIVITY_RECORDS_COUNT,
+                )?;
+                if activity_record.position.u128() != next_position {
+                    return Err(StdError::generic_err(
+                        "Activity records must be imported in order.",
+                    ));
+                }
+                append_user_activity_record(
+                    &mut deps.storage,
+                    &address_canonical,
+                    activity_record,
+                )?;
+            }
+        }
+        StatePage::CancelRecords { cancel_records } => import_activity_records(
+            &mut deps.storage,
+            &admin_canonical_address,
+            &cancel_records,
+            PREFIX_CANCEL_RECORDS,
+        )?,
+        StatePage::Candles {
+            pair,
+            interval,
+            candles,
+        } => {
+            let from_token_address_canonical: CanonicalAddr =
+                deps.api.canonical_address(&pair.from_token)?;
+            let to_token_address_canonical: CanonicalAddr =
+                deps.api.canonical_address(&pair.to_token)?;
+            if read_pair(
+                &deps.storage,
+                &from_token_address_canonical,
+                &to_token_address_canonical,
+            )
+            .is_none()
+            {
+                return Err(StdError::generic_err("Pair is not registered."));
+            }
+            let mut candles_count: u64 = read_candles_count(
+                &deps.storage,
+                &from_token_address_canonical,
+                &to_token_address_canonical,
+                interval,
+            );
+            let mut last_start_time: Option<u64> = candles_count
+                .checked_sub(1)
+                .and_then(|position| {
+                    read_candle(
+                        &deps.storage,
+                        &from_token_address_canonical,
+                        &to_token_address_canonical,
+                        interval,
+                        position,
+                    )
+                })
+                .map(|candle| candle.start_time);
+            for candle in candles {
+                if matches!(last_start_time, Some(start_time) if candle.start_time <= start_time) {
+                    return Err(StdError::generic_err("Candles must be imported in order."));
+                }
+                write_candle(
+                    &mut deps.storage,
+                    &from_token_address_canonical,
+                    &to_token_address_canonical,
+                    interval,
+                    candles_count,
+                    &candle,
+                )?;
+                candles_count += 1;
+                last_start_time = Some(candle.start_time);
+            }
+            write_candles_count(
+                &mut deps.storage,
+                &from_token_address_canonical,
+                &to_token_address_canonical,
+                interval,
+                candles_count,
+            )?;
+        }
+        StatePage::Commitments { commitments } => {
+            for commitment in commitments {
+                let commitments_count: u128 = read_commitments_count(&deps.storage);
+                if commitment.position.u128() != commitments_count {
+                    return Err(StdError::generic_err(
+                        "Commitments must be imported in order.",
+                    ));
+                }
+                write_commitment(&mut deps.storage, &commitment)?;
+                write_commitments_count(&mut deps.storage, commitments_count + 1)?;
+                if !commitment.closed {
+                    insert_open_commitment(&mut deps.storage, &commitment)?;
+                    let mut from_token_totals: TokenTotals =
+                        read_token_totals(&deps.storage, &commitment.from_token);
+                    from_token_totals.committed_amount += commitment.from_amount;
+                    write_token_totals(
+                        &mut deps.storage,
+                        &commitment.from_token,
+                        &from_token_totals,
+                    )?;
+                }
+            }
+        }
+        StatePage::FillRecords { fill_records } => import_activity_records(
+            &mut deps.storage,
+            &admin_canonical_address,
+            &fill_records,
+            PREFIX_FILL_RECORDS,
+        )?,
+        StatePage::Fillers { fillers } => {
+            let mut butt_totals: TokenTotals =
+                read_token_totals(&deps.storage, &config.butt.address);
+            for filler in fillers {
+                let filler_address_canonical: CanonicalAddr =
+                    deps.api.canonical_address(&filler.address)?;
+                if read_filler(&deps.storage, &filler_address_canonical).is_some() {
+                    return Err(StdError::generic_err("Filler is already imported."));
+                }
+                read_or_register_filler(
+                    &mut deps.storage,
+                    &filler_address_canonical,
+                    filler.address.clone(),
+                )?;
+                write_filler(&mut deps.storage, &filler_address_canonical, &filler)?;
+                butt_totals.bonded_amount += filler.bonded_amount + filler.unbonding_amount;
+            }
+            write_token_totals(&mut deps.storage, &config.butt.address, &butt_totals)?;
+        }
+        StatePage::Orders { orders } => {
+            let contract_canonical_address: CanonicalAddr =
+                deps.api.canonical_address(&env.contract.address)?;
+            for mut order in orders {
+                let contract_order_position: u128 = storage_count(
+                    &deps.storage,
+                    &contract_canonical_address,
+                    PREFIX_ORDERS_COUNT,
+                )?;
+                let creator_order_position: u128 =
+                    storage_count(&deps.storage, &order.creator, PREFIX_ORDERS_COUNT)?;
+                if order.position.u128() != contract_order_position
+                    || order.other_storage_position.u128() != creator_order_position
+                {
+                    return Err(StdError::generic_err("Orders must be imported in order."));
+                }
+                append_order(&mut deps.storage, &order, &contract_canonical_address)?;
+                if order.is_open() {
+                    insert_open_order(&mut deps.storage, &order)?;
+                    update_order_token_totals(&mut deps.storage, None, &order)?;
+                }
+                order.position = Uint128(creator_order_position);
+                order.other_storage_position = Uint128(contract_order_position);
+                append_order(&mut deps.storage, &order, &order.creator.clone())?;
+            }
+        }
+        StatePage::PairStats { pair_stats } => {
+            // Pairs are set as usual first
+            for pair_stats in pair_stats {
+                let from_token_address_canonical: CanonicalAddr =
+                    deps.api.canonical_address(&pair_stats.from_token)?;
+                let to_token_address_canonical: CanonicalAddr =
+                    deps.api.canonical_address(&pair_stats.to_token)?;
+                if read_pair(
+                    &deps.storage,
+                    &from_token_address_canonical,
+                    &to_token_address_canonical,
+                )
+                .is_none()
+                {
+                    return Err(StdError::generic_err("Pair is not registered."));
+                }
+                write_pair_stats(
+                    &mut deps.storage,
+                    &from_token_address_canonical,
+                    &to_token_address_canonical,
+                    &pair_stats,
+                )?;
+            }
+        }
+        StatePage::RegisteredTokens { registered_tokens } => {
+            // Tokens are registered as usual first so that the viewing key and receive are set up
+            for registered_token in registered_tokens {
+                let token_address_canonical: CanonicalAddr =
+                    deps.api.canonical_address(&registered_token.address)?;
+                if read_registered_token(&deps.storage, &token_address_canonical).is_none() {
+                    return Err(StdError::generic_err("Token is not registered."));
+                }
+                write_registered_token(
+                    &mut deps.storage,
+                    &token_address_canonical,
+                    &registered_token,
+                )?;
+            }
+        }
+    }
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn my_activity<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    key: String,
+    filters: ActivityFilters,
+    pagination: Option<Pagination>,
+) -> StdResult<Binary> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    // This is here so that the user can use their viewing key for butt for this
+    query_balance_of_token(deps, address.clone(), config.butt, key)?;
+
+    user_activity_records(deps, address, filters, pagination)
+}
+
+fn order_activity_record_positions<S: ReadonlyStorage>(
+    storage: &S,
+    for_address: &CanonicalAddr,
+    order_position: u128,
+) -> Vec<u128> {
+    let store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_ORDER_ACTIVITY_RECORDS, for_address.as_slice()],
+        storage,
+    );
+    let store = TypedStore::<Vec<u128>, _>::attach(&store);
+    store
+        .may_load(&order_position.to_le_bytes())
+        .unwrap()
+        .unwrap_or_default()
+}
+
+fn order_at_position<S: Storage>(
+    store: &S,
+    address: &CanonicalAddr,
+    position: u128,
+) -> StdResult<Order> {
+    let store = ReadonlyPrefixedStorage::multilevel(&[PREFIX_ORDERS, address.as_slice()], store);
+    // Try to access the storage of orders for the account.
+    // If it doesn't exist yet, return an empty list of transfers.
+    let store = TypedStore::<Order, _>::attach(&store);
+
+    store.load(&position.to_le_bytes())
+}
+
+// Open contract orders, without creators, so that fillers don't need a viewing key
+fn order_book<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pair: Option<TokenPair>,
+    significant_figures: Option<u8>,
+    pagination: Option<Pagination>,
+) -> StdResult<Binary> {
+    if significant_figures == Some(0) {
+        return Err(StdError::generic_err(
+            "Significant figures must be greater than zero.",
+        ));
+    }
+
+    let contract_canonical_address: CanonicalAddr = deps
+        .api
+        .canonical_address(&read_contract_address(&deps.storage)?)?;
+    let pair: Option<(&HumanAddr, &HumanAddr)> =
+        pair.as_ref().map(|pair| (&pair.from_token, &pair.to_token));
+    let (positions, next_start_after) = read_open_order_positions(
+        &deps.storage,
+        pair,
+        &Page::new(pagination, SortOrder::Descending),
+    );
+    let mut orders: Vec<BookOrder> = vec![];
+    for position in positions {
+        let contract_order: Order =
+            order_at_position(&deps.storage, &contract_canonical_address, position)?;
+        orders.push(contract_order.into_book_order(significant_figures)?);
+    }
+
+    let tokens: Vec<TokenMetadata> = token_metadata(
+        &deps.storage,
+        &deps.api,
+        orders
+            .iter()
+            .flat_map(|order| vec![&order.from_token, &order.to_token])
+            .collect(),
+    )?;
+    let result = QueryAnswer::OrderBook {
+        orders,
+        total: Some(Uint128(read_open_orders_count(&deps.storage, pair))),
+        next_start_after: next_start_after.map(Uint128),
+        tokens,
+    };
+    to_binary(&result)
+}
+
+fn orders<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    key: String,
+    pagination: Option<Pagination>,
+) -> StdResult<Binary> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    // This is here so that the user can use their viewing key for butt for this
+    query_balance_of_token(deps, address.clone(), config.butt, key)?;
+
+    let (orders, total, next_start_after) = get_orders(
+        &deps.api,
         &deps.storage,
         &deps.api.canonical_address(&address)?,
-        page,
-        page_size,
+        &Page::new(pagination, SortOrder::Descending),
     )?;
 
+    let tokens: Vec<TokenMetadata> = token_metadata(
+        &deps.storage,
+        &deps.api,
+        orders
+            .iter()
+            .flat_map(|order| vec![&order.from_token, &order.to_token])
+            .collect(),
+    )?;
     let result = QueryAnswer::Orders {
         orders,
         total: Some(Uint128(total)),
+        next_start_after: next_start_after.map(Uint128),
+        tokens,
     };
     to_binary(&result)
 }
@@ -938,16 +2995,43 @@ fn orders_by_positions<S: Storage, A: Api, Q: Querier>(
     let mut orders: Vec<HumanizedOrder> = vec![];
     for position in positions.iter() {
         let order = order_at_position(&deps.storage, &address, position.u128())?;
-        orders.push(order.into_humanized(&deps.api)?)
+        orders.push(humanize_order(&deps.api, &deps.storage, order)?)
     }
 
+    let tokens: Vec<TokenMetadata> = token_metadata(
+        &deps.storage,
+        &deps.api,
+        orders
+            .iter()
+            .flat_map(|order| vec![&order.from_token, &order.to_token])
+            .collect(),
+    )?;
     let result = QueryAnswer::Orders {
         orders,
         total: None,
+        next_start_after: None,
+        tokens,
     };
     to_binary(&result)
 }
 
+// First position from low up to high where is_after is true, given that it stays true from there on
+fn partition_point(
+    mut low: u128,
+    mut high: u128,
+    is_after: &dyn Fn(u128) -> StdResult<bool>,
+) -> StdResult<u128> {
+    while low < high {
+        let middle: u128 = low + (high - low) / 2;
+        if is_after(middle)? {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    Ok(low)
+}
+
 fn pad_response(response: StdResult<HandleResponse>) -> StdResult<HandleResponse> {
     response.map(|mut response| {
         response.data = response.data.map(|mut data| {
@@ -958,6 +3042,207 @@ fn pad_response(response: StdResult<HandleResponse>) -> StdResult<HandleResponse
     })
 }
 
+// Pads query answers the way pad_response pads handle data, optionally up to a power of two number
+// of blocks
+fn pad_query_result(result: StdResult<Binary>, bucketed: bool) -> StdResult<Binary> {
+    result.map(|mut data| {
+        space_pad(BLOCK_SIZE, &mut data.0);
+        if bucketed {
+            let blocks: usize = (data.len() / BLOCK_SIZE).max(1);
+            space_pad(blocks.next_power_of_two() * BLOCK_SIZE, &mut data.0);
+        }
+        data
+    })
+}
+
+fn pair_stats<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    from_token: HumanAddr,
+    to_token: HumanAddr,
+) -> StdResult<Binary> {
+    let from_token_canonical_address: CanonicalAddr = deps.api.canonical_address(&from_token)?;
+    let to_token_canonical_address: CanonicalAddr = deps.api.canonical_address(&to_token)?;
+    if read_pair(
+        &deps.storage,
+        &from_token_canonical_address,
+        &to_token_canonical_address,
+    )
+    .is_none()
+    {
+        return Err(StdError::generic_err("Pair is not registered."));
+    }
+    let pair_stats: PairStats = read_pair_stats(
+        &deps.storage,
+        &from_token_canonical_address,
+        &to_token_canonical_address,
+    )
+    .unwrap_or(PairStats {
+        from_token,
+        to_token,
+        ..PairStats::default()
+    });
+
+    let tokens: Vec<TokenMetadata> = token_metadata(
+        &deps.storage,
+        &deps.api,
+        vec![&pair_stats.from_token, &pair_stats.to_token],
+    )?;
+    to_binary(&QueryAnswer::PairStats { pair_stats, tokens })
+}
+
+fn pairs<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pagination: Option<Pagination>,
+) -> StdResult<Binary> {
+    let pair_token_addresses: Vec<(HumanAddr, HumanAddr)> =
+        read_pair_token_addresses(&deps.storage);
+    let total: u128 = pair_token_addresses.len() as u128;
+    let (positions, next_start_after) =
+        Page::new(pagination, SortOrder::Ascending).positions(0, total);
+    let mut pairs: Vec<Pair> = vec![];
+    for position in positions {
+        let (from_token, to_token) = &pair_token_addresses[position as usize];
+        pairs.push(
+            read_pair(
+                &deps.storage,
+                &deps.api.canonical_address(from_token)?,
+                &deps.api.canonical_address(to_token)?,
+            )
+            .unwrap(),
+        );
+    }
+
+    let tokens: Vec<TokenMetadata> = token_metadata(
+        &deps.storage,
+        &deps.api,
+        pairs
+            .iter()
+            .flat_map(|pair| vec![&pair.from_token, &pair.to_token])
+            .collect(),
+    )?;
+    let result = QueryAnswer::Pairs {
+        pairs,
+        total: Some(Uint128(total)),
+        next_start_after: next_start_after.map(Uint128),
+        tokens,
+    };
+    to_binary(&result)
+}
+
+fn pending_config_changes<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pagination: Option<Pagination>,
+) -> StdResult<Binary> {
+    let page: Page = Page::new(pagination, SortOrder::Ascending);
+    let (ids, next_start_after) = read_pending_config_change_ids(&deps.storage, &page);
+    let pending_config_changes: Vec<PendingConfigChange> = ids
+        .into_iter()
+        .filter_map(|id| read_pending_config_change(&deps.storage, id))
+        .collect();
+
+    let result = QueryAnswer::PendingConfigChanges {
+        pending_config_changes,
+        total: Some(Uint128(read_pending_config_changes_count(&deps.storage))),
+        next_start_after: next_start_after.map(Uint128),
+    };
+    to_binary(&result)
+}
+
+fn permit_queries<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    permit: Permit,
+    query: QueryWithPermit,
+) -> StdResult<Binary> {
+    let account: HumanAddr = HumanAddr(permit::validate(
+        deps,
+        PREFIX_REVOKED_PERMITS,
+        &permit,
+        read_contract_address(&deps.storage)?,
+        None,
+    )?);
+
+    match query {
+        QueryWithPermit::MyActivity {
+            filters,
+            pagination,
+        } => {
+            if !permit.check_permission(&TokenPermissions::History) {
+                return Err(StdError::generic_err(format!(
+                    "No permission to query activity, got permissions {:?}",
+                    permit.params.permissions
+                )));
+            }
+
+            user_activity_records(deps, account, filters, pagination)
+        }
+    }
+}
+
+fn propose_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    address: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let mut config_store = TypedStoreMut::attach(&mut deps.storage);
+    let mut config: Config = config_store.load(CONFIG_KEY).unwrap();
+    authorize(vec![config.admin.clone()], &env.message.sender)?;
+
+    config.pending_admin = Some(address);
+    config_store.store(CONFIG_KEY, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn propose_config_change<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    addresses_allowed_to_fill: Option<Vec<HumanAddr>>,
+    config_change_delay_in_blocks: Option<u64>,
+    execution_fee: Option<Uint128>,
+    open_filling: Option<OpenFilling>,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    if addresses_allowed_to_fill.is_none()
+        && config_change_delay_in_blocks.is_none()
+        && execution_fee.is_none()
+        && open_filling.is_none()
+    {
+        return Err(StdError::generic_err("Config change is empty."));
+    }
+    authorize_config_change(
+        &deps.storage,
+        &config,
+        &addresses_allowed_to_fill,
+        &config_change_delay_in_blocks,
+        &execution_fee,
+        &open_filling,
+        &env.message.sender,
+    )?;
+
+    let id: u128 = read_config_changes_count(&deps.storage);
+    let pending_config_change: PendingConfigChange = PendingConfigChange {
+        id: Uint128(id),
+        addresses_allowed_to_fill,
+        config_change_delay_in_blocks,
+        execution_fee,
+        open_filling,
+        proposed_at_block_height: env.block.height,
+        executable_at_block_height: env.block.height + config.config_change_delay_in_blocks,
+    };
+    write_pending_config_change(&mut deps.storage, &pending_config_change)?;
+    write_config_changes_count(&mut deps.storage, id + 1)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&pending_config_change)?),
+    })
+}
+
 fn query_balance_of_token<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     address: HumanAddr,
@@ -981,31 +3266,283 @@ fn query_balance_of_token<S: Storage, A: Api, Q: Querier>(
     }
 }
 
-fn register_tokens<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
+// The contract's balance of a registered token must cover what is owed to order creators
+fn query_escrow_balance<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
     env: &Env,
-    tokens: Vec<SecretContract>,
-    viewing_key: String,
-) -> StdResult<HandleResponse> {
-    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
-    authorize(vec![config.admin], &env.message.sender)?;
-    let mut messages = vec![];
-    for token in tokens {
-        let token_address_canonical = deps.api.canonical_address(&token.address)?;
-        let token_details: Option<RegisteredToken> =
-            read_registered_token(&deps.storage, &token_address_canonical);
-        if token_details.is_none() {
-            let token_details: RegisteredToken = RegisteredToken {
-                address: token.address.clone(),
-                contract_hash: token.contract_hash.clone(),
-                sum_balance: Uint128(0),
-            };
-            write_registered_token(&mut deps.storage, &token_address_canonical, &token_details)?;
-            messages.push(snip20::register_receive_msg(
-                env.contract_code_hash.clone(),
-                None,
-                BLOCK_SIZE,
-                token.contract_hash.clone(),
+    registered_token: &RegisteredToken,
+    key: String,
+) -> StdResult<Uint128> {
+    let balance: Uint128 = query_balance_of_token(
+        deps,
+        env.contract.address.clone(),
+        SecretContract {
+            address: registered_token.address.clone(),
+            contract_hash: registered_token.contract_hash.clone(),
+        },
+        key,
+    )?;
+    if balance < registered_token.sum_balance {
+        return Err(StdError::generic_err(
+            "Token balance is less than sum balance.",
+        ));
+    }
+
+    Ok(balance)
+}
+
+fn query_token_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token: &SecretContract,
+) -> StdResult<TokenInfo> {
+    if token.address == HumanAddr::from(MOCK_TOKEN_ADDRESS)
+        || token.address == HumanAddr::from(MOCK_BUTT_ADDRESS)
+        || token.address == HumanAddr::from(MOCK_SSCRT_ADDRESS)
+    {
+        Ok(TokenInfo {
+            name: "Mock Token".to_string(),
+            symbol: "MOCK".to_string(),
+            decimals: 6,
+            total_supply: None,
+        })
+    } else {
+        snip20::token_info_query(
+            &deps.querier,
+            BLOCK_SIZE,
+            token.contract_hash.clone(),
+            token.address.clone(),
+        )
+    }
+}
+
+// Fillers are added to the registry the first time they are seen
+fn read_or_register_filler<S: Storage>(
+    storage: &mut S,
+    filler_address_canonical: &CanonicalAddr,
+    filler_address: HumanAddr,
+) -> StdResult<Filler> {
+    if let Some(filler) = read_filler(storage, filler_address_canonical) {
+        return Ok(filler);
+    }
+
+    let fillers_count: u128 = read_fillers_count(storage);
+    write_filler_address(storage, fillers_count, &filler_address)?;
+    write_fillers_count(storage, fillers_count + 1)?;
+    Ok(Filler {
+        address: filler_address,
+        ..Filler::default()
+    })
+}
+
+// Returns the escrowed amount of a commitment that has not been revealed
+fn reclaim_commitment<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    position: u128,
+) -> StdResult<HandleResponse> {
+    let commitment: Commitment = commitment_for_creator(deps, env, position)?;
+    let from_amount: Uint128 = commitment.from_amount;
+    let from_token_details: RegisteredToken = close_commitment(deps, commitment)?;
+
+    pad_response(Ok(HandleResponse {
+        messages: vec![snip20::transfer_msg(
+            env.message.sender.clone(),
+            from_amount,
+            None,
+            BLOCK_SIZE,
+            from_token_details.contract_hash,
+            from_token_details.address,
+        )?],
+        log: vec![],
+        data: None,
+    }))
+}
+
+// Reads the token's name, symbol and decimals again, e.g. after the token has been migrated
+fn refresh_token_metadata<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    address: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(
+        addresses_with_role(&deps.storage, &config, Role::TokenRegistrar),
+        &env.message.sender,
+    )?;
+
+    let token_address_canonical: CanonicalAddr = deps.api.canonical_address(&address)?;
+    let mut registered_token: RegisteredToken =
+        match read_registered_token(&deps.storage, &token_address_canonical) {
+            Some(registered_token) => registered_token,
+            None => return Err(StdError::generic_err("Token is not registered.")),
+        };
+    let token_info: TokenInfo = query_token_info(
+        deps,
+        &SecretContract {
+            address,
+            contract_hash: registered_token.contract_hash.clone(),
+        },
+    )?;
+    registered_token.name = Some(token_info.name);
+    registered_token.symbol = Some(token_info.symbol);
+    registered_token.decimals = Some(token_info.decimals);
+    write_registered_token(
+        &mut deps.storage,
+        &token_address_canonical,
+        &registered_token,
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+// Cancels an open order and refunds the creator the unfilled amount
+// and the execution fee if it has not been spent
+fn refund_and_cancel_order<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    creator_order: &mut Order,
+    admin_initiated: bool,
+) -> StdResult<Vec<CosmosMsg>> {
+    let creator: HumanAddr = deps.api.human_address(&creator_order.creator)?;
+    let from_token_address_canonical: CanonicalAddr =
+        deps.api.canonical_address(&creator_order.from_token)?;
+    let mut from_registered_token: RegisteredToken =
+        read_registered_token(&deps.storage, &from_token_address_canonical).unwrap();
+    let unfilled_amount: Uint128 = (creator_order.from_amount - creator_order.from_amount_filled)?;
+
+    // Update from_registered_token balance
+    from_registered_token.sum_balance = (from_registered_token.sum_balance - unfilled_amount)?;
+    write_registered_token(
+        &mut deps.storage,
+        &from_token_address_canonical,
+        &from_registered_token,
+    )?;
+
+    // Send refund to the creator
+    let mut messages: Vec<CosmosMsg> = vec![];
+    messages.push(snip20::transfer_msg(
+        creator.clone(),
+        unfilled_amount,
+        None,
+        BLOCK_SIZE,
+        from_registered_token.contract_hash,
+        from_registered_token.address,
+    )?);
+
+    // Update Txs
+    creator_order.cancelled = true;
+    let contract_canonical_address: CanonicalAddr =
+        deps.api.canonical_address(&env.contract.address)?;
+    update_creator_order_and_associated_contract_order(
+        &mut deps.storage,
+        &creator_order.creator,
+        creator_order.clone(),
+        &contract_canonical_address,
+    )?;
+    // Create activity record
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    let admin_canonical_address: CanonicalAddr = deps.api.canonical_address(&config.admin)?;
+    let mut activity_record: ActivityRecord = ActivityRecord {
+        position: Uint128(storage_count(
+            &deps.storage,
+            &admin_canonical_address,
+            PREFIX_CANCEL_RECORDS_COUNT,
+        )?),
+        admin_initiated,
+        refund_amount: Some(unfilled_amount),
+        ..ActivityRecord::new(
+            ActivityKind::Cancelled,
+            creator_order.other_storage_position,
+            creator_order.from_token.clone(),
+            creator_order.to_token.clone(),
+            env.block.height,
+            env.block.time,
+        )
+    };
+
+    // If order has an execution fee and it has not been spent, send it back to the user
+    if !creator_order.execution_fee_paid {
+        if let Some(execution_fee_unwrapped) = creator_order.execution_fee {
+            messages.push(snip20::transfer_msg(
+                creator,
+                execution_fee_unwrapped,
+                None,
+                BLOCK_SIZE,
+                config.sscrt.contract_hash,
+                config.sscrt.address,
+            )?);
+            activity_record.execution_fee_refund_amount = Some(execution_fee_unwrapped);
+        }
+    }
+    append_activity_record(
+        &mut deps.storage,
+        &activity_record,
+        &admin_canonical_address,
+        PREFIX_CANCEL_RECORDS,
+    )?;
+    append_user_activity_record(
+        &mut deps.storage,
+        &creator_order.creator,
+        ActivityRecord {
+            order_position: creator_order.position,
+            ..activity_record
+        },
+    )?;
+
+    Ok(messages)
+}
+
+fn register_tokens<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    tokens: Vec<SecretContract>,
+    viewing_key: String,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(
+        addresses_with_role(&deps.storage, &config, Role::TokenRegistrar),
+        &env.message.sender,
+    )?;
+    let mut messages = vec![];
+    for token in tokens {
+        let token_address_canonical = deps.api.canonical_address(&token.address)?;
+        let token_details: Option<RegisteredToken> =
+            read_registered_token(&deps.storage, &token_address_canonical);
+        if let Some(mut token_details_unwrapped) = token_details {
+            // Registering a disabled token lists it again
+            if token_details_unwrapped.disabled {
+                token_details_unwrapped.disabled = false;
+                write_registered_token(
+                    &mut deps.storage,
+                    &token_address_canonical,
+                    &token_details_unwrapped,
+                )?;
+            }
+        } else {
+            let token_info: TokenInfo = query_token_info(deps, &token)?;
+            let token_details: RegisteredToken = RegisteredToken {
+                address: token.address.clone(),
+                contract_hash: token.contract_hash.clone(),
+                sum_balance: Uint128(0),
+                disabled: false,
+                name: Some(token_info.name),
+                symbol: Some(token_info.symbol),
+                decimals: Some(token_info.decimals),
+            };
+            write_registered_token(&mut deps.storage, &token_address_canonical, &token_details)?;
+            let mut registered_token_addresses: Vec<HumanAddr> =
+                read_registered_token_addresses(&deps.storage);
+            registered_token_addresses.push(token.address.clone());
+            write_registered_token_addresses(&mut deps.storage, &registered_token_addresses)?;
+            messages.push(snip20::register_receive_msg(
+                env.contract_code_hash.clone(),
+                None,
+                BLOCK_SIZE,
+                token.contract_hash.clone(),
                 token.address.clone(),
             )?);
         }
@@ -1025,6 +3562,30 @@ fn register_tokens<S: Storage, A: Api, Q: Querier>(
     })
 }
 
+fn registered_tokens<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pagination: Option<Pagination>,
+) -> StdResult<Binary> {
+    let registered_token_addresses: Vec<HumanAddr> = read_registered_token_addresses(&deps.storage);
+    let total: u128 = registered_token_addresses.len() as u128;
+    let (positions, next_start_after) =
+        Page::new(pagination, SortOrder::Ascending).positions(0, total);
+    let mut registered_tokens: Vec<RegisteredToken> = vec![];
+    for position in positions {
+        let address: &HumanAddr = &registered_token_addresses[position as usize];
+        registered_tokens.push(
+            read_registered_token(&deps.storage, &deps.api.canonical_address(address)?).unwrap(),
+        );
+    }
+
+    let result = QueryAnswer::RegisteredTokens {
+        registered_tokens,
+        total: Some(Uint128(total)),
+        next_start_after: next_start_after.map(Uint128),
+    };
+    to_binary(&result)
+}
+
 fn rescue_tokens<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
@@ -1033,7 +3594,10 @@ fn rescue_tokens<S: Storage, A: Api, Q: Querier>(
     token_address: Option<HumanAddr>,
 ) -> StdResult<HandleResponse> {
     let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
-    authorize(vec![config.admin.clone()], &env.message.sender)?;
+    authorize(
+        addresses_with_role(&deps.storage, &config, Role::Treasury),
+        &env.message.sender,
+    )?;
 
     let mut messages: Vec<CosmosMsg> = vec![];
     if let Some(denom_unwrapped) = denom {
@@ -1062,13 +3626,15 @@ fn rescue_tokens<S: Storage, A: Api, Q: Querier>(
                 deps,
                 env.contract.address.clone(),
                 SecretContract {
-                    address: token_address_unwrapped,
+                    address: token_address_unwrapped.clone(),
                     contract_hash: registered_token.contract_hash.clone(),
                 },
                 key_unwrapped,
             )?;
-            let sum_balance: Uint128 = registered_token.sum_balance;
-            let difference: Uint128 = (balance - sum_balance)?;
+            // Escrowed execution fees are owed to fillers, the same as in solvency
+            let required: Uint128 = registered_token.sum_balance
+                + read_token_totals(&deps.storage, &token_address_unwrapped).execution_fee_amount;
+            let difference: Uint128 = (balance - required)?;
             if !difference.is_zero() {
                 messages.push(snip20::transfer_msg(
                     config.admin,
@@ -1089,79 +3655,72 @@ fn rescue_tokens<S: Storage, A: Api, Q: Querier>(
     })
 }
 
-// Take a Vec<u8> and pad it up to a multiple of `block_size`, using spaces at the end.
-fn space_pad(block_size: usize, message: &mut Vec<u8>) -> &mut Vec<u8> {
-    let len = message.len();
-    let surplus = len % block_size;
-    if surplus == 0 {
-        return message;
+// Turns a commitment into an order once REVEAL_DELAY_IN_BLOCKS have passed. The order is
+// validated the same way as when it is created directly.
+fn reveal_order<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    position: u128,
+    to_amount: Uint128,
+    to_token: HumanAddr,
+    salt: String,
+) -> StdResult<HandleResponse> {
+    let commitment: Commitment = commitment_for_creator(deps, env, position)?;
+    if env.block.height < commitment.committed_at_block_height + REVEAL_DELAY_IN_BLOCKS {
+        return Err(StdError::generic_err("Order can not be revealed yet."));
     }
-
-    let missing = block_size - surplus;
-    message.reserve(missing);
-    message.extend(std::iter::repeat(b' ').take(missing));
-    message
+    if commitment_hash(&to_token, to_amount, &salt) != commitment.hash {
+        return Err(StdError::generic_err(
+            "Order does not match the commitment.",
+        ));
+    }
+    // The escrowed amount moves from the commitment to the order
+    let (from_token, from_amount): (HumanAddr, Uint128) =
+        (commitment.from_token.clone(), commitment.from_amount);
+    close_commitment(deps, commitment)?;
+
+    pad_response(create_order(
+        deps,
+        env,
+        env.message.sender.clone(),
+        from_token,
+        from_amount,
+        to_amount,
+        to_token,
+    ))
 }
 
-fn storage_count<S: ReadonlyStorage>(
-    store: &S,
-    for_address: &CanonicalAddr,
-    storage_prefix: &[u8],
-) -> StdResult<u128> {
-    let store = ReadonlyPrefixedStorage::new(storage_prefix, store);
-    let store = TypedStore::<u128, _>::attach(&store);
-    let position: Option<u128> = store.may_load(for_address.as_slice())?;
-
-    Ok(position.unwrap_or(0))
-}
+fn revoke_permit<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    permit_name: String,
+) -> StdResult<HandleResponse> {
+    RevokedPermits::revoke_permit(
+        &mut deps.storage,
+        PREFIX_REVOKED_PERMITS,
+        &env.message.sender,
+        &permit_name,
+    );
 
-fn swap_msg(contract_address: HumanAddr, position: Option<&Uint128>) -> StdResult<Binary> {
-    let swap_msg = if let Some(position_unwrapped) = position {
-        to_binary(&ReceiveMsg::FillOrder {
-            position: *position_unwrapped,
-        })?
-    } else {
-        to_binary(&Snip20Swap::Swap {
-            // set expected_return to None because we don't care about slippage mid-route
-            expected_return: None,
-            // set the recepient of the swap to be this contract (the router)
-            to: Some(contract_address),
-        })?
-    };
-    Ok(swap_msg)
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
 }
 
-fn update_config<S: Storage, A: Api, Q: Querier>(
+fn revoke_role<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
-    addresses_allowed_to_fill: Option<Vec<HumanAddr>>,
-    execution_fee: Option<Uint128>,
+    role: Role,
+    address: HumanAddr,
 ) -> StdResult<HandleResponse> {
-    let mut config_store = TypedStoreMut::attach(&mut deps.storage);
-    let mut config: Config = config_store.load(CONFIG_KEY).unwrap();
-    authorize(vec![config.admin.clone()], &env.message.sender)?;
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(vec![config.admin], &env.message.sender)?;
 
-    if let Some(addresses_allowed_to_fill_unwrapped) = addresses_allowed_to_fill {
-        config.addresses_allowed_to_fill = addresses_allowed_to_fill_unwrapped;
-        if !config
-            .addresses_allowed_to_fill
-            .contains(&env.contract.address)
-        {
-            config
-                .addresses_allowed_to_fill
-                .push(env.contract.address.clone())
-        }
-        if !config
-            .addresses_allowed_to_fill
-            .contains(&config.admin.clone())
-        {
-            config.addresses_allowed_to_fill.push(config.admin.clone())
-        }
-    }
-    if let Some(execution_fee_unwrapped) = execution_fee {
-        config.execution_fee = execution_fee_unwrapped;
-    }
-    config_store.store(CONFIG_KEY, &config)?;
+    let mut addresses: Vec<HumanAddr> = read_role_addresses(&deps.storage, role);
+    addresses.retain(|role_address| role_address != &address);
+    write_role_addresses(&mut deps.storage, role, &addresses)?;
 
     Ok(HandleResponse {
         messages: vec![],
@@ -1170,1263 +3729,7384 @@ fn update_config<S: Storage, A: Api, Q: Querier>(
     })
 }
 
-fn update_creator_order_and_associated_contract_order<S: Storage>(
-    store: &mut S,
-    user_address: &CanonicalAddr,
-    creator_order: Order,
-    contract_address: &CanonicalAddr,
-) -> StdResult<()> {
-    let mut user_store =
-        PrefixedStorage::multilevel(&[PREFIX_ORDERS, user_address.as_slice()], store);
-    // Try to access the storage of orders for the account.
-    // If it doesn't exist yet, return an empty list of transfers.
-    let mut user_store = TypedStoreMut::<Order, _, _>::attach(&mut user_store);
-    user_store.store(&creator_order.position.u128().to_le_bytes(), &creator_order)?;
-    let mut contract_store =
-        PrefixedStorage::multilevel(&[PREFIX_ORDERS, contract_address.as_slice()], store);
-    let mut contract_store = TypedStoreMut::<Order, _, _>::attach(&mut contract_store);
-    let contract_order_position: Uint128 = creator_order.other_storage_position;
-    let creator_order_position: Uint128 = creator_order.position;
-    let mut contract_order = creator_order;
-    contract_order.position = contract_order_position;
-    contract_order.other_storage_position = creator_order_position;
-    contract_store.store(
-        &contract_order.position.u128().to_le_bytes(),
-        &contract_order,
+fn role<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, role: Role) -> StdResult<Binary> {
+    let result = QueryAnswer::Role {
+        role,
+        addresses: read_role_addresses(&deps.storage, role),
+    };
+    to_binary(&result)
+}
+
+fn set_filler_address_public<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    address_public: bool,
+) -> StdResult<HandleResponse> {
+    let filler_address_canonical: CanonicalAddr =
+        deps.api.canonical_address(&env.message.sender)?;
+    let mut filler: Filler = read_or_register_filler(
+        &mut deps.storage,
+        &filler_address_canonical,
+        env.message.sender.clone(),
     )?;
-    Ok(())
+    filler.address_public = address_public;
+    write_filler(&mut deps.storage, &filler_address_canonical, &filler)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::state::SecretContract;
-    use cosmwasm_std::from_binary;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
-    use cosmwasm_std::StdError::NotFound;
+fn set_filler_disabled<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    address: HumanAddr,
+    disabled: bool,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(
+        addresses_with_role(&deps.storage, &config, Role::FillerManager),
+        &env.message.sender,
+    )?;
 
-    pub const MOCK_ADMIN: &str = "admin";
-    pub const MOCK_VIEWING_KEY: &str = "DELIGHTFUL";
-    pub const MOCK_SSCRT_ADDRESS: &str = "mock-sscrt-address";
+    let filler_address_canonical: CanonicalAddr = deps.api.canonical_address(&address)?;
+    let mut filler: Filler =
+        read_or_register_filler(&mut deps.storage, &filler_address_canonical, address)?;
+    filler.disabled = disabled;
+    write_filler(&mut deps.storage, &filler_address_canonical, &filler)?;
 
-    // === HELPERS ===
-    fn create_order_helper<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>) {
-        let receive_msg = ReceiveMsg::CreateOrder {
-            to_amount: Uint128(MOCK_AMOUNT),
-            to_token: mock_token().address,
-        };
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_user_address(),
-            from: mock_user_address(),
-            amount: Uint128(MOCK_AMOUNT),
-            msg: Some(to_binary(&receive_msg).unwrap()),
-        };
-        handle(deps, mock_env(mock_butt().address, &[]), handle_msg.clone()).unwrap();
-    }
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
 
-    fn init_helper(
-        register_tokens: bool,
-    ) -> (
-        StdResult<InitResponse>,
-        Extern<MockStorage, MockApi, MockQuerier>,
-    ) {
-        let env = mock_env(MOCK_ADMIN, &[]);
-        let mut deps = mock_dependencies(20, &[]);
-        let msg = InitMsg {
-            butt: mock_butt(),
-            execution_fee: mock_execution_fee(),
-            sscrt: mock_sscrt(),
-        };
-        let init_result = init(&mut deps, env.clone(), msg);
-        if register_tokens {
-            let handle_msg = HandleMsg::RegisterTokens {
-                tokens: vec![mock_butt(), mock_token()],
-                viewing_key: MOCK_VIEWING_KEY.to_string(),
-            };
-            handle(&mut deps, env, handle_msg.clone()).unwrap();
+// Replaces the AMM pairs that best_route searches through
+fn set_amm_pairs<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    amm_pairs: Vec<AmmPair>,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(
+        addresses_with_role(&deps.storage, &config, Role::TokenRegistrar),
+        &env.message.sender,
+    )?;
+
+    for amm_pair in amm_pairs.iter() {
+        if amm_pair.token_a == amm_pair.token_b {
+            return Err(StdError::generic_err("Pair tokens must be different."));
+        }
+        if read_registered_token(
+            &deps.storage,
+            &deps.api.canonical_address(&amm_pair.token_a)?,
+        )
+        .is_none()
+            || read_registered_token(
+                &deps.storage,
+                &deps.api.canonical_address(&amm_pair.token_b)?,
+            )
+            .is_none()
+        {
+            return Err(StdError::generic_err("Token is not registered."));
         }
-        (init_result, deps)
     }
+    write_amm_pairs(&mut deps.storage, &amm_pairs)?;
 
-    fn mock_butt() -> SecretContract {
-        SecretContract {
-            address: HumanAddr::from(MOCK_BUTT_ADDRESS),
-            contract_hash: "mock-butt-contract-hash".to_string(),
-        }
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn set_candle_intervals<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    candle_intervals: Vec<u64>,
+) -> StdResult<HandleResponse> {
+    let mut config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(vec![config.admin.clone()], &env.message.sender)?;
+    if candle_intervals.len() > MAX_CANDLE_INTERVALS {
+        return Err(StdError::generic_err("Too many candle intervals."));
+    }
+    if candle_intervals.contains(&0) {
+        return Err(StdError::generic_err(
+            "Candle interval must be greater than zero.",
+        ));
     }
 
-    fn mock_contract() -> SecretContract {
-        let env = mock_env(mock_user_address(), &[]);
-        SecretContract {
-            address: env.contract.address,
-            contract_hash: env.contract_code_hash,
+    config.candle_intervals = candle_intervals;
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn set_pairs<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    pairs: Vec<Pair>,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(
+        addresses_with_role(&deps.storage, &config, Role::TokenRegistrar),
+        &env.message.sender,
+    )?;
+
+    let mut pair_token_addresses: Vec<(HumanAddr, HumanAddr)> =
+        read_pair_token_addresses(&deps.storage);
+    for pair in pairs {
+        if pair.from_token == pair.to_token {
+            return Err(StdError::generic_err("Pair tokens must be different."));
+        }
+        let from_token_address_canonical = deps.api.canonical_address(&pair.from_token)?;
+        let to_token_address_canonical = deps.api.canonical_address(&pair.to_token)?;
+        if read_registered_token(&deps.storage, &from_token_address_canonical).is_none()
+            || read_registered_token(&deps.storage, &to_token_address_canonical).is_none()
+        {
+            return Err(StdError::generic_err("Token is not registered."));
+        }
+        if read_pair(
+            &deps.storage,
+            &from_token_address_canonical,
+            &to_token_address_canonical,
+        )
+        .is_none()
+        {
+            pair_token_addresses.push((pair.from_token.clone(), pair.to_token.clone()));
         }
+        write_pair(
+            &mut deps.storage,
+            &from_token_address_canonical,
+            &to_token_address_canonical,
+            &pair,
+        )?;
     }
+    write_pair_token_addresses(&mut deps.storage, &pair_token_addresses)?;
 
-    fn mock_execution_fee() -> Uint128 {
-        Uint128(5_555)
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn set_pause_flags<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    pause_flags: PauseFlags,
+) -> StdResult<HandleResponse> {
+    let mut config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(
+        addresses_with_role(&deps.storage, &config, Role::Pauser),
+        &env.message.sender,
+    )?;
+
+    config.pause_flags = pause_flags;
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+// Fills a copy of the contract order the same way fill_order does, so that fillers don't have to
+// reproduce the pro rata maths
+fn simulate_fill<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    position: u128,
+    amount: Uint128,
+) -> StdResult<Binary> {
+    if amount.is_zero() {
+        return Err(StdError::generic_err("Amount must be greater than zero."));
     }
 
-    fn mock_sscrt() -> SecretContract {
-        SecretContract {
-            address: HumanAddr::from(MOCK_SSCRT_ADDRESS),
-            contract_hash: "mock-sscrt-contract-hash".to_string(),
-        }
+    let contract_canonical_address: CanonicalAddr = deps
+        .api
+        .canonical_address(&read_contract_address(&deps.storage)?)?;
+    let mut contract_order: Order =
+        order_at_position(&deps.storage, &contract_canonical_address, position)?;
+    let fill: Fill = contract_order.fill(amount)?;
+    let tokens: Vec<TokenMetadata> = token_metadata(
+        &deps.storage,
+        &deps.api,
+        vec![&contract_order.from_token, &contract_order.to_token],
+    )?;
+
+    to_binary(&QueryAnswer::SimulateFill {
+        fill,
+        order: contract_order.into_book_order(None)?,
+        tokens,
+    })
+}
+
+// The tokens sent into each hop, and SSCRT when a hop pays an execution fee
+fn route_token_metadata<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    hops: &VecDeque<Hop>,
+    hop_simulations: &[HopSimulation],
+) -> StdResult<Vec<TokenMetadata>> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    let mut token_addresses: Vec<&HumanAddr> =
+        hops.iter().map(|hop| &hop.from_token.address).collect();
+    if hop_simulations
+        .iter()
+        .any(|hop_simulation| hop_simulation.execution_fee.is_some())
+    {
+        token_addresses.push(&config.sscrt.address);
     }
+    token_metadata(&deps.storage, &deps.api, token_addresses)
+}
 
-    fn mock_token() -> SecretContract {
-        SecretContract {
-            address: HumanAddr::from(MOCK_TOKEN_ADDRESS),
-            contract_hash: "mock-token-contract-hash".to_string(),
-        }
+// Walks the route the way handle_first_hop and handle_hop would, without authorizing a filler.
+// Limit order hops fill copies of the orders and AMM hops use the pair's simulation query.
+// The error that the route would revert with is returned as the revert reason.
+fn simulate_route<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    borrow_amount: Uint128,
+    hops: VecDeque<Hop>,
+    minimum_acceptable_amount: Option<Uint128>,
+) -> StdResult<Binary> {
+    let mut hop_simulations: Vec<HopSimulation> = vec![];
+    let result: StdResult<Uint128> = simulate_route_amount(
+        deps,
+        borrow_amount,
+        &hops,
+        minimum_acceptable_amount,
+        &mut hop_simulations,
+    );
+    let final_amount: Option<Uint128> = if hop_simulations.len() == hops.len() {
+        hop_simulations.last().map(|hop| hop.amount_out)
+    } else {
+        None
+    };
+    let excess: Uint128 = final_amount
+        .and_then(|final_amount| (final_amount - borrow_amount).ok())
+        .unwrap_or_default();
+    let revert_reason: Option<String> = match result {
+        Ok(_) => None,
+        Err(StdError::GenericErr { msg, .. }) => Some(msg),
+        Err(error) => Some(error.to_string()),
+    };
+
+    let tokens: Vec<TokenMetadata> = route_token_metadata(deps, &hops, &hop_simulations)?;
+
+    to_binary(&QueryAnswer::SimulateRoute {
+        hops: hop_simulations,
+        final_amount,
+        excess,
+        revert_reason,
+        tokens,
+    })
+}
+
+fn simulate_route_amount<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    borrow_amount: Uint128,
+    hops: &VecDeque<Hop>,
+    minimum_acceptable_amount: Option<Uint128>,
+    hop_simulations: &mut Vec<HopSimulation>,
+) -> StdResult<Uint128> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    validate_not_paused(config.pause_flags.route_paused(), "Routing is paused.")?;
+    if hops.len() != 2 {
+        return Err(StdError::generic_err("Route must be 2 hops."));
     }
 
-    fn mock_user_address() -> HumanAddr {
-        HumanAddr::from("gary")
+    let contract_address: HumanAddr = read_contract_address(&deps.storage)?;
+    let contract_canonical_address: CanonicalAddr =
+        deps.api.canonical_address(&contract_address)?;
+    let borrow_token: HumanAddr = hops[0].from_token.address.clone();
+    let mut amount: Uint128 = borrow_amount;
+    // The token received from an AMM hop isn't known until the next hop
+    let mut token: Option<HumanAddr> = Some(borrow_token.clone());
+    for (index, hop) in hops.iter().enumerate() {
+        if let Some(token) = &token {
+            validate_human_addr(
+                token,
+                &hop.from_token.address,
+                "Route called by wrong token.",
+            )?;
+        }
+        let amount_in: Uint128 = amount;
+        if hop.trade_smart_contract.address == contract_address {
+            let position: u128 = match hop.position {
+                Some(position) => position.u128(),
+                None => return Err(StdError::generic_err("Limit order hop needs a position.")),
+            };
+            // The borrowed amount is sent to the first hop as it is
+            if index > 0 {
+                amount = clamp_to_unfilled_amount(
+                    &deps.storage,
+                    &contract_canonical_address,
+                    position,
+                    amount,
+                )?;
+            }
+            if amount.is_zero() {
+                return Err(StdError::generic_err("Amount must be greater than zero."));
+            }
+            let mut contract_order: Order =
+                order_at_position(&deps.storage, &contract_canonical_address, position)?;
+            validate_human_addr(
+                &contract_order.to_token,
+                &hop.from_token.address,
+                "To token does not match the token sent in.",
+            )?;
+            let fill: Fill = contract_order.fill(amount)?;
+            hop_simulations.push(HopSimulation {
+                amount_in: amount,
+                amount_out: fill.from_amount,
+                execution_fee: fill.execution_fee,
+            });
+            amount = fill.from_amount;
+            token = Some(contract_order.from_token);
+        } else {
+            let simulation: AmmSimulationResponse =
+                deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+                    contract_addr: hop.trade_smart_contract.address.clone(),
+                    callback_code_hash: hop.trade_smart_contract.contract_hash.clone(),
+                    msg: to_binary(&AmmPairQueryMsg::Simulation {
+                        offer_asset: AmmAsset {
+                            info: AmmAssetInfo::Token {
+                                contract_addr: hop.from_token.address.clone(),
+                                token_code_hash: hop.from_token.contract_hash.clone(),
+                                viewing_key: String::new(),
+                            },
+                            amount: amount_in,
+                        },
+                    })?,
+                }))?;
+            hop_simulations.push(HopSimulation {
+                amount_in,
+                amount_out: simulation.return_amount,
+                execution_fee: None,
+            });
+            amount = simulation.return_amount;
+            token = None;
+        }
+    }
+    if let Some(token) = &token {
+        validate_human_addr(&borrow_token, token, "Route called by wrong token.")?;
+    }
+    if amount < borrow_amount {
+        return Err(StdError::generic_err(
+            "Operation fell short of borrow_amount.",
+        ));
+    }
+    if let Some(minimum_acceptable_amount) = minimum_acceptable_amount {
+        if amount < minimum_acceptable_amount {
+            return Err(StdError::generic_err(
+                "Operation fell short of minimum_acceptable_amount.",
+            ));
+        }
     }
 
-    // === UNIT TESTS ===
-    #[test]
-    fn test_set_execution_fee_for_order() {
-        let (_init_result, mut deps) = init_helper(true);
-        let mut env = mock_env(mock_butt().address, &[]);
+    Ok(amount)
+}
 
-        // when token sent in is not sscrt
-        let receive_msg = ReceiveMsg::SetExecutionFeeForOrder {};
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_user_address(),
-            from: mock_user_address(),
-            amount: Uint128(1),
-            msg: Some(to_binary(&receive_msg).unwrap()),
-        };
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
-        // * it raises an error
-        assert_eq!(
-            handle_result.unwrap_err(),
+// Compares each registered token's balance with what the contract owes so that accounting drift
+// shows up before withdrawals fail
+fn solvency<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    key: String,
+    viewing_key: String,
+) -> StdResult<Binary> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    // This is here to check the admin's viewing key
+    query_balance_of_token(deps, config.admin.clone(), config.butt.clone(), key)?;
+
+    let contract_address: HumanAddr = read_contract_address(&deps.storage)?;
+    let mut token_solvencies: Vec<TokenSolvency> = vec![];
+    for address in read_registered_token_addresses(&deps.storage) {
+        let registered_token: RegisteredToken =
+            read_registered_token(&deps.storage, &deps.api.canonical_address(&address)?).unwrap();
+        let balance: Uint128 = query_balance_of_token(
+            deps,
+            contract_address.clone(),
+            SecretContract {
+                address: registered_token.address.clone(),
+                contract_hash: registered_token.contract_hash,
+            },
+            viewing_key.clone(),
+        )?;
+        let token_totals: TokenTotals = read_token_totals(&deps.storage, &address);
+        token_solvencies.push(TokenSolvency {
+            token: address,
+            balance,
+            sum_balance: registered_token.sum_balance,
+            unfilled_order_amount: token_totals.unfilled_order_amount,
+            bonded_amount: token_totals.bonded_amount,
+            committed_amount: token_totals.committed_amount,
+            execution_fee_amount: token_totals.execution_fee_amount,
+            surplus: Uint128(0),
+            deficit: Uint128(0),
+        });
+    }
+
+    for token_solvency in token_solvencies.iter_mut() {
+        let required: Uint128 = token_solvency.sum_balance + token_solvency.execution_fee_amount;
+        if token_solvency.balance >= required {
+            token_solvency.surplus = (token_solvency.balance - required)?;
+        } else {
+            token_solvency.deficit = (required - token_solvency.balance)?;
+        }
+    }
+
+    let tokens: Vec<TokenMetadata> = token_metadata(
+        &deps.storage,
+        &deps.api,
+        token_solvencies
+            .iter()
+            .map(|token_solvency| &token_solvency.token)
+            .collect(),
+    )?;
+    to_binary(&QueryAnswer::Solvency {
+        token_solvencies,
+        tokens,
+    })
+}
+
+// Take a Vec<u8> and pad it up to a multiple of `block_size`, using spaces at the end.
+fn space_pad(block_size: usize, message: &mut Vec<u8>) -> &mut Vec<u8> {
+    let len = message.len();
+    let surplus = len % block_size;
+    if surplus == 0 {
+        return message;
+    }
+
+    let missing = block_size - surplus;
+    message.reserve(missing);
+    message.extend(std::iter::repeat(b' ').take(missing));
+    message
+}
+
+fn storage_count<S: ReadonlyStorage>(
+    store: &S,
+    for_address: &CanonicalAddr,
+    storage_prefix: &[u8],
+) -> StdResult<u128> {
+    let store = ReadonlyPrefixedStorage::new(storage_prefix, store);
+    let store = TypedStore::<u128, _>::attach(&store);
+    let position: Option<u128> = store.may_load(for_address.as_slice())?;
+
+    Ok(position.unwrap_or(0))
+}
+
+fn swap_msg(contract_address: HumanAddr, position: Option<&Uint128>) -> StdResult<Binary> {
+    let swap_msg = if let Some(position_unwrapped) = position {
+        to_binary(&ReceiveMsg::FillOrder {
+            position: *position_unwrapped,
+        })?
+    } else {
+        to_binary(&Snip20Swap::Swap {
+            // set expected_return to None because we don't care about slippage mid-route
+            expected_return: None,
+            // set the recepient of the swap to be this contract (the router)
+            to: Some(contract_address),
+        })?
+    };
+    Ok(swap_msg)
+}
+
+// Metadata of each token once, in the order they are first mentioned
+fn token_metadata<S: Storage, A: Api>(
+    storage: &S,
+    api: &A,
+    token_addresses: Vec<&HumanAddr>,
+) -> StdResult<Vec<TokenMetadata>> {
+    let mut tokens: Vec<TokenMetadata> = vec![];
+    for address in token_addresses {
+        if tokens.iter().any(|token| &token.address == address) {
+            continue;
+        }
+        tokens.push(
+            match read_registered_token(storage, &api.canonical_address(address)?) {
+                Some(registered_token) => TokenMetadata {
+                    address: address.clone(),
+                    name: registered_token.name,
+                    symbol: registered_token.symbol,
+                    decimals: registered_token.decimals,
+                },
+                None => TokenMetadata {
+                    address: address.clone(),
+                    name: None,
+                    symbol: None,
+                    decimals: None,
+                },
+            },
+        );
+    }
+
+    Ok(tokens)
+}
+
+fn unbond_filler<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    amount: Uint128,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    let filler_address_canonical: CanonicalAddr =
+        deps.api.canonical_address(&env.message.sender)?;
+    let mut filler: Filler = match read_filler(&deps.storage, &filler_address_canonical) {
+        Some(filler) => filler,
+        None => return Err(StdError::generic_err("Filler does not exist.")),
+    };
+    if amount.is_zero() {
+        return Err(StdError::generic_err("Amount must be greater than zero."));
+    }
+    if amount > filler.bonded_amount {
+        return Err(StdError::generic_err(
+            "Amount is greater than bonded amount.",
+        ));
+    }
+
+    // Unbonding again restarts the unbonding period for everything being unbonded
+    filler.bonded_amount = (filler.bonded_amount - amount)?;
+    filler.unbonding_amount += amount;
+    filler.unbonding_available_at_block_height =
+        env.block.height + config.open_filling.unbonding_period_in_blocks;
+    write_filler(&mut deps.storage, &filler_address_canonical, &filler)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&filler)?),
+    })
+}
+
+fn update_token<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    address: HumanAddr,
+    contract_hash: String,
+    decimals: Option<u8>,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(
+        addresses_with_role(&deps.storage, &config, Role::TokenRegistrar),
+        &env.message.sender,
+    )?;
+
+    let token_address_canonical: CanonicalAddr = deps.api.canonical_address(&address)?;
+    let mut registered_token: RegisteredToken =
+        match read_registered_token(&deps.storage, &token_address_canonical) {
+            Some(registered_token) => registered_token,
+            None => return Err(StdError::generic_err("Token is not registered.")),
+        };
+    registered_token.contract_hash = contract_hash;
+    if decimals.is_some() {
+        registered_token.decimals = decimals;
+    }
+    write_registered_token(
+        &mut deps.storage,
+        &token_address_canonical,
+        &registered_token,
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+// Adds a fill to the latest candle when it covers the block time, otherwise starts a new candle.
+// Fills without a price are left out.
+fn update_candle<S: Storage>(
+    storage: &mut S,
+    from_token_address: &CanonicalAddr,
+    to_token_address: &CanonicalAddr,
+    interval: u64,
+    from_amount: Uint128,
+    to_amount: Uint128,
+    block_time: u64,
+) -> StdResult<()> {
+    let price: Uint128 = match calculate_price(from_amount, to_amount) {
+        Some(price) => price,
+        None => return Ok(()),
+    };
+    let start_time: u64 = block_time - block_time % interval;
+    let count: u64 = read_candles_count(storage, from_token_address, to_token_address, interval);
+    let latest_candle: Option<Candle> = match count.checked_sub(1) {
+        Some(position) => read_candle(
+            storage,
+            from_token_address,
+            to_token_address,
+            interval,
+            position,
+        ),
+        None => None,
+    };
+    match latest_candle {
+        Some(mut candle) if candle.start_time == start_time => {
+            candle.high = candle.high.max(price);
+            candle.low = candle.low.min(price);
+            candle.close = price;
+            candle.from_volume = add_volume(candle.from_volume, from_amount)?;
+            candle.to_volume = add_volume(candle.to_volume, to_amount)?;
+            write_candle(
+                storage,
+                from_token_address,
+                to_token_address,
+                interval,
+                count - 1,
+                &candle,
+            )
+        }
+        _ => {
+            let candle: Candle = Candle {
+                start_time,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                from_volume: from_amount,
+                to_volume: to_amount,
+            };
+            write_candle(
+                storage,
+                from_token_address,
+                to_token_address,
+                interval,
+                count,
+                &candle,
+            )?;
+            write_candles_count(
+                storage,
+                from_token_address,
+                to_token_address,
+                interval,
+                count + 1,
+            )
+        }
+    }
+}
+
+fn update_creator_order_and_associated_contract_order<S: Storage>(
+    store: &mut S,
+    user_address: &CanonicalAddr,
+    creator_order: Order,
+    contract_address: &CanonicalAddr,
+) -> StdResult<()> {
+    let mut user_store =
+        PrefixedStorage::multilevel(&[PREFIX_ORDERS, user_address.as_slice()], store);
+    // Try to access the storage of orders for the account.
+    // If it doesn't exist yet, return an empty list of transfers.
+    let mut user_store = TypedStoreMut::<Order, _, _>::attach(&mut user_store);
+    user_store.store(&creator_order.position.u128().to_le_bytes(), &creator_order)?;
+    let mut contract_store =
+        PrefixedStorage::multilevel(&[PREFIX_ORDERS, contract_address.as_slice()], store);
+    let mut contract_store = TypedStoreMut::<Order, _, _>::attach(&mut contract_store);
+    let contract_order_position: Uint128 = creator_order.other_storage_position;
+    let creator_order_position: Uint128 = creator_order.position;
+    let previous_contract_order: Option<Order> =
+        contract_store.may_load(&contract_order_position.u128().to_le_bytes())?;
+    let mut contract_order = creator_order;
+    contract_order.position = contract_order_position;
+    contract_order.other_storage_position = creator_order_position;
+    contract_store.store(
+        &contract_order.position.u128().to_le_bytes(),
+        &contract_order,
+    )?;
+    if !contract_order.is_open() {
+        remove_open_order(store, &contract_order)?;
+    }
+    update_order_token_totals(store, previous_contract_order.as_ref(), &contract_order)
+}
+
+// Moves the order's share of the token totals from its previous state to its current one
+fn update_order_token_totals<S: Storage>(
+    storage: &mut S,
+    previous_order: Option<&Order>,
+    order: &Order,
+) -> StdResult<()> {
+    let (previous_unfilled_amount, previous_escrowed_execution_fee): (Uint128, Uint128) =
+        match previous_order {
+            Some(previous_order) => (
+                previous_order.unfilled_amount(),
+                previous_order.escrowed_execution_fee(),
+            ),
+            None => (Uint128(0), Uint128(0)),
+        };
+    let mut from_token_totals: TokenTotals = read_token_totals(storage, &order.from_token);
+    from_token_totals.unfilled_order_amount = ((from_token_totals.unfilled_order_amount
+        + order.unfilled_amount())
+        - previous_unfilled_amount)?;
+    write_token_totals(storage, &order.from_token, &from_token_totals)?;
+
+    let sscrt_address: HumanAddr = TypedStore::<Config, _>::attach(storage)
+        .load(CONFIG_KEY)?
+        .sscrt
+        .address;
+    let mut sscrt_totals: TokenTotals = read_token_totals(storage, &sscrt_address);
+    sscrt_totals.execution_fee_amount = ((sscrt_totals.execution_fee_amount
+        + order.escrowed_execution_fee())
+        - previous_escrowed_execution_fee)?;
+    write_token_totals(storage, &sscrt_address, &sscrt_totals)
+}
+
+fn user_activity_records<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    filters: ActivityFilters,
+    pagination: Option<Pagination>,
+) -> StdResult<Binary> {
+    let (activity_records, total, next_start_after) = get_filtered_activity_records(
+        &deps.storage,
+        &deps.api.canonical_address(&address)?,
+        &filters,
+        &Page::new(pagination, SortOrder::Descending),
+        PREFIX_ACTIVITY_RECORDS,
+    )?;
+
+    let tokens: Vec<TokenMetadata> = token_metadata(
+        &deps.storage,
+        &deps.api,
+        activity_records
+            .iter()
+            .flat_map(|activity_record| {
+                vec![&activity_record.from_token, &activity_record.to_token]
+            })
+            .collect(),
+    )?;
+    let result = QueryAnswer::ActivityRecords {
+        activity_records,
+        total: Some(Uint128(total)),
+        next_start_after: next_start_after.map(Uint128),
+        tokens,
+    };
+    to_binary(&result)
+}
+
+fn withdraw_filler_bond<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    let filler_address_canonical: CanonicalAddr =
+        deps.api.canonical_address(&env.message.sender)?;
+    let mut filler: Filler = match read_filler(&deps.storage, &filler_address_canonical) {
+        Some(filler) => filler,
+        None => return Err(StdError::generic_err("Filler does not exist.")),
+    };
+    if filler.unbonding_amount.is_zero() {
+        return Err(StdError::generic_err("Nothing to withdraw."));
+    }
+    if env.block.height < filler.unbonding_available_at_block_height {
+        return Err(StdError::generic_err("Bond is still unbonding."));
+    }
+
+    let butt_address_canonical: CanonicalAddr = deps.api.canonical_address(&config.butt.address)?;
+    let mut butt_registered_token: RegisteredToken =
+        read_registered_token(&deps.storage, &butt_address_canonical).unwrap();
+    butt_registered_token.sum_balance =
+        (butt_registered_token.sum_balance - filler.unbonding_amount)?;
+    write_registered_token(
+        &mut deps.storage,
+        &butt_address_canonical,
+        &butt_registered_token,
+    )?;
+    let mut butt_totals: TokenTotals = read_token_totals(&deps.storage, &config.butt.address);
+    butt_totals.bonded_amount = (butt_totals.bonded_amount - filler.unbonding_amount)?;
+    write_token_totals(&mut deps.storage, &config.butt.address, &butt_totals)?;
+    let messages: Vec<CosmosMsg> = vec![snip20::transfer_msg(
+        env.message.sender.clone(),
+        filler.unbonding_amount,
+        None,
+        BLOCK_SIZE,
+        config.butt.contract_hash,
+        config.butt.address,
+    )?];
+    filler.unbonding_amount = Uint128(0);
+    write_filler(&mut deps.storage, &filler_address_canonical, &filler)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{
+        MAX_PAGE_SIZE, PRICE_SCALE, ROLLING_VOLUME_BUCKET_IN_SECONDS,
+        ROLLING_VOLUME_PERIOD_IN_SECONDS,
+    };
+    use crate::state::{OrderStatus, SecretContract};
+    use cosmwasm_std::from_binary;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::StdError::NotFound;
+
+    pub const MOCK_ADMIN: &str = "admin";
+    pub const MOCK_SALT: &str = "mock-salt";
+    pub const MOCK_VIEWING_KEY: &str = "DELIGHTFUL";
+
+    // === HELPERS ===
+    fn pagination_helper(start_after: Option<u128>, limit: u128) -> Option<Pagination> {
+        Some(Pagination {
+            start_after: start_after.map(Uint128),
+            limit: Some(Uint128(limit)),
+            order: None,
+            bucketed: None,
+        })
+    }
+
+    // Commits to a butt for token order like the one create_order_helper creates
+    fn commit_order_helper<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>) {
+        let receive_msg = ReceiveMsg::CommitOrder {
+            hash: commitment_hash(&mock_token().address, Uint128(MOCK_AMOUNT), MOCK_SALT),
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(MOCK_AMOUNT),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        handle(deps, mock_env(mock_butt().address, &[]), handle_msg).unwrap();
+    }
+
+    fn create_order_helper<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>) {
+        let receive_msg = ReceiveMsg::CreateOrder {
+            to_amount: Uint128(MOCK_AMOUNT),
+            to_token: mock_token().address,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(MOCK_AMOUNT),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        handle(deps, mock_env(mock_butt().address, &[]), handle_msg.clone()).unwrap();
+    }
+
+    fn init_helper(
+        register_tokens: bool,
+    ) -> (
+        StdResult<InitResponse>,
+        Extern<MockStorage, MockApi, MockQuerier>,
+    ) {
+        init_helper_with_importing(register_tokens, false)
+    }
+
+    fn init_helper_with_importing(
+        register_tokens: bool,
+        importing: bool,
+    ) -> (
+        StdResult<InitResponse>,
+        Extern<MockStorage, MockApi, MockQuerier>,
+    ) {
+        let env = mock_env(MOCK_ADMIN, &[]);
+        let mut deps = mock_dependencies(20, &[]);
+        let msg = InitMsg {
+            butt: mock_butt(),
+            config_change_delay_in_blocks: None,
+            execution_fee: mock_execution_fee(),
+            importing: Some(importing),
+            sscrt: mock_sscrt(),
+        };
+        let init_result = init(&mut deps, env.clone(), msg);
+        if register_tokens {
+            let handle_msg = HandleMsg::RegisterTokens {
+                tokens: vec![mock_butt(), mock_token()],
+                viewing_key: MOCK_VIEWING_KEY.to_string(),
+            };
+            handle(&mut deps, env.clone(), handle_msg.clone()).unwrap();
+            let handle_msg = HandleMsg::SetPairs {
+                pairs: vec![
+                    mock_pair(mock_butt().address, mock_token().address),
+                    mock_pair(mock_token().address, mock_butt().address),
+                ],
+            };
+            handle(&mut deps, env, handle_msg).unwrap();
+        }
+        (init_result, deps)
+    }
+
+    fn mock_butt() -> SecretContract {
+        SecretContract {
+            address: HumanAddr::from(MOCK_BUTT_ADDRESS),
+            contract_hash: "mock-butt-contract-hash".to_string(),
+        }
+    }
+
+    fn mock_contract() -> SecretContract {
+        let env = mock_env(mock_user_address(), &[]);
+        SecretContract {
+            address: env.contract.address,
+            contract_hash: env.contract_code_hash,
+        }
+    }
+
+    fn mock_execution_fee() -> Uint128 {
+        Uint128(5_555)
+    }
+
+    fn mock_pair(from_token: HumanAddr, to_token: HumanAddr) -> Pair {
+        Pair {
+            from_token,
+            to_token,
+            enabled: true,
+            minimum_from_amount: Uint128(0),
+            price_tick_size: Uint128(0),
+        }
+    }
+
+    fn mock_sscrt() -> SecretContract {
+        SecretContract {
+            address: HumanAddr::from(MOCK_SSCRT_ADDRESS),
+            contract_hash: "mock-sscrt-contract-hash".to_string(),
+        }
+    }
+
+    fn mock_token() -> SecretContract {
+        SecretContract {
+            address: HumanAddr::from(MOCK_TOKEN_ADDRESS),
+            contract_hash: "mock-token-contract-hash".to_string(),
+        }
+    }
+
+    fn mock_user_address() -> HumanAddr {
+        HumanAddr::from("gary")
+    }
+
+    // === UNIT TESTS ===
+    #[test]
+    fn test_set_execution_fee_for_order() {
+        let (_init_result, mut deps) = init_helper(true);
+        let mut env = mock_env(mock_butt().address, &[]);
+
+        // when token sent in is not sscrt
+        let receive_msg = ReceiveMsg::SetExecutionFeeForOrder {};
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(1),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        // * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
             StdError::generic_err("Execution fee token must be SSCRT.")
         );
-
-        // when token sent in is sscrt
-        env = mock_env(mock_sscrt().address, &[]);
-        // = when amount sent in is not equal to execution fee
-        let handle_result = handle(&mut deps, env.clone(), handle_msg);
-        // * it raises an error
+
+        // when token sent in is sscrt
+        env = mock_env(mock_sscrt().address, &[]);
+        // = when amount sent in is not equal to execution fee
+        let handle_result = handle(&mut deps, env.clone(), handle_msg);
+        // * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Amount sent in must equal execution fee.")
+        );
+        // = when amount sent in is equal to execution fee
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_execution_fee(),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        let handle_result = handle(&mut deps, env.clone(), handle_msg);
+        // == when user does not have any orders
+        // === * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Order does not exist.")
+        );
+        // == when user has at least one order
+        create_order_helper(&mut deps);
+        // === when current block is the same as the block when the order is created
+        // ==== when order has fee set already
+        let mut creator_order = order_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        creator_order.execution_fee = Some(Uint128(1));
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+        // ==== * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_execution_fee(),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Execution fee already set for order.")
+        );
+        // ==== when order does not have execution fee set already
+        // ===== when order is cancelled
+        creator_order.execution_fee = None;
+        creator_order.cancelled = true;
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+        // ===== * it raises an error
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Order already cancelled.")
+        );
+        // ===== when order is filled
+        creator_order.cancelled = false;
+        creator_order.from_amount_filled = creator_order.from_amount;
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+        // ===== * it raises an error
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Order already filled.")
+        );
+        // ===== when order is open
+        creator_order.from_amount_filled = Uint128(0);
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+        // ===== * it sets the execution fee for the user order
+        let handle_unwrapped = handle(&mut deps, env.clone(), handle_msg.clone()).unwrap();
+        creator_order = order_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        assert_eq!(creator_order.execution_fee, Some(mock_execution_fee()));
+        // ===== * it sets the execution fee for the contract order
+        let contract_order = order_at_position(
+            &mut deps.storage,
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+            creator_order.other_storage_position.u128(),
+        )
+        .unwrap();
+        assert_eq!(contract_order.execution_fee, Some(mock_execution_fee()));
+        // ===== * it sends the humanized creator order back as data
+        assert_eq!(
+            handle_unwrapped.data,
+            pad_response(Ok(HandleResponse {
+                messages: vec![],
+                log: vec![],
+                data: Some(
+                    to_binary(
+                        &humanize_order(&deps.api, &deps.storage, creator_order.clone()).unwrap()
+                    )
+                    .unwrap()
+                ),
+            }))
+            .unwrap()
+            .data
+        );
+        // ===== * it creates an activity record for the creator
+        let (activity_records, total, _) = get_activity_records(
+            &deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            &Page::new(pagination_helper(None, 1), SortOrder::Descending),
+            PREFIX_ACTIVITY_RECORDS,
+        )
+        .unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(activity_records[0].activity, ActivityKind::FeeSet);
+        assert_eq!(activity_records[0].order_position, creator_order.position);
+        assert_eq!(
+            activity_records[0].execution_fee,
+            Some(mock_execution_fee())
+        );
+
+        // === when current block is different from the block when the order is created
+        let mut creator_order = order_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        creator_order.created_at_block_height = 1;
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+        // === * it raises an error
+        let handle_result = handle(&mut deps, env.clone(), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err(
+                "Execution fee must be set at the same block as when order is created."
+            )
+        );
+
+        // = when an order with an execution fee is filled more than once
+        handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(2),
+                msg: Some(
+                    to_binary(&ReceiveMsg::CreateOrder {
+                        to_amount: Uint128(MOCK_AMOUNT),
+                        to_token: mock_token().address,
+                    })
+                    .unwrap(),
+                ),
+            },
+        )
+        .unwrap();
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: mock_execution_fee(),
+                msg: Some(to_binary(&ReceiveMsg::SetExecutionFeeForOrder {}).unwrap()),
+            },
+        )
+        .unwrap();
+        let contract_order_position: Uint128 = order_at_position(
+            &deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            1,
+        )
+        .unwrap()
+        .other_storage_position;
+        let fill = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+                    amount: u128|
+         -> StdResult<HandleResponse> {
+            handle(
+                deps,
+                mock_env(mock_token().address, &[]),
+                HandleMsg::Receive {
+                    sender: HumanAddr::from(MOCK_ADMIN),
+                    from: HumanAddr::from(MOCK_ADMIN),
+                    amount: Uint128(amount),
+                    msg: Some(
+                        to_binary(&ReceiveMsg::FillOrder {
+                            position: contract_order_position,
+                        })
+                        .unwrap(),
+                    ),
+                },
+            )
+        };
+        // == when the first fill would get none of the from amount
+        // == * it raises an error
+        assert_eq!(
+            fill(&mut deps, 1).unwrap_err(),
+            StdError::generic_err("Amount is too small to fill.")
+        );
+        // == * it only pays the execution fee to the first fill that goes through
+        let execution_fee_transfer: CosmosMsg = snip20::transfer_msg(
+            HumanAddr::from(MOCK_ADMIN),
+            mock_execution_fee(),
+            None,
+            BLOCK_SIZE,
+            mock_sscrt().contract_hash,
+            mock_sscrt().address,
+        )
+        .unwrap();
+        let mut messages: Vec<CosmosMsg> = fill(&mut deps, MOCK_AMOUNT / 2).unwrap().messages;
+        messages.extend(fill(&mut deps, MOCK_AMOUNT / 2).unwrap().messages);
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|message| **message == execution_fee_transfer)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_accept_admin() {
+        let (_init_result, mut deps) = init_helper(false);
+        let handle_msg = HandleMsg::AcceptAdmin {};
+
+        // = when no admin has been proposed
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("No admin has been proposed.")
+        );
+
+        // = when an admin has been proposed
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ProposeAdmin {
+                address: mock_user_address(),
+            },
+        )
+        .unwrap();
+        // == when called by someone other than the pending admin
+        // == * it raises an Unauthorized error
+        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+        // == when called by the pending admin
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        // == * it sets the pending admin as the admin
+        assert_eq!(config.admin, mock_user_address());
+        // == * it clears the pending admin
+        assert_eq!(config.pending_admin, None);
+        // == * it replaces the old admin with the new admin in addresses_allowed_to_fill
+        assert_eq!(
+            config.addresses_allowed_to_fill,
+            vec![mock_contract().address, mock_user_address()]
+        );
+        // == * the old admin can no longer propose config changes
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ProposeConfigChange {
+                addresses_allowed_to_fill: None,
+                config_change_delay_in_blocks: None,
+                execution_fee: Some(Uint128(MOCK_AMOUNT)),
+                open_filling: None,
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+    }
+
+    #[test]
+    fn test_activity_records() {
+        let (_init_result, mut deps) = init_helper(true);
+        let mut env = mock_env(mock_user_address(), &[]);
+        for _ in 0..3 {
+            create_order_helper(&mut deps);
+        }
+        for position in 0..3 {
+            env.block.height += 10;
+            env.block.time += 60;
+            handle(
+                &mut deps,
+                env.clone(),
+                HandleMsg::CancelOrder {
+                    from_token_address: mock_butt().address,
+                    position: Uint128(position),
+                },
+            )
+            .unwrap();
+        }
+        let query_cancel_records = |filters: Option<ActivityFilters>| -> (Vec<u128>, Uint128) {
+            let res = query(
+                &deps,
+                QueryMsg::CancelRecords {
+                    key: MOCK_VIEWING_KEY.to_string(),
+                    filters,
+                    pagination: pagination_helper(None, 2),
+                },
+            )
+            .unwrap();
+            match from_binary(&res).unwrap() {
+                QueryAnswer::ActivityRecords {
+                    activity_records,
+                    total,
+                    ..
+                } => (
+                    activity_records
+                        .iter()
+                        .map(|activity_record| activity_record.order_position.u128())
+                        .collect(),
+                    total.unwrap(),
+                ),
+                _ => panic!("unexpected"),
+            }
+        };
+
+        // = when there are no filters
+        // = * it returns the records newest first
+        assert_eq!(query_cancel_records(None), (vec![2, 1], Uint128(3)));
+
+        // = when filtering by block height
+        // = * it returns the records within the inclusive range
+        assert_eq!(
+            query_cancel_records(Some(ActivityFilters {
+                from_block_height: Some(env.block.height - 10),
+                ..ActivityFilters::default()
+            })),
+            (vec![2, 1], Uint128(2))
+        );
+        assert_eq!(
+            query_cancel_records(Some(ActivityFilters {
+                to_block_height: Some(env.block.height - 11),
+                ..ActivityFilters::default()
+            })),
+            (vec![0], Uint128(1))
+        );
+
+        // = when filtering by block time
+        // = * it returns the records within the inclusive range
+        assert_eq!(
+            query_cancel_records(Some(ActivityFilters {
+                from_block_time: Some(env.block.time - 60),
+                to_block_time: Some(env.block.time - 60),
+                ..ActivityFilters::default()
+            })),
+            (vec![1], Uint128(1))
+        );
+        // == when the range is empty
+        // == * it returns no records
+        assert_eq!(
+            query_cancel_records(Some(ActivityFilters {
+                from_block_time: Some(env.block.time),
+                to_block_time: Some(env.block.time - 60),
+                ..ActivityFilters::default()
+            })),
+            (vec![], Uint128(0))
+        );
+
+        // = when filtering by activity, order position and pair
+        // = * it returns the matching records
+        assert_eq!(
+            query_cancel_records(Some(ActivityFilters {
+                activity: Some(ActivityKind::Cancelled),
+                order_position: Some(Uint128(1)),
+                pair: Some(TokenPair {
+                    from_token: mock_butt().address,
+                    to_token: mock_token().address,
+                }),
+                ..ActivityFilters::default()
+            })),
+            (vec![1], Uint128(1))
+        );
+        assert_eq!(
+            query_cancel_records(Some(ActivityFilters {
+                activity: Some(ActivityKind::Filled),
+                ..ActivityFilters::default()
+            })),
+            (vec![], Uint128(0))
+        );
+        assert_eq!(
+            query_cancel_records(Some(ActivityFilters {
+                pair: Some(TokenPair {
+                    from_token: mock_token().address,
+                    to_token: mock_butt().address,
+                }),
+                ..ActivityFilters::default()
+            })),
+            (vec![], Uint128(0))
+        );
+    }
+
+    #[test]
+    fn test_authorize_filler() {
+        let (_init_result, mut deps) = init_helper(true);
+        let mut config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(MOCK_AMOUNT),
+                msg: Some(to_binary(&ReceiveMsg::BondFiller {}).unwrap()),
+            },
+        )
+        .unwrap();
+
+        // = when address is allowed to fill
+        // = * it authorizes the address
+        assert!(authorize_filler(&deps.storage, &deps.api, &config, &config.admin).is_ok());
+        // = when address has been removed from the addresses allowed to fill
+        let mut removed_config: Config = config.clone();
+        set_addresses_allowed_to_fill(
+            &mut deps.storage,
+            &deps.api,
+            &mut removed_config,
+            vec![mock_user_address()],
+        )
+        .unwrap();
+        // = * it raises an Unauthorized error
+        assert_eq!(
+            authorize_filler(&deps.storage, &deps.api, &removed_config, &config.admin).unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+        set_addresses_allowed_to_fill(
+            &mut deps.storage,
+            &deps.api,
+            &mut removed_config,
+            config.addresses_allowed_to_fill.clone(),
+        )
+        .unwrap();
+
+        // = when address is not allowed to fill
+        // == when open filling is disabled
+        // == * it raises an Unauthorized error
+        assert_eq!(
+            authorize_filler(&deps.storage, &deps.api, &config, &mock_user_address()).unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+        // == when open filling is enabled
+        config.open_filling = OpenFilling {
+            enabled: true,
+            bond_amount: Uint128(MOCK_AMOUNT + 1),
+            unbonding_period_in_blocks: 5,
+        };
+        // === when filler has not bonded
+        // === * it raises an Unauthorized error
+        assert_eq!(
+            authorize_filler(&deps.storage, &deps.api, &config, &mock_sscrt().address).unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+        // === when filler has bonded less than the bond amount
+        // === * it raises an Unauthorized error
+        assert_eq!(
+            authorize_filler(&deps.storage, &deps.api, &config, &mock_user_address()).unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+        // === when filler has bonded the bond amount
+        config.open_filling.bond_amount = Uint128(MOCK_AMOUNT);
+        // === * it authorizes the filler
+        assert!(authorize_filler(&deps.storage, &deps.api, &config, &mock_user_address()).is_ok());
+        // === when filler is disabled
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetFillerDisabled {
+                address: mock_user_address(),
+                disabled: true,
+            },
+        )
+        .unwrap();
+        // === * it raises an Unauthorized error
+        assert_eq!(
+            authorize_filler(&deps.storage, &deps.api, &config, &mock_user_address()).unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+    }
+
+    #[test]
+    fn test_best_route() {
+        let (_init_result, mut deps) = init_helper(true);
+        create_order_helper(&mut deps);
+        let receive_msg = ReceiveMsg::CreateOrder {
+            to_amount: Uint128(MOCK_AMOUNT / 2),
+            to_token: mock_butt().address,
+        };
+        handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(MOCK_AMOUNT),
+                msg: Some(to_binary(&receive_msg).unwrap()),
+            },
+        )
+        .unwrap();
+        let limit_order_hop = |from_token: SecretContract, position: u128| -> Hop {
+            Hop {
+                from_token,
+                trade_smart_contract: mock_contract(),
+                position: Some(Uint128(position)),
+            }
+        };
+
+        // = when the token is not registered
+        // = * it raises an error
+        let query_result = query(
+            &deps,
+            QueryMsg::BestRoute {
+                token: mock_sscrt().address,
+                borrow_amount: Uint128(MOCK_AMOUNT),
+                max_hops: None,
+            },
+        );
+        assert_eq!(
+            query_result.unwrap_err(),
+            StdError::generic_err("Token is not registered.")
+        );
+
+        // = when max hops is not the route length
+        // = * it raises an error
+        for max_hops in [0, 1, ROUTE_HOPS as u8 + 1].iter() {
+            let query_result = query(
+                &deps,
+                QueryMsg::BestRoute {
+                    token: mock_butt().address,
+                    borrow_amount: Uint128(MOCK_AMOUNT),
+                    max_hops: Some(*max_hops),
+                },
+            );
+            assert_eq!(
+                query_result.unwrap_err(),
+                StdError::generic_err("Routes must be 2 hops.")
+            );
+        }
+
+        // = when the token is registered
+        // == when no route can be completed
+        // == * it returns no route
+        let res = query(
+            &deps,
+            QueryMsg::BestRoute {
+                token: mock_butt().address,
+                borrow_amount: Uint128(0),
+                max_hops: None,
+            },
+        )
+        .unwrap();
+        match from_binary(&res).unwrap() {
+            QueryAnswer::BestRoute { route } => assert_eq!(route, None),
+            _ => panic!("unexpected"),
+        }
+        // == when a route through limit orders can be completed
+        // === when an AMM pair route can not be simulated
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetAmmPairs {
+                amm_pairs: vec![AmmPair {
+                    contract: mock_sscrt(),
+                    token_a: mock_butt().address,
+                    token_b: mock_token().address,
+                }],
+            },
+        )
+        .unwrap();
+        // === * it returns the limit order route with the same amounts as SimulateRoute
+        let res = query(
+            &deps,
+            QueryMsg::BestRoute {
+                token: mock_butt().address,
+                borrow_amount: Uint128(MOCK_AMOUNT / 4),
+                max_hops: None,
+            },
+        )
+        .unwrap();
+        let route: RouteQuote = match from_binary(&res).unwrap() {
+            QueryAnswer::BestRoute { route } => route.unwrap(),
+            _ => panic!("unexpected"),
+        };
+        let hops: VecDeque<Hop> = vec![
+            limit_order_hop(mock_butt(), 1),
+            limit_order_hop(mock_token(), 0),
+        ]
+        .into_iter()
+        .collect();
+        let res = query(
+            &deps,
+            QueryMsg::SimulateRoute {
+                borrow_amount: Uint128(MOCK_AMOUNT / 4),
+                hops: hops.clone(),
+                minimum_acceptable_amount: None,
+            },
+        )
+        .unwrap();
+        match from_binary(&res).unwrap() {
+            QueryAnswer::SimulateRoute {
+                hops,
+                final_amount,
+                excess,
+                ..
+            } => {
+                assert_eq!(route.hops, hops);
+                assert_eq!(route.final_amount, final_amount.unwrap());
+                assert_eq!(route.excess, excess);
+            }
+            _ => panic!("unexpected"),
+        }
+        // === * it returns the message to submit the route
+        assert_eq!(
+            route.handle_msg,
+            HandleMsg::HandleFirstHop {
+                borrow_amount: Uint128(MOCK_AMOUNT / 4),
+                hops,
+                minimum_acceptable_amount: None,
+            }
+        );
+        // === * it gives the excess of a route through two limit orders to the creator
+        assert_eq!(route.excess_to_filler, Uint128(0));
+        // === when max hops is the route length
+        // === * it returns the same route
+        let res = query(
+            &deps,
+            QueryMsg::BestRoute {
+                token: mock_butt().address,
+                borrow_amount: Uint128(MOCK_AMOUNT / 4),
+                max_hops: Some(ROUTE_HOPS as u8),
+            },
+        )
+        .unwrap();
+        match from_binary(&res).unwrap() {
+            QueryAnswer::BestRoute {
+                route: max_hops_route,
+            } => {
+                assert_eq!(max_hops_route, Some(route.clone()))
+            }
+            _ => panic!("unexpected"),
+        }
+        // === when a route with a smaller final amount pays the filler an execution fee
+        handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(MOCK_AMOUNT),
+                msg: Some(
+                    to_binary(&ReceiveMsg::CreateOrder {
+                        to_amount: Uint128(MOCK_AMOUNT / 4 * 3),
+                        to_token: mock_butt().address,
+                    })
+                    .unwrap(),
+                ),
+            },
+        )
+        .unwrap();
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: mock_execution_fee(),
+                msg: Some(to_binary(&ReceiveMsg::SetExecutionFeeForOrder {}).unwrap()),
+            },
+        )
+        .unwrap();
+        let res = query(
+            &deps,
+            QueryMsg::BestRoute {
+                token: mock_butt().address,
+                borrow_amount: Uint128(MOCK_AMOUNT / 4),
+                max_hops: None,
+            },
+        )
+        .unwrap();
+        let fee_route: RouteQuote = match from_binary(&res).unwrap() {
+            QueryAnswer::BestRoute { route } => route.unwrap(),
+            _ => panic!("unexpected"),
+        };
+        // === * it returns the route that pays the filler the most
+        assert!(fee_route.final_amount < route.final_amount);
+        assert_eq!(fee_route.hops[0].execution_fee, Some(mock_execution_fee()));
+        assert_eq!(
+            fee_route.handle_msg,
+            HandleMsg::HandleFirstHop {
+                borrow_amount: Uint128(MOCK_AMOUNT / 4),
+                hops: vec![
+                    limit_order_hop(mock_butt(), 2),
+                    limit_order_hop(mock_token(), 0),
+                ]
+                .into_iter()
+                .collect(),
+                minimum_acceptable_amount: None,
+            }
+        );
+        // === when a pair has more open orders than are tried
+        for _ in 0..ROUTE_ORDERS_PER_PAIR {
+            handle(
+                &mut deps,
+                mock_env(mock_token().address, &[]),
+                HandleMsg::Receive {
+                    sender: mock_user_address(),
+                    from: mock_user_address(),
+                    amount: Uint128(MOCK_AMOUNT),
+                    msg: Some(
+                        to_binary(&ReceiveMsg::CreateOrder {
+                            to_amount: Uint128(MOCK_AMOUNT / 5 * 3),
+                            to_token: mock_butt().address,
+                        })
+                        .unwrap(),
+                    ),
+                },
+            )
+            .unwrap();
+        }
+        let res = query(
+            &deps,
+            QueryMsg::BestRoute {
+                token: mock_butt().address,
+                borrow_amount: Uint128(MOCK_AMOUNT / 4),
+                max_hops: None,
+            },
+        )
+        .unwrap();
+        // === * it only tries the best priced ones, so the pricier order with the fee is left out
+        match from_binary(&res).unwrap() {
+            QueryAnswer::BestRoute {
+                route: capped_route,
+            } => {
+                assert_eq!(capped_route, Some(route))
+            }
+            _ => panic!("unexpected"),
+        }
+    }
+
+    #[test]
+    fn test_bond_filler() {
+        let (_init_result, mut deps) = init_helper(false);
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(MOCK_AMOUNT),
+            msg: Some(to_binary(&ReceiveMsg::BondFiller {}).unwrap()),
+        };
+
+        // = when token sent in is not BUTT
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Bonds must be made in BUTT.")
+        );
+
+        // = when token sent in is BUTT
+        // == when BUTT is not registered
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("BUTT is not registered.")
+        );
+        // == when BUTT is registered
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::RegisterTokens {
+                tokens: vec![mock_butt()],
+                viewing_key: MOCK_VIEWING_KEY.to_string(),
+            },
+        )
+        .unwrap();
+        handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        handle(&mut deps, mock_env(mock_butt().address, &[]), handle_msg).unwrap();
+        // == * it adds to the filler's bonded amount
+        let filler: Filler = read_filler(
+            &deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(filler.address, mock_user_address());
+        assert_eq!(filler.bonded_amount, Uint128(MOCK_AMOUNT * 2));
+        // == * it increases the sum balance for BUTT
+        assert_eq!(
+            read_registered_token(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_butt().address).unwrap()
+            )
+            .unwrap()
+            .sum_balance,
+            Uint128(MOCK_AMOUNT * 2)
+        );
+    }
+
+    #[test]
+    fn test_cancel_config_change() {
+        let (_init_result, mut deps) = init_helper(false);
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ProposeConfigChange {
+                addresses_allowed_to_fill: None,
+                config_change_delay_in_blocks: None,
+                execution_fee: Some(Uint128(MOCK_AMOUNT)),
+                open_filling: None,
+            },
+        )
+        .unwrap();
+        let handle_msg = HandleMsg::CancelConfigChange { id: Uint128(0) };
+
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // = when called by the admin
+        // == when the config change does not exist
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::CancelConfigChange { id: Uint128(1) },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Config change does not exist.")
+        );
+        // == when the config change exists
+        handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg).unwrap();
+        // == * it removes the pending config change
+        assert_eq!(read_pending_config_change(&deps.storage, 0), None);
+        // == * it can no longer be executed
+        let mut env = mock_env(MOCK_ADMIN, &[]);
+        env.block.height += DEFAULT_CONFIG_CHANGE_DELAY_IN_BLOCKS;
+        let handle_result = handle(
+            &mut deps,
+            env,
+            HandleMsg::ExecuteConfigChange { id: Uint128(0) },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Config change does not exist.")
+        );
+        // == when the config change is the cursor of a page of pending config changes
+        for _ in 0..2 {
+            handle(
+                &mut deps,
+                mock_env(MOCK_ADMIN, &[]),
+                HandleMsg::ProposeConfigChange {
+                    addresses_allowed_to_fill: None,
+                    config_change_delay_in_blocks: None,
+                    execution_fee: Some(Uint128(MOCK_AMOUNT)),
+                    open_filling: None,
+                },
+            )
+            .unwrap();
+        }
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::CancelConfigChange { id: Uint128(1) },
+        )
+        .unwrap();
+        // == * the next page still starts after it
+        let res = query(
+            &deps,
+            QueryMsg::PendingConfigChanges {
+                pagination: pagination_helper(Some(1), 1),
+            },
+        )
+        .unwrap();
+        match from_binary(&res).unwrap() {
+            QueryAnswer::PendingConfigChanges {
+                pending_config_changes,
+                total,
+                next_start_after,
+            } => {
+                assert_eq!(pending_config_changes.len(), 1);
+                assert_eq!(pending_config_changes[0].id, Uint128(2));
+                assert_eq!(total, Some(Uint128(1)));
+                assert_eq!(next_start_after, None);
+            }
+            _ => panic!("unexpected"),
+        };
+    }
+
+    #[test]
+    fn test_cancel_order() {
+        let (_init_result, mut deps) = init_helper(true);
+        let env = mock_env(mock_user_address(), &[]);
+
+        // = when order at position does not exist
+        let mut handle_msg = HandleMsg::CancelOrder {
+            from_token_address: mock_token().address,
+            position: Uint128(0),
+        };
+        let mut handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+
+        // = * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            NotFound {
+                kind: "cw_secret_network_limit_orders::state::Order".to_string(),
+                backtrace: None
+            }
+        );
+
+        // = when order at position exists
+        create_order_helper(&mut deps);
+        // == when token used to cancel doesn't match the from_token
+        handle_result = handle(&mut deps, env.clone(), handle_msg);
+        // == * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("From token address does not match the order's from token.")
+        );
+        // == when token used to cancel matches the from_token
+        handle_msg = HandleMsg::CancelOrder {
+            from_token_address: mock_butt().address,
+            position: Uint128(0),
+        };
+        // === when order is cancelled
+        let mut creator_order = order_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        creator_order.cancelled = true;
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &creator_order.creator,
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+        // === * it raises an error
+        handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Order already cancelled.")
+        );
+        // === when order is filled
+        creator_order.cancelled = false;
+        creator_order.from_amount_filled = creator_order.from_amount;
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &creator_order.creator,
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+        // === * it raises an error
+        handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Order already filled.")
+        );
+        // === when order can be cancelled
+        creator_order.from_amount_filled = Uint128(5);
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &creator_order.creator,
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+        let from_registered_token: RegisteredToken = read_registered_token(
+            &deps.storage,
+            &deps
+                .api
+                .canonical_address(&creator_order.from_token)
+                .unwrap(),
+        )
+        .unwrap();
+        let sum_balance_before_cancel: Uint128 = from_registered_token.sum_balance;
+        handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        // === * it reduces the from token's sum_balance by the unfilled amount
+        let from_registered_token: RegisteredToken = read_registered_token(
+            &deps.storage,
+            &deps
+                .api
+                .canonical_address(&creator_order.from_token)
+                .unwrap(),
+        )
+        .unwrap();
+        let unfilled_amount: Uint128 =
+            (creator_order.from_amount - creator_order.from_amount_filled).unwrap();
+        assert_eq!(
+            from_registered_token.sum_balance,
+            (sum_balance_before_cancel - unfilled_amount).unwrap()
+        );
+
+        // === * it sends the unfilled from token amount back to the creator
+        let handle_result_unwrapped = handle_result.unwrap();
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                deps.api.human_address(&creator_order.creator).unwrap(),
+                unfilled_amount,
+                None,
+                BLOCK_SIZE,
+                from_registered_token.contract_hash.clone(),
+                from_registered_token.address.clone(),
+            )
+            .unwrap()]
+        );
+        // === * it sends the creator order as humanized back as data
+        let creator_order = order_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            handle_result_unwrapped.data,
+            pad_response(Ok(HandleResponse {
+                messages: vec![],
+                log: vec![],
+                data: Some(
+                    to_binary(
+                        &humanize_order(&deps.api, &deps.storage, creator_order.clone()).unwrap()
+                    )
+                    .unwrap()
+                ),
+            }))
+            .unwrap()
+            .data
+        );
+
+        // === * it sets cancelled to true
+        let mut creator_order = order_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        let contract_order = order_at_position(
+            &mut deps.storage,
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+            creator_order.other_storage_position.u128(),
+        )
+        .unwrap();
+        assert_eq!(creator_order.cancelled, true);
+        assert_eq!(contract_order.cancelled, true);
+
+        // ===== * it creates an activity record
+        let (activity_records, total, _) = get_activity_records(
+            &deps.storage,
+            &deps
+                .api
+                .canonical_address(&HumanAddr::from(MOCK_ADMIN))
+                .unwrap(),
+            &Page::new(pagination_helper(None, 50), SortOrder::Descending),
+            PREFIX_CANCEL_RECORDS,
+        )
+        .unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(
+            activity_records[0],
+            ActivityRecord {
+                refund_amount: Some(unfilled_amount),
+                ..ActivityRecord::new(
+                    ActivityKind::Cancelled,
+                    contract_order.position,
+                    mock_butt().address,
+                    mock_token().address,
+                    env.block.height.clone(),
+                    env.block.time
+                )
+            }
+        );
+        // ===== * it creates an activity record for the creator
+        let (activity_records, total, _) = get_activity_records(
+            &deps.storage,
+            &creator_order.creator,
+            &Page::new(pagination_helper(None, 50), SortOrder::Descending),
+            PREFIX_ACTIVITY_RECORDS,
+        )
+        .unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(activity_records[0].activity, ActivityKind::Cancelled);
+        assert_eq!(activity_records[0].order_position, creator_order.position);
+        assert_eq!(activity_records[0].refund_amount, Some(unfilled_amount));
+        // ==== when order has an execution fee
+        creator_order.execution_fee = Some(Uint128(1));
+        creator_order.cancelled = false;
+        creator_order.from_amount_filled = Uint128(999999999999);
+        creator_order.execution_fee_paid = true;
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &creator_order.creator,
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+        // ===== when order is partially filled and the execution fee has been paid
+        // ===== * it does not send the execution fee back to the creator
+        handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap().messages,
+            vec![snip20::transfer_msg(
+                deps.api.human_address(&creator_order.creator).unwrap(),
+                (creator_order.from_amount - creator_order.from_amount_filled).unwrap(),
+                None,
+                BLOCK_SIZE,
+                from_registered_token.contract_hash.clone(),
+                from_registered_token.address.clone(),
+            )
+            .unwrap()]
+        );
+
+        // ===== when order has not been partially filled
+        creator_order.from_amount = Uint128(1);
+        creator_order.execution_fee = Some(Uint128(1));
+        creator_order.cancelled = false;
+        creator_order.from_amount_filled = Uint128(0);
+        creator_order.execution_fee_paid = false;
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &creator_order.creator,
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+
+        // ===== * it sends the execution fee back to the creator
+        handle_result = handle(&mut deps, env.clone(), handle_msg);
+        assert_eq!(
+            handle_result.unwrap().messages,
+            vec![
+                snip20::transfer_msg(
+                    deps.api.human_address(&creator_order.creator).unwrap(),
+                    (creator_order.from_amount - creator_order.from_amount_filled).unwrap(),
+                    None,
+                    BLOCK_SIZE,
+                    from_registered_token.contract_hash,
+                    from_registered_token.address,
+                )
+                .unwrap(),
+                snip20::transfer_msg(
+                    deps.api.human_address(&creator_order.creator).unwrap(),
+                    creator_order.execution_fee.unwrap(),
+                    None,
+                    BLOCK_SIZE,
+                    mock_sscrt().contract_hash,
+                    mock_sscrt().address,
+                )
+                .unwrap()
+            ]
+        );
+        // ===== * it records the execution fee refund
+        let (activity_records, _, _) = get_activity_records(
+            &deps.storage,
+            &creator_order.creator,
+            &Page::new(pagination_helper(None, 1), SortOrder::Descending),
+            PREFIX_ACTIVITY_RECORDS,
+        )
+        .unwrap();
+        assert_eq!(
+            activity_records[0].execution_fee_refund_amount,
+            creator_order.execution_fee
+        );
+    }
+
+    #[test]
+    fn test_candles() {
+        let (_init_result, mut deps) = init_helper(true);
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetCandleIntervals {
+                candle_intervals: vec![3_600],
+            },
+        )
+        .unwrap();
+        // Order at position 0 has a price of 1 and order at position 1 has a price of 2
+        create_order_helper(&mut deps);
+        handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(MOCK_AMOUNT),
+                msg: Some(
+                    to_binary(&ReceiveMsg::CreateOrder {
+                        to_amount: Uint128(MOCK_AMOUNT * 2),
+                        to_token: mock_token().address,
+                    })
+                    .unwrap(),
+                ),
+            },
+        )
+        .unwrap();
+        let fill_msg = |position: u128, amount: u128| -> HandleMsg {
+            HandleMsg::Receive {
+                sender: config.admin.clone(),
+                from: config.admin.clone(),
+                amount: Uint128(amount),
+                msg: Some(
+                    to_binary(&ReceiveMsg::FillOrder {
+                        position: Uint128(position),
+                    })
+                    .unwrap(),
+                ),
+            }
+        };
+        let mut env = mock_env(mock_token().address, &[]);
+        let first_start_time: u64 = env.block.time - env.block.time % 3_600;
+        handle(&mut deps, env.clone(), fill_msg(0, MOCK_AMOUNT / 4)).unwrap();
+        env.block.time += 60;
+        handle(&mut deps, env.clone(), fill_msg(1, MOCK_AMOUNT / 2)).unwrap();
+        env.block.time += 3_600;
+        handle(&mut deps, env.clone(), fill_msg(0, MOCK_AMOUNT / 4)).unwrap();
+        let pair: TokenPair = TokenPair {
+            from_token: mock_butt().address,
+            to_token: mock_token().address,
+        };
+        let first_candle: Candle = Candle {
+            start_time: first_start_time,
+            open: Uint128(PRICE_SCALE),
+            high: Uint128(PRICE_SCALE * 2),
+            low: Uint128(PRICE_SCALE),
+            close: Uint128(PRICE_SCALE * 2),
+            from_volume: Uint128(MOCK_AMOUNT / 2),
+            to_volume: Uint128(MOCK_AMOUNT * 3 / 4),
+        };
+        let second_candle: Candle = Candle {
+            start_time: first_start_time + 3_600,
+            open: Uint128(PRICE_SCALE),
+            high: Uint128(PRICE_SCALE),
+            low: Uint128(PRICE_SCALE),
+            close: Uint128(PRICE_SCALE),
+            from_volume: Uint128(MOCK_AMOUNT / 4),
+            to_volume: Uint128(MOCK_AMOUNT / 4),
+        };
+
+        // = when pair is not registered
+        // = * it raises an error
+        let query_result = query(
+            &deps,
+            QueryMsg::Candles {
+                pair: TokenPair {
+                    from_token: mock_butt().address,
+                    to_token: mock_sscrt().address,
+                },
+                interval: 3_600,
+                from_time: 0,
+                to_time: env.block.time,
+                pagination: pagination_helper(None, 10),
+            },
+        );
+        assert_eq!(
+            query_result.unwrap_err(),
+            StdError::generic_err("Pair is not registered.")
+        );
+
+        // = when time range covers all candles
+        // = * it returns the candles in time order
+        let res = query(
+            &deps,
+            QueryMsg::Candles {
+                pair: pair.clone(),
+                interval: 3_600,
+                from_time: 0,
+                to_time: env.block.time,
+                pagination: pagination_helper(None, 10),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::Candles { candles, total, .. } => {
+                assert_eq!(total, Some(Uint128(2)));
+                assert_eq!(candles, vec![first_candle.clone(), second_candle.clone()]);
+            }
+            _ => panic!("unexpected"),
+        };
+        // == when paging
+        // == * it returns the page within the time range
+        let res = query(
+            &deps,
+            QueryMsg::Candles {
+                pair: pair.clone(),
+                interval: 3_600,
+                from_time: 0,
+                to_time: env.block.time,
+                pagination: pagination_helper(Some(0), 1),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::Candles { candles, .. } => {
+                assert_eq!(candles, vec![second_candle.clone()]);
+            }
+            _ => panic!("unexpected"),
+        };
+
+        // = when time range covers some candles
+        // = * it returns the candles starting within the range
+        let res = query(
+            &deps,
+            QueryMsg::Candles {
+                pair: pair.clone(),
+                interval: 3_600,
+                from_time: first_start_time,
+                to_time: first_start_time + 3_599,
+                pagination: pagination_helper(None, 10),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::Candles { candles, total, .. } => {
+                assert_eq!(total, Some(Uint128(1)));
+                assert_eq!(candles, vec![first_candle]);
+            }
+            _ => panic!("unexpected"),
+        };
+
+        // = when interval has no candles
+        // = * it returns no candles
+        let res = query(
+            &deps,
+            QueryMsg::Candles {
+                pair,
+                interval: 86_400,
+                from_time: 0,
+                to_time: env.block.time,
+                pagination: pagination_helper(None, 10),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::Candles { candles, total, .. } => {
+                assert_eq!(total, Some(Uint128(0)));
+                assert_eq!(candles, vec![]);
+            }
+            _ => panic!("unexpected"),
+        };
+    }
+
+    #[test]
+    fn test_commit_order() {
+        let (_init_result, mut deps) = init_helper(true);
+        let commit_msg = |hash: Binary, amount: u128| -> HandleMsg {
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(amount),
+                msg: Some(to_binary(&ReceiveMsg::CommitOrder { hash }).unwrap()),
+            }
+        };
+        let hash: Binary = commitment_hash(&mock_token().address, Uint128(MOCK_AMOUNT), MOCK_SALT);
+
+        // = when the hash is not a sha256 hash
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            commit_msg(Binary::from(b"mock-hash".to_vec()), MOCK_AMOUNT),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Commitment hash must be a sha256 hash.")
+        );
+
+        // = when the amount is zero
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            commit_msg(hash.clone(), 0),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Amount must be greater than zero.")
+        );
+
+        // = when the commitment is valid
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            commit_msg(hash.clone(), MOCK_AMOUNT),
+        )
+        .unwrap();
+        // = * it returns the position of the commitment
+        match from_binary(&handle_result.data.unwrap()).unwrap() {
+            HandleAnswer::CommitOrder { position } => assert_eq!(position, Uint128(0)),
+            _ => panic!("unexpected"),
+        }
+        // = * it stores the commitment
+        assert_eq!(
+            read_commitment(&deps.storage, 0).unwrap(),
+            Commitment {
+                position: Uint128(0),
+                creator: deps.api.canonical_address(&mock_user_address()).unwrap(),
+                from_token: mock_butt().address,
+                from_amount: Uint128(MOCK_AMOUNT),
+                hash,
+                committed_at_block_height: mock_env(MOCK_ADMIN, &[]).block.height,
+                closed: false,
+            }
+        );
+        assert_eq!(read_commitments_count(&deps.storage), 1);
+        // = * it increases the sum balance of the from token
+        assert_eq!(
+            read_registered_token(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_butt().address).unwrap()
+            )
+            .unwrap()
+            .sum_balance,
+            Uint128(MOCK_AMOUNT)
+        );
+        // = * it does not create an order
+        assert_eq!(
+            storage_count(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_user_address()).unwrap(),
+                PREFIX_ORDERS_COUNT
+            )
+            .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_complete_import() {
+        let (_init_result, mut deps) = init_helper_with_importing(true, true);
+        let handle_msg = HandleMsg::CompleteImport {
+            key: MOCK_VIEWING_KEY.to_string(),
+        };
+
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // = when called by the admin
+        // == when a token balance is less than its sum balance
+        let token_address_canonical: CanonicalAddr =
+            deps.api.canonical_address(&mock_token().address).unwrap();
+        let mut registered_token: RegisteredToken =
+            read_registered_token(&deps.storage, &token_address_canonical).unwrap();
+        registered_token.sum_balance = Uint128(MOCK_AMOUNT + 1);
+        write_registered_token(
+            &mut deps.storage,
+            &token_address_canonical,
+            &registered_token,
+        )
+        .unwrap();
+        // == * it raises an error
+        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Token balance is less than sum balance.")
+        );
+        // == when token balances cover their sum balances
+        registered_token.sum_balance = Uint128(MOCK_AMOUNT);
+        write_registered_token(
+            &mut deps.storage,
+            &token_address_canonical,
+            &registered_token,
+        )
+        .unwrap();
+        handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg.clone()).unwrap();
+        // == * it completes the import
+        assert!(read_import_complete(&deps.storage));
+        // == * it allows orders to be created
+        create_order_helper(&mut deps);
+
+        // = when import is complete
+        // = * it raises an error
+        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Import is complete.")
+        );
+    }
+
+    #[test]
+    fn test_commitments() {
+        let (_init_result, mut deps) = init_helper(true);
+        for _ in 0..3 {
+            commit_order_helper(&mut deps);
+        }
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::ReclaimCommitment {
+                position: Uint128(1),
+            },
+        )
+        .unwrap();
+        let query_commitments =
+            |pagination: Option<Pagination>| -> (Vec<u128>, Option<Uint128>, Uint128) {
+                let res = query(
+                    &deps,
+                    QueryMsg::Commitments {
+                        address: mock_user_address(),
+                        key: MOCK_VIEWING_KEY.to_string(),
+                        pagination,
+                    },
+                )
+                .unwrap();
+                match from_binary(&res).unwrap() {
+                    QueryAnswer::Commitments {
+                        commitments,
+                        total,
+                        next_start_after,
+                        ..
+                    } => (
+                        commitments
+                            .iter()
+                            .map(|commitment| commitment.position.u128())
+                            .collect(),
+                        next_start_after,
+                        total.unwrap(),
+                    ),
+                    _ => panic!("unexpected"),
+                }
+            };
+
+        // = when pagination is not set
+        // = * it returns the open commitments newest first
+        assert_eq!(query_commitments(None), (vec![2, 0], None, Uint128(2)));
+
+        // = when there are more open commitments than the limit
+        // = * it returns the cursor for the next page
+        assert_eq!(
+            query_commitments(pagination_helper(None, 1)),
+            (vec![2], Some(Uint128(2)), Uint128(2))
+        );
+        // = * it skips closed commitments on the next page
+        assert_eq!(
+            query_commitments(pagination_helper(Some(2), 1)),
+            (vec![0], None, Uint128(2))
+        );
+    }
+
+    #[test]
+    fn test_config() {
+        let (_init_result, deps) = init_helper(false);
+
+        let res = query(&deps, QueryMsg::Config {}).unwrap();
+        let (value, tokens): (Config, Vec<TokenMetadata>) = match from_binary(&res).unwrap() {
+            QueryAnswer::Config { config, tokens } => (config, tokens),
+            _ => panic!("unexpected"),
+        };
+        assert_eq!(
+            Config {
+                addresses_allowed_to_fill: vec![
+                    HumanAddr::from(MOCK_ADMIN),
+                    mock_contract().address
+                ],
+                admin: HumanAddr::from(MOCK_ADMIN),
+                butt: mock_butt(),
+                candle_intervals: DEFAULT_CANDLE_INTERVALS.to_vec(),
+                config_change_delay_in_blocks: DEFAULT_CONFIG_CHANGE_DELAY_IN_BLOCKS,
+                execution_fee: mock_execution_fee(),
+                open_filling: OpenFilling::default(),
+                pause_flags: PauseFlags::default(),
+                pending_admin: None,
+                sscrt: mock_sscrt(),
+            },
+            value
+        );
+        // * it includes the metadata of BUTT and SSCRT
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|token| token.address.clone())
+                .collect::<Vec<HumanAddr>>(),
+            vec![mock_butt().address, mock_sscrt().address]
+        );
+    }
+
+    #[test]
+    fn test_create_order() {
+        let (_init_result, mut deps) = init_helper(true);
+
+        // = when to_token isn't registered
+        let receive_msg = ReceiveMsg::CreateOrder {
+            to_amount: Uint128(MOCK_AMOUNT),
+            to_token: mock_user_address(),
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(MOCK_AMOUNT),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("To token is not registered.")
+        );
+
+        // = when to_token is registered
+        let receive_msg = ReceiveMsg::CreateOrder {
+            to_amount: Uint128(MOCK_AMOUNT),
+            to_token: mock_token().address,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(MOCK_AMOUNT),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+
+        // == when the pair is disabled
+        let mut pair: Pair = mock_pair(mock_butt().address, mock_token().address);
+        pair.enabled = false;
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetPairs {
+                pairs: vec![pair.clone()],
+            },
+        )
+        .unwrap();
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Pair is disabled.")
+        );
+        // == when from_amount is below the pair minimum
+        pair.enabled = true;
+        pair.minimum_from_amount = Uint128(MOCK_AMOUNT + 1);
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetPairs {
+                pairs: vec![pair.clone()],
+            },
+        )
+        .unwrap();
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("From amount is below the pair minimum.")
+        );
+        // == when the price is not a multiple of the pair tick size
+        pair.minimum_from_amount = Uint128(MOCK_AMOUNT);
+        pair.price_tick_size = Uint128(PRICE_SCALE / 10 * 3);
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetPairs {
+                pairs: vec![pair.clone()],
+            },
+        )
+        .unwrap();
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Price must be a multiple of the pair tick size.")
+        );
+        pair.price_tick_size = Uint128(PRICE_SCALE / 10);
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetPairs { pairs: vec![pair] },
+        )
+        .unwrap();
+
+        // == when order is created
+        // === * it increases the sum balance for the from_token
+        assert_eq!(
+            read_registered_token(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_butt().address).unwrap()
+            )
+            .unwrap()
+            .sum_balance,
+            Uint128(0)
+        );
+        let handle_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            read_registered_token(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_butt().address).unwrap()
+            )
+            .unwrap()
+            .sum_balance,
+            Uint128(MOCK_AMOUNT)
+        );
+        // === * it sends the humanized creator order back as data
+        let order: Order = Order {
+            position: Uint128(0),
+            execution_fee: None,
+            execution_fee_paid: false,
+            other_storage_position: Uint128(0),
+            from_token: mock_butt().address,
+            to_token: mock_token().address,
+            creator: deps.api.canonical_address(&mock_user_address()).unwrap(),
+            from_amount: Uint128(MOCK_AMOUNT),
+            from_amount_filled: Uint128(0),
+            net_to_amount: Uint128(MOCK_AMOUNT),
+            net_to_amount_filled: Uint128(0),
+            cancelled: false,
+            fee: Uint128(0),
+            created_at_block_time: mock_env(MOCK_ADMIN, &[]).block.time,
+            created_at_block_height: mock_env(MOCK_ADMIN, &[]).block.height,
+        };
+        assert_eq!(
+            handle_unwrapped.data,
+            pad_response(Ok(HandleResponse {
+                messages: vec![],
+                log: vec![],
+                data: Some(
+                    to_binary(&humanize_order(&deps.api, &deps.storage, order.clone()).unwrap())
+                        .unwrap()
+                ),
+            }))
+            .unwrap()
+            .data
+        );
+
+        // === * it stores the order for the creator
+        // === * it stores the order for the smart_contract
+        assert_eq!(
+            order_at_position(
+                &mut deps.storage,
+                &deps.api.canonical_address(&mock_user_address()).unwrap(),
+                0
+            )
+            .unwrap(),
+            order
+        );
+        assert_eq!(
+            order_at_position(
+                &mut deps.storage,
+                &deps
+                    .api
+                    .canonical_address(&mock_contract().address)
+                    .unwrap(),
+                0
+            )
+            .unwrap(),
+            order
+        );
+        // === * it creates an activity record for the creator
+        let env = mock_env(MOCK_ADMIN, &[]);
+        let (activity_records, total, _) = get_activity_records(
+            &deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            &Page::new(pagination_helper(None, 50), SortOrder::Descending),
+            PREFIX_ACTIVITY_RECORDS,
+        )
+        .unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(
+            activity_records[0],
+            ActivityRecord::new(
+                ActivityKind::Created,
+                Uint128(0),
+                mock_butt().address,
+                mock_token().address,
+                env.block.height,
+                env.block.time
+            )
+        );
+    }
+
+    #[test]
+    fn test_disable_token() {
+        let (_init_result, mut deps) = init_helper(true);
+        create_order_helper(&mut deps);
+        let handle_msg = HandleMsg::DisableToken {
+            address: mock_token().address,
+        };
+
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // = when called by the admin
+        // == when token is not registered
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::DisableToken {
+                address: mock_sscrt().address,
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Token is not registered.")
+        );
+        // == when token is registered
+        handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg).unwrap();
+        // == * it disables the token
+        assert!(
+            read_registered_token(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_token().address).unwrap()
+            )
+            .unwrap()
+            .disabled
+        );
+        // == * it does not allow orders to be created to the token
+        let create_order_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(MOCK_AMOUNT),
+            msg: Some(
+                to_binary(&ReceiveMsg::CreateOrder {
+                    to_amount: Uint128(MOCK_AMOUNT),
+                    to_token: mock_token().address,
+                })
+                .unwrap(),
+            ),
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            create_order_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("To token is disabled.")
+        );
+        // == * it does not allow orders to be created from the token
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(MOCK_AMOUNT),
+                msg: Some(
+                    to_binary(&ReceiveMsg::CreateOrder {
+                        to_amount: Uint128(MOCK_AMOUNT),
+                        to_token: mock_butt().address,
+                    })
+                    .unwrap(),
+                ),
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("From token is disabled.")
+        );
+        // == * it allows existing orders with the token to be cancelled
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::CancelOrder {
+                from_token_address: mock_butt().address,
+                position: Uint128(0),
+            },
+        )
+        .unwrap();
+        // == * registering the token again enables it
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::RegisterTokens {
+                tokens: vec![mock_token()],
+                viewing_key: MOCK_VIEWING_KEY.to_string(),
+            },
+        )
+        .unwrap();
+        handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            create_order_msg,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_execute_config_change() {
+        let (_init_result, mut deps) = init_helper(false);
+        let env = mock_env(mock_user_address(), &[]);
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ProposeConfigChange {
+                addresses_allowed_to_fill: Some(vec![mock_user_address()]),
+                config_change_delay_in_blocks: None,
+                execution_fee: Some(Uint128(MOCK_AMOUNT)),
+                open_filling: None,
+            },
+        )
+        .unwrap();
+        let handle_msg = HandleMsg::ExecuteConfigChange { id: Uint128(0) };
+
+        // = when the config change does not exist
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ExecuteConfigChange { id: Uint128(1) },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Config change does not exist.")
+        );
+
+        // = when the config change exists
+        // == when called by a non-admin
+        // == * it raises an Unauthorized error
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+        // == when called by the admin
+        // === when the delay has not passed
+        // === * it raises an error
+        let mut admin_env = mock_env(MOCK_ADMIN, &[]);
+        admin_env.block.height += DEFAULT_CONFIG_CHANGE_DELAY_IN_BLOCKS - 1;
+        let handle_result = handle(&mut deps, admin_env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Config change is still timelocked.")
+        );
+        // === when the delay has passed
+        admin_env.block.height += 1;
+        handle(&mut deps, admin_env, handle_msg).unwrap();
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        // === * it updates the addresses_allowed_to_fill and adds admin and contract address
+        assert_eq!(
+            config.addresses_allowed_to_fill,
+            vec![
+                mock_user_address(),
+                env.contract.address.clone(),
+                config.admin.clone()
+            ]
+        );
+        // === * it updates the execution_fee
+        assert_eq!(config.execution_fee, Uint128(MOCK_AMOUNT));
+        // === * it removes the pending config change
+        assert_eq!(read_pending_config_change(&deps.storage, 0), None);
+
+        // = when called by a filler manager
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::GrantRole {
+                role: Role::FillerManager,
+                address: mock_user_address(),
+            },
+        )
+        .unwrap();
+        handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::ProposeConfigChange {
+                addresses_allowed_to_fill: Some(vec![]),
+                config_change_delay_in_blocks: None,
+                execution_fee: None,
+                open_filling: None,
+            },
+        )
+        .unwrap();
+        let mut env = env;
+        env.block.height += DEFAULT_CONFIG_CHANGE_DELAY_IN_BLOCKS;
+        handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::ExecuteConfigChange { id: Uint128(1) },
+        )
+        .unwrap();
+        // = * it updates the addresses_allowed_to_fill
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(
+            config.addresses_allowed_to_fill,
+            vec![env.contract.address, config.admin]
+        );
+
+        // = when changing the config change delay
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ProposeConfigChange {
+                addresses_allowed_to_fill: None,
+                config_change_delay_in_blocks: Some(1),
+                execution_fee: None,
+                open_filling: None,
+            },
+        )
+        .unwrap();
+        let mut admin_env = mock_env(MOCK_ADMIN, &[]);
+        admin_env.block.height += DEFAULT_CONFIG_CHANGE_DELAY_IN_BLOCKS;
+        handle(
+            &mut deps,
+            admin_env.clone(),
+            HandleMsg::ExecuteConfigChange { id: Uint128(2) },
+        )
+        .unwrap();
+        // = * it updates the config change delay
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(config.config_change_delay_in_blocks, 1);
+        // = * later config changes use the new delay
+        let handle_result = handle(
+            &mut deps,
+            admin_env.clone(),
+            HandleMsg::ProposeConfigChange {
+                addresses_allowed_to_fill: None,
+                config_change_delay_in_blocks: None,
+                execution_fee: Some(mock_execution_fee()),
+                open_filling: None,
+            },
+        )
+        .unwrap();
+        let pending_config_change: PendingConfigChange =
+            from_binary(&handle_result.data.unwrap()).unwrap();
+        assert_eq!(
+            pending_config_change.executable_at_block_height,
+            admin_env.block.height + 1
+        );
+    }
+
+    #[test]
+    fn test_export_state() {
+        let (_init_result, mut deps) = init_helper(true);
+        create_order_helper(&mut deps);
+        create_order_helper(&mut deps);
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::CancelOrder {
+                from_token_address: mock_butt().address,
+                position: Uint128(0),
+            },
+        )
+        .unwrap();
+
+        // = when exporting orders
+        // = * it returns the contract's orders in position order
+        let contract_canonical_address: CanonicalAddr = deps
+            .api
+            .canonical_address(&mock_contract().address)
+            .unwrap();
+        let res = query(
+            &deps,
+            QueryMsg::ExportState {
+                key: MOCK_VIEWING_KEY.to_string(),
+                section: StateSection::Orders,
+                pagination: pagination_helper(Some(0), 1),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::ExportState {
+                state_page, total, ..
+            } => {
+                assert_eq!(total, Uint128(2));
+                assert_eq!(
+                    state_page,
+                    StatePage::Orders {
+                        orders: vec![order_at_position(
+                            &deps.storage,
+                            &contract_canonical_address,
+                            1
+                        )
+                        .unwrap()]
+                    }
+                );
+            }
+            _ => panic!("unexpected"),
+        };
+
+        // = when exporting cancel records
+        // = * it returns the admin's cancel records
+        let res = query(
+            &deps,
+            QueryMsg::ExportState {
+                key: MOCK_VIEWING_KEY.to_string(),
+                section: StateSection::CancelRecords,
+                pagination: pagination_helper(None, 10),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::ExportState {
+                state_page, total, ..
+            } => {
+                assert_eq!(total, Uint128(1));
+                match state_page {
+                    StatePage::CancelRecords { cancel_records } => {
+                        assert_eq!(cancel_records.len(), 1);
+                        assert_eq!(cancel_records[0].order_position, Uint128(0));
+                    }
+                    _ => panic!("unexpected"),
+                }
+            }
+            _ => panic!("unexpected"),
+        };
+
+        // = when exporting fill records
+        // = * it returns no fill records
+        let res = query(
+            &deps,
+            QueryMsg::ExportState {
+                key: MOCK_VIEWING_KEY.to_string(),
+                section: StateSection::FillRecords,
+                pagination: pagination_helper(None, 10),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::ExportState {
+                state_page, total, ..
+            } => {
+                assert_eq!(total, Uint128(0));
+                assert_eq!(
+                    state_page,
+                    StatePage::FillRecords {
+                        fill_records: vec![]
+                    }
+                );
+            }
+            _ => panic!("unexpected"),
+        };
+
+        // = when exporting registered tokens
+        // = * it returns the registered tokens in the order they were registered
+        let res = query(
+            &deps,
+            QueryMsg::ExportState {
+                key: MOCK_VIEWING_KEY.to_string(),
+                section: StateSection::RegisteredTokens,
+                pagination: pagination_helper(None, 10),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::ExportState {
+                state_page,
+                total,
+                tokens,
+                ..
+            } => {
+                assert_eq!(total, Uint128(2));
+                // = * it includes the metadata of the tokens on the page
+                assert_eq!(tokens.len(), 2);
+                match state_page {
+                    StatePage::RegisteredTokens { registered_tokens } => {
+                        assert_eq!(registered_tokens[0].address, mock_butt().address);
+                        assert_eq!(registered_tokens[0].sum_balance, Uint128(MOCK_AMOUNT));
+                        assert_eq!(registered_tokens[1].address, mock_token().address);
+                    }
+                    _ => panic!("unexpected"),
+                }
+            }
+            _ => panic!("unexpected"),
+        };
+
+        // = when exporting pair stats
+        // = * it returns stats for every pair, including pairs without fills
+        let res = query(
+            &deps,
+            QueryMsg::ExportState {
+                key: MOCK_VIEWING_KEY.to_string(),
+                section: StateSection::PairStats,
+                pagination: pagination_helper(None, 10),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::ExportState {
+                state_page, total, ..
+            } => {
+                assert_eq!(total, Uint128(2));
+                match state_page {
+                    StatePage::PairStats { pair_stats } => {
+                        assert_eq!(pair_stats[0].from_token, mock_butt().address);
+                        assert_eq!(pair_stats[0].to_token, mock_token().address);
+                        assert_eq!(pair_stats[0].trade_count, Uint128(0));
+                        assert_eq!(pair_stats[1].from_token, mock_token().address);
+                    }
+                    _ => panic!("unexpected"),
+                }
+            }
+            _ => panic!("unexpected"),
+        };
+    }
+
+    #[test]
+    fn test_fill_order() {
+        let (_init_result, mut deps) = init_helper(true);
+        let env = mock_env(mock_butt().address, &[]);
+
+        // when called by a non-admin
+        let receive_msg = ReceiveMsg::FillOrder {
+            position: Uint128(0),
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(MOCK_AMOUNT),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        // * it raises an error
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when called by an address that's allowed
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        // = when amount sent in is zero
+        let handle_msg = HandleMsg::Receive {
+            sender: config.admin.clone(),
+            from: env.contract.address.clone(),
+            amount: Uint128(0),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        let handle_result = handle(&mut deps, env.clone(), handle_msg);
+        // = * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Amount must be greater than zero.")
+        );
+        // = when amount sent in is positive
+        let handle_msg = HandleMsg::Receive {
+            sender: config.admin.clone(),
+            from: config.admin.clone(),
+            amount: Uint128(1),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        // == when order does not exist
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        // == * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            NotFound {
+                kind: "cw_secret_network_limit_orders::state::Order".to_string(),
+                backtrace: None
+            }
+        );
+        // == when order exists
+        create_order_helper(&mut deps);
+        // === when to_token does not match the token sent in
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        // === * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("To token does not match the token sent in.")
+        );
+        // === when to token matches the token sent in
+        // ==== when order is cancelled
+        let mut creator_order = order_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        creator_order.cancelled = true;
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &creator_order.creator,
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+
+        // ==== * it raises an error
+        let handle_result = handle(&mut deps, mock_env(mock_token().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Order already cancelled.")
+        );
+        // ==== when order is not cancelled
+        creator_order.cancelled = false;
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &creator_order.creator,
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+
+        // ===== when amount sent in is greater than unfilled amount
+        let handle_msg = HandleMsg::Receive {
+            sender: config.admin.clone(),
+            from: config.admin.clone(),
+            amount: Uint128(MOCK_AMOUNT + 1),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        // ===== * it raises an error
+        let handle_result = handle(&mut deps, mock_env(mock_token().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Amount is greater than unfilled amount.")
+        );
+
+        // ===== when amount sent in is less than or equal to the net unfilled to amount
+        let handle_msg = HandleMsg::Receive {
+            sender: config.admin.clone(),
+            from: config.admin.clone(),
+            amount: Uint128(MOCK_AMOUNT / 2),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            handle_msg.clone(),
+        );
+        // ===== * it updates the from amount filled for both orders
+        // ===== * it updates the net to amount filled
+        let mut creator_order = order_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        let contract_order = order_at_position(
+            &mut deps.storage,
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+            creator_order.other_storage_position.u128(),
+        )
+        .unwrap();
+        assert_eq!(
+            creator_order.from_amount_filled,
+            creator_order
+                .from_amount
+                .multiply_ratio(Uint128(1), Uint128(2))
+        );
+        assert_eq!(
+            contract_order.from_amount_filled,
+            contract_order
+                .from_amount
+                .multiply_ratio(Uint128(1), Uint128(2))
+        );
+        assert_eq!(
+            creator_order.net_to_amount_filled,
+            creator_order
+                .net_to_amount
+                .multiply_ratio(Uint128(1), Uint128(2))
+        );
+        assert_eq!(
+            contract_order.net_to_amount_filled,
+            contract_order
+                .net_to_amount
+                .multiply_ratio(Uint128(1), Uint128(2))
+        );
+
+        // ===== * it sends the correct ratio of the from_token to the admin
+        // ===== * it sends the amount to the creator
+        assert_eq!(
+            handle_result.unwrap().messages,
+            vec![
+                snip20::send_msg(
+                    config.admin.clone(),
+                    Uint128(MOCK_AMOUNT / 2),
+                    None,
+                    None,
+                    BLOCK_SIZE,
+                    mock_butt().contract_hash,
+                    mock_butt().address,
+                )
+                .unwrap(),
+                snip20::transfer_msg(
+                    mock_user_address(),
+                    Uint128(MOCK_AMOUNT / 2),
+                    None,
+                    BLOCK_SIZE,
+                    mock_token().contract_hash,
+                    mock_token().address,
+                )
+                .unwrap(),
+            ]
+        );
+
+        // ===== * it updates the from tokens sum balance
+        let from_registered_token: RegisteredToken = read_registered_token(
+            &deps.storage,
+            &deps
+                .api
+                .canonical_address(&creator_order.from_token)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            from_registered_token.sum_balance,
+            creator_order
+                .net_to_amount
+                .multiply_ratio(Uint128(1), Uint128(2))
+        );
+        // ===== * it does not update the to tokens sum balance
+        let to_registered_token: RegisteredToken = read_registered_token(
+            &deps.storage,
+            &deps.api.canonical_address(&creator_order.to_token).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(to_registered_token.sum_balance, Uint128(0));
+        // ===== * it creates an activity record
+        let res = query(
+            &deps,
+            QueryMsg::FillRecords {
+                key: MOCK_VIEWING_KEY.to_string(),
+                filters: None,
+                pagination: pagination_helper(None, 50),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::ActivityRecords {
+                activity_records,
+                total,
+                ..
+            } => {
+                assert_eq!(total, Some(Uint128(1)));
+                assert_eq!(
+                    activity_records[0],
+                    ActivityRecord {
+                        result_from_amount_filled: Some(creator_order.from_amount_filled),
+                        result_net_to_amount_filled: Some(creator_order.net_to_amount_filled),
+                        from_amount_filled_delta: Some(Uint128(MOCK_AMOUNT / 2)),
+                        net_to_amount_filled_delta: Some(Uint128(MOCK_AMOUNT / 2)),
+                        price: Some(Uint128(PRICE_SCALE)),
+                        fee: Some(Uint128(0)),
+                        filler: Some(config.admin.clone()),
+                        ..ActivityRecord::new(
+                            ActivityKind::Filled,
+                            contract_order.position,
+                            mock_butt().address,
+                            mock_token().address,
+                            env.block.height.clone(),
+                            env.block.time
+                        )
+                    }
+                )
+            }
+            _ => panic!("unexpected"),
+        };
+        // ====== when order has an execution fee
+        creator_order.execution_fee = Some(Uint128(1));
+        // ======= when order is partially filled and the execution fee has been paid
+        creator_order.execution_fee_paid = true;
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &creator_order.creator,
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+
+        // ====== * it does not send the execution fee
+        let handle_msg = HandleMsg::Receive {
+            sender: config.admin.clone(),
+            from: config.admin.clone(),
+            amount: Uint128(MOCK_AMOUNT / 4),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_token().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap().messages,
+            vec![
+                snip20::send_msg(
+                    config.admin.clone(),
+                    Uint128(MOCK_AMOUNT / 4),
+                    None,
+                    None,
+                    BLOCK_SIZE,
+                    mock_butt().contract_hash,
+                    mock_butt().address,
+                )
+                .unwrap(),
+                snip20::transfer_msg(
+                    mock_user_address(),
+                    Uint128(MOCK_AMOUNT / 4),
+                    None,
+                    BLOCK_SIZE,
+                    mock_token().contract_hash,
+                    mock_token().address,
+                )
+                .unwrap(),
+            ]
+        );
+
+        // ======= when order is completely unfilled
+        creator_order.from_amount_filled = Uint128(0);
+        creator_order.net_to_amount_filled = Uint128(0);
+        creator_order.execution_fee = Some(Uint128(1));
+        creator_order.execution_fee_paid = false;
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &creator_order.creator,
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+
+        // ======== when route state exists
+        let route_state: RouteState = RouteState {
+            current_hop: None,
+            remaining_hops: VecDeque::new(),
+            borrow_amount: Uint128(5),
+            borrow_token: mock_sscrt(),
+            initiator: mock_contract().address,
+            minimum_acceptable_amount: Some(Uint128(5)),
+            send_excess_to: mock_contract().address,
+        };
+        store_route_state(&mut deps.storage, &route_state).unwrap();
+        // ======== * it sends the execution fee to the route initiator
+        let handle_msg = HandleMsg::Receive {
+            sender: config.admin.clone(),
+            from: config.admin.clone(),
+            amount: Uint128(MOCK_AMOUNT / 8),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap().messages,
+            vec![
+                snip20::send_msg(
+                    config.admin.clone(),
+                    Uint128(MOCK_AMOUNT / 8),
+                    None,
+                    None,
+                    BLOCK_SIZE,
+                    mock_butt().contract_hash,
+                    mock_butt().address,
+                )
+                .unwrap(),
+                snip20::transfer_msg(
+                    mock_user_address(),
+                    Uint128(MOCK_AMOUNT / 8),
+                    None,
+                    BLOCK_SIZE,
+                    mock_token().contract_hash,
+                    mock_token().address,
+                )
+                .unwrap(),
+                snip20::transfer_msg(
+                    mock_contract().address,
+                    creator_order.execution_fee.unwrap(),
+                    None,
+                    BLOCK_SIZE,
+                    mock_sscrt().contract_hash,
+                    mock_sscrt().address,
+                )
+                .unwrap(),
+            ]
+        );
+
+        // ======== when route state does not exist
+        creator_order.from_amount_filled = Uint128(0);
+        creator_order.net_to_amount_filled = Uint128(0);
+        creator_order.execution_fee = Some(Uint128(1));
+        creator_order.execution_fee_paid = false;
+        update_creator_order_and_associated_contract_order(
+            &mut deps.storage,
+            &creator_order.creator,
+            creator_order.clone(),
+            &deps
+                .api
+                .canonical_address(&mock_contract().address)
+                .unwrap(),
+        )
+        .unwrap();
+
+        delete_route_state(&mut deps.storage);
+        // ======== * it sends the execution fee to the user calling the function
+        let handle_result = handle(&mut deps, mock_env(mock_token().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap().messages,
+            vec![
+                snip20::send_msg(
+                    config.admin.clone(),
+                    Uint128(MOCK_AMOUNT / 8),
+                    None,
+                    None,
+                    BLOCK_SIZE,
+                    mock_butt().contract_hash,
+                    mock_butt().address,
+                )
+                .unwrap(),
+                snip20::transfer_msg(
+                    mock_user_address(),
+                    Uint128(MOCK_AMOUNT / 8),
+                    None,
+                    BLOCK_SIZE,
+                    mock_token().contract_hash,
+                    mock_token().address,
+                )
+                .unwrap(),
+                snip20::transfer_msg(
+                    config.admin.clone(),
+                    creator_order.execution_fee.unwrap(),
+                    None,
+                    BLOCK_SIZE,
+                    mock_sscrt().contract_hash,
+                    mock_sscrt().address,
+                )
+                .unwrap(),
+            ]
+        );
+        // ======== * it updates the filler's stats
+        let filler: Filler = read_filler(
+            &deps.storage,
+            &deps.api.canonical_address(&config.admin).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(filler.total_fills, Uint128(3));
+        assert_eq!(filler.last_active_block_height, env.block.height);
+        assert_eq!(
+            filler.execution_fees_earned,
+            creator_order.execution_fee.unwrap()
+        );
+        assert_eq!(
+            filler.volumes,
+            vec![
+                TokenAmount {
+                    token: mock_token().address,
+                    amount: Uint128(MOCK_AMOUNT * 7 / 8)
+                },
+                TokenAmount {
+                    token: mock_butt().address,
+                    amount: Uint128(MOCK_AMOUNT * 7 / 8)
+                }
+            ]
+        );
+        // ======== * it updates the pair's stats
+        let pair_stats: PairStats = read_pair_stats(
+            &deps.storage,
+            &deps.api.canonical_address(&mock_butt().address).unwrap(),
+            &deps.api.canonical_address(&mock_token().address).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(pair_stats.trade_count, Uint128(4));
+        assert_eq!(pair_stats.last_fill_price, Uint128(PRICE_SCALE));
+        assert_eq!(pair_stats.vwap, Uint128(PRICE_SCALE));
+        assert_eq!(pair_stats.cumulative_from_volume, Uint128(MOCK_AMOUNT));
+        assert_eq!(pair_stats.cumulative_to_volume, Uint128(MOCK_AMOUNT));
+    }
+
+    #[test]
+    fn test_filler_stats() {
+        let (_init_result, mut deps) = init_helper(true);
+
+        // = when filler does not exist
+        // = * it raises an error
+        let query_result = query(
+            &deps,
+            QueryMsg::FillerStats {
+                address: mock_user_address(),
+            },
+        );
+        assert_eq!(
+            query_result.unwrap_err(),
+            StdError::generic_err("Filler does not exist.")
+        );
+
+        // = when filler exists
+        handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(MOCK_AMOUNT),
+                msg: Some(to_binary(&ReceiveMsg::BondFiller {}).unwrap()),
+            },
+        )
+        .unwrap();
+        // = * it returns the filler
+        let res = query(
+            &deps,
+            QueryMsg::FillerStats {
+                address: mock_user_address(),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::FillerStats { filler, tokens } => {
+                assert_eq!(
+                    filler,
+                    Filler {
+                        address: mock_user_address(),
+                        bonded_amount: Uint128(MOCK_AMOUNT),
+                        ..Filler::default()
+                    }
+                );
+                // * it includes the metadata of BUTT and SSCRT
+                assert_eq!(
+                    tokens
+                        .iter()
+                        .map(|token| token.address.clone())
+                        .collect::<Vec<HumanAddr>>(),
+                    vec![mock_butt().address, mock_sscrt().address]
+                );
+            }
+            _ => panic!("unexpected"),
+        };
+    }
+
+    #[test]
+    fn test_fillers() {
+        let (_init_result, mut deps) = init_helper(true);
+        for address in [mock_user_address(), mock_sscrt().address].iter() {
+            handle(
+                &mut deps,
+                mock_env(MOCK_ADMIN, &[]),
+                HandleMsg::SetFillerDisabled {
+                    address: address.clone(),
+                    disabled: true,
+                },
+            )
+            .unwrap();
+        }
+
+        // = when page is within range
+        // = * it returns the fillers in the order they were first seen
+        let res = query(
+            &deps,
+            QueryMsg::Fillers {
+                pagination: pagination_helper(None, 1),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::Fillers { fillers, total, .. } => {
+                assert_eq!(total, Some(Uint128(2)));
+                assert_eq!(fillers.len(), 1);
+                assert_eq!(fillers[0].address, mock_user_address());
+            }
+            _ => panic!("unexpected"),
+        };
+        let res = query(
+            &deps,
+            QueryMsg::Fillers {
+                pagination: pagination_helper(Some(0), 1),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::Fillers { fillers, .. } => {
+                assert_eq!(fillers.len(), 1);
+                assert_eq!(fillers[0].address, mock_sscrt().address);
+            }
+            _ => panic!("unexpected"),
+        };
+
+        // = when start_after is past the end
+        // = * it returns no fillers
+        let res = query(
+            &deps,
+            QueryMsg::Fillers {
+                pagination: pagination_helper(Some(1), 1),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::Fillers { fillers, total, .. } => {
+                assert_eq!(total, Some(Uint128(2)));
+                assert_eq!(fillers, vec![]);
+            }
+            _ => panic!("unexpected"),
+        };
+    }
+
+    #[test]
+    fn test_finalize_route() {
+        let (_init_result, mut deps) = init_helper(true);
+        let env = mock_env(mock_user_address(), &[]);
+
+        // when route state does not exist
+        // * it raises an error
+        let handle_msg = HandleMsg::FinalizeRoute {};
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("No route to finalize")
+        );
+
+        // when route state exists
+        // = when there are hops
+        let mut hops: VecDeque<Hop> = VecDeque::new();
+        hops.push_back(Hop {
+            from_token: mock_token(),
+            trade_smart_contract: mock_contract(),
+            position: Some(Uint128(2)),
+        });
+        let route_state: RouteState = RouteState {
+            current_hop: Some(Hop {
+                from_token: mock_token(),
+                trade_smart_contract: mock_contract(),
+                position: Some(Uint128(1)),
+            }),
+            remaining_hops: hops,
+            borrow_token: mock_token(),
+            borrow_amount: Uint128(1_000_000),
+            initiator: mock_user_address(),
+            minimum_acceptable_amount: None,
+            send_excess_to: mock_contract().address,
+        };
+        store_route_state(&mut deps.storage, &route_state).unwrap();
+        // == when it isn't called by the contract
+        // == * it raises an error
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+        // == when it's called by the contract
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err(format!("Cannot finalize: route still contains hops."))
+        );
+
+        // === when there are no hops but there is a current_hop
+        let hops: VecDeque<Hop> = VecDeque::new();
+        let route_state: RouteState = RouteState {
+            current_hop: Some(Hop {
+                from_token: mock_token(),
+                trade_smart_contract: mock_contract(),
+                position: Some(Uint128(1)),
+            }),
+            remaining_hops: hops,
+            borrow_token: mock_token(),
+            borrow_amount: Uint128(1_000_000),
+            initiator: mock_user_address(),
+            minimum_acceptable_amount: None,
+            send_excess_to: mock_contract().address,
+        };
+        store_route_state(&mut deps.storage, &route_state).unwrap();
+        // === * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err(format!("Cannot finalize: route still contains hops."))
+        );
+        // ==== when there are no hops and no current_hop
+        let hops: VecDeque<Hop> = VecDeque::new();
+        let route_state: RouteState = RouteState {
+            current_hop: None,
+            remaining_hops: hops,
+            borrow_token: mock_token(),
+            borrow_amount: Uint128(1_000_000),
+            initiator: mock_user_address(),
+            minimum_acceptable_amount: None,
+            send_excess_to: mock_contract().address,
+        };
+        store_route_state(&mut deps.storage, &route_state).unwrap();
+
+        // ==== * it returns an Ok response
+        handle(
+            &mut deps,
+            mock_env(mock_contract().address, &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        // ==== * it deletes the route state
+        assert_eq!(read_route_state(&deps.storage).unwrap().is_none(), true);
+    }
+
+    #[test]
+    fn test_force_cancel_orders() {
+        let (_init_result, mut deps) = init_helper(true);
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::RegisterTokens {
+                tokens: vec![mock_sscrt()],
+                viewing_key: MOCK_VIEWING_KEY.to_string(),
+            },
+        )
+        .unwrap();
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetPairs {
+                pairs: vec![mock_pair(mock_sscrt().address, mock_butt().address)],
+            },
+        )
+        .unwrap();
+        create_order_helper(&mut deps);
+        create_order_helper(&mut deps);
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: mock_execution_fee(),
+                msg: Some(to_binary(&ReceiveMsg::SetExecutionFeeForOrder {}).unwrap()),
+            },
+        )
+        .unwrap();
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(MOCK_AMOUNT),
+                msg: Some(
+                    to_binary(&ReceiveMsg::CreateOrder {
+                        to_amount: Uint128(MOCK_AMOUNT),
+                        to_token: mock_butt().address,
+                    })
+                    .unwrap(),
+                ),
+            },
+        )
+        .unwrap();
+        let handle_msg = HandleMsg::ForceCancelOrders {
+            token: mock_token().address,
+            limit: Uint128(2),
+        };
+
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // = when called by the admin
+        let handle_result_unwrapped =
+            handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg.clone()).unwrap();
+        // = * it refunds the unfilled amounts and unspent execution fees to the creators
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![
+                snip20::transfer_msg(
+                    mock_user_address(),
+                    Uint128(MOCK_AMOUNT),
+                    None,
+                    BLOCK_SIZE,
+                    mock_butt().contract_hash,
+                    mock_butt().address,
+                )
+                .unwrap(),
+                snip20::transfer_msg(
+                    mock_user_address(),
+                    Uint128(MOCK_AMOUNT),
+                    None,
+                    BLOCK_SIZE,
+                    mock_butt().contract_hash,
+                    mock_butt().address,
+                )
+                .unwrap(),
+                snip20::transfer_msg(
+                    mock_user_address(),
+                    mock_execution_fee(),
+                    None,
+                    BLOCK_SIZE,
+                    mock_sscrt().contract_hash,
+                    mock_sscrt().address,
+                )
+                .unwrap()
+            ]
+        );
+        // = * it reports its progress in padded data
+        let data: Binary = handle_result_unwrapped.data.unwrap();
+        assert_eq!(data.len() % BLOCK_SIZE, 0);
+        let handle_answer: HandleAnswer = from_binary(&data).unwrap();
+        match handle_answer {
+            HandleAnswer::ForceCancelOrders {
+                cancelled,
+                next_position,
+                complete,
+            } => {
+                assert_eq!(cancelled, Uint128(2));
+                assert_eq!(next_position, Uint128(2));
+                assert!(!complete);
+            }
+            _ => panic!("unexpected"),
+        }
+        // = * it cancels the orders for the creator and the contract
+        let user_canonical_address = deps.api.canonical_address(&mock_user_address()).unwrap();
+        assert!(
+            order_at_position(&deps.storage, &user_canonical_address, 0)
+                .unwrap()
+                .cancelled
+        );
+        assert!(
+            order_at_position(
+                &deps.storage,
+                &deps
+                    .api
+                    .canonical_address(&mock_contract().address)
+                    .unwrap(),
+                1
+            )
+            .unwrap()
+            .cancelled
+        );
+        // = * it reduces the sum balance of the from token
+        assert_eq!(
+            read_registered_token(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_butt().address).unwrap()
+            )
+            .unwrap()
+            .sum_balance,
+            Uint128(0)
+        );
+        // = * it creates cancel activity records flagged as admin initiated
+        let (activity_records, total, _) = get_activity_records(
+            &deps.storage,
+            &deps
+                .api
+                .canonical_address(&HumanAddr::from(MOCK_ADMIN))
+                .unwrap(),
+            &Page::new(pagination_helper(None, 50), SortOrder::Descending),
+            PREFIX_CANCEL_RECORDS,
+        )
+        .unwrap();
+        assert_eq!(total, 2);
+        assert!(activity_records[0].admin_initiated);
+        assert!(activity_records[1].admin_initiated);
+
+        // = when called again
+        let handle_result_unwrapped =
+            handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg).unwrap();
+        // = * it resumes from where it left off and skips orders without the token
+        assert_eq!(handle_result_unwrapped.messages, vec![]);
+        assert!(
+            !order_at_position(&deps.storage, &user_canonical_address, 2)
+                .unwrap()
+                .cancelled
+        );
+        let handle_answer: HandleAnswer =
+            from_binary(&handle_result_unwrapped.data.unwrap()).unwrap();
+        match handle_answer {
+            HandleAnswer::ForceCancelOrders {
+                cancelled,
+                next_position,
+                complete,
+            } => {
+                assert_eq!(cancelled, Uint128(0));
+                assert_eq!(next_position, Uint128(3));
+                assert!(complete);
+            }
+            _ => panic!("unexpected"),
+        }
+    }
+
+    #[test]
+    fn test_grant_role() {
+        let (_init_result, mut deps) = init_helper(false);
+        let handle_msg = HandleMsg::GrantRole {
+            role: Role::TokenRegistrar,
+            address: mock_user_address(),
+        };
+
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // = when called by the admin
+        handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg.clone()).unwrap();
+        // = * it adds the address to the role
+        assert_eq!(
+            read_role_addresses(&deps.storage, Role::TokenRegistrar),
+            vec![mock_user_address()]
+        );
+        // = * it does not add the address to other roles
+        assert_eq!(
+            read_role_addresses(&deps.storage, Role::Treasury),
+            Vec::<HumanAddr>::new()
+        );
+        // == when the address already has the role
+        handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg).unwrap();
+        // == * it does not add the address twice
+        assert_eq!(
+            read_role_addresses(&deps.storage, Role::TokenRegistrar),
+            vec![mock_user_address()]
+        );
+        // = * the address can perform actions for the role
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::RegisterTokens {
+                tokens: vec![mock_butt(), mock_token()],
+                viewing_key: MOCK_VIEWING_KEY.to_string(),
+            },
+        )
+        .unwrap();
+        // = * the address can not perform actions for other roles
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::RescueTokens {
+                denom: Some("uscrt".to_string()),
+                key: None,
+                token_address: None,
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+    }
+
+    #[test]
+    fn test_hand_over_escrow() {
+        let (_init_result, mut deps) = init_helper(true);
+        create_order_helper(&mut deps);
+        let handle_msg = HandleMsg::HandOverEscrow {
+            key: MOCK_VIEWING_KEY.to_string(),
+            recipient: mock_sscrt().address,
+        };
+
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // = when called by the admin
+        // == when contract is not withdraw only
+        // == * it raises an error
+        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Contract must be withdraw only.")
+        );
+        // == when contract is withdraw only
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetPauseFlags {
+                pause_flags: PauseFlags {
+                    withdraw_only: true,
+                    ..PauseFlags::default()
+                },
+            },
+        )
+        .unwrap();
+        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg).unwrap();
+        // == * it transfers the balance of every registered token to the recipient
+        assert_eq!(
+            handle_result.messages,
+            vec![
+                snip20::transfer_msg(
+                    mock_sscrt().address,
+                    Uint128(MOCK_AMOUNT),
+                    None,
+                    BLOCK_SIZE,
+                    mock_butt().contract_hash,
+                    mock_butt().address,
+                )
+                .unwrap(),
+                snip20::transfer_msg(
+                    mock_sscrt().address,
+                    Uint128(MOCK_AMOUNT),
+                    None,
+                    BLOCK_SIZE,
+                    mock_token().contract_hash,
+                    mock_token().address,
+                )
+                .unwrap(),
+            ]
+        );
+        // == * it returns the balances handed over
+        let handle_answer: HandleAnswer = from_binary(&handle_result.data.unwrap()).unwrap();
+        match handle_answer {
+            HandleAnswer::HandOverEscrow { balances, tokens } => {
+                assert_eq!(tokens.len(), 2);
+                assert_eq!(
+                    balances,
+                    vec![
+                        TokenAmount {
+                            token: mock_butt().address,
+                            amount: Uint128(MOCK_AMOUNT)
+                        },
+                        TokenAmount {
+                            token: mock_token().address,
+                            amount: Uint128(MOCK_AMOUNT)
+                        }
+                    ]
+                );
+            }
+            _ => panic!("unexpected"),
+        }
+    }
+
+    #[test]
+    fn test_handle_first_hop() {
+        let (_init_result, mut deps) = init_helper(true);
+        let borrow_amount: Uint128 = Uint128(555);
+        let mut hops: VecDeque<Hop> = VecDeque::new();
+        create_order_helper(&mut deps);
+        let first_hop = Hop {
+            from_token: mock_butt(),
+            trade_smart_contract: mock_contract(),
+            position: Some(Uint128(0)),
+        };
+        hops.push_back(first_hop.clone());
+        let handle_msg = HandleMsg::HandleFirstHop {
+            borrow_amount,
+            hops: hops.clone(),
+            minimum_acceptable_amount: Some(borrow_amount),
+        };
+        // when called by an address that is not in the addresses allowed to fill
+        // * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+        // when called by an address that is allowed to fill
+        // = when hops is less than 2
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(HumanAddr::from(MOCK_ADMIN), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Route must be 2 hops.")
+        );
+        // = when there are more than 2 hops
+        hops.push_back(Hop {
+            from_token: mock_butt(),
+            trade_smart_contract: mock_contract(),
+            position: None,
+        });
+        hops.push_back(Hop {
+            from_token: mock_butt(),
+            trade_smart_contract: mock_contract(),
+            position: None,
+        });
+        let handle_result = handle(
+            &mut deps,
+            mock_env(HumanAddr::from(MOCK_ADMIN), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Route must be 2 hops.")
+        );
+        // == when there are 2 hops
+        hops.pop_back();
+        let handle_msg = HandleMsg::HandleFirstHop {
+            borrow_amount,
+            hops: hops.clone(),
+            minimum_acceptable_amount: Some(borrow_amount),
+        };
+        let handle_unwrapped = handle(
+            &mut deps,
+            mock_env(HumanAddr::from(MOCK_ADMIN), &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        let route_state: RouteState = read_route_state(&deps.storage).unwrap().unwrap();
+        // == * it stores the current hop
+        assert_eq!(route_state.current_hop.unwrap(), first_hop);
+        // == * it stores the borrow amount
+        assert_eq!(route_state.borrow_amount, borrow_amount);
+        // == * it stores the borrow token as the first hops from_token
+        assert_eq!(route_state.borrow_token, first_hop.from_token);
+        // == * it stores the address to send left over amount after paying back debt
+        assert_eq!(route_state.initiator, HumanAddr::from(MOCK_ADMIN));
+        // == * it stores the remaining hops
+        hops.pop_front();
+        assert_eq!(route_state.remaining_hops, hops);
+        // === when first hop is to limit order smart contract (second isn't)
+        // === * it stores the send_excess_to as the initiator
+        assert_eq!(route_state.send_excess_to, HumanAddr::from(MOCK_ADMIN));
+        // === * it sends the token with the right message to the swap contract
+        // === * it sends a message to finalize the contract
+        assert_eq!(
+            handle_unwrapped.messages,
+            vec![
+                snip20::send_msg(
+                    first_hop.trade_smart_contract.address,
+                    borrow_amount,
+                    Some(
+                        to_binary(&ReceiveMsg::FillOrder {
+                            position: first_hop.position.unwrap(),
+                        })
+                        .unwrap()
+                    ),
+                    None,
+                    BLOCK_SIZE,
+                    first_hop.from_token.contract_hash,
+                    first_hop.from_token.address,
+                )
+                .unwrap(),
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: mock_contract().address,
+                    callback_code_hash: mock_contract().contract_hash,
+                    msg: to_binary(&HandleMsg::FinalizeRoute {}).unwrap(),
+                    send: vec![],
+                })
+            ]
+        );
+        // === when first hop is to swap contract
+        let mut hops: VecDeque<Hop> = VecDeque::new();
+        let first_hop = Hop {
+            from_token: mock_butt(),
+            position: None,
+            trade_smart_contract: mock_contract(),
+        };
+        hops.push_back(first_hop.clone());
+        hops.push_back(Hop {
+            from_token: mock_butt(),
+            trade_smart_contract: mock_contract(),
+            position: Some(Uint128(0)),
+        });
+        let handle_msg = HandleMsg::HandleFirstHop {
+            borrow_amount,
+            hops: hops.clone(),
+            minimum_acceptable_amount: Some(borrow_amount),
+        };
+        let handle_unwrapped = handle(
+            &mut deps,
+            mock_env(HumanAddr::from(MOCK_ADMIN), &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        // === * it sends the token with the right message to the swap contract
+        // === * it sends a message to finalize the contract
+        assert_eq!(
+            handle_unwrapped.messages,
+            vec![
+                snip20::send_msg(
+                    first_hop.trade_smart_contract.address,
+                    borrow_amount,
+                    Some(
+                        to_binary(&Snip20Swap::Swap {
+                            expected_return: None,
+                            to: Some(mock_contract().address),
+                        })
+                        .unwrap()
+                    ),
+                    None,
+                    BLOCK_SIZE,
+                    first_hop.from_token.contract_hash,
+                    first_hop.from_token.address,
+                )
+                .unwrap(),
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: mock_contract().address,
+                    callback_code_hash: mock_contract().contract_hash,
+                    msg: to_binary(&HandleMsg::FinalizeRoute {}).unwrap(),
+                    send: vec![],
+                })
+            ]
+        );
+        // === when both hops are for limit orders
+        let receive_msg = ReceiveMsg::CreateOrder {
+            to_amount: Uint128(MOCK_AMOUNT),
+            to_token: mock_token().address,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr::from("secretgary"),
+            from: HumanAddr::from("secretgary"),
+            amount: Uint128(MOCK_AMOUNT),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        let mut hops: VecDeque<Hop> = VecDeque::new();
+        hops.push_back(Hop {
+            from_token: mock_butt(),
+            trade_smart_contract: mock_contract(),
+            position: Some(Uint128(0)),
+        });
+        hops.push_back(Hop {
+            from_token: mock_butt(),
+            trade_smart_contract: mock_contract(),
+            position: Some(Uint128(1)),
+        });
+        // === * it sets the send_excess_to on the route as the creator of the newest limit order
+        let handle_msg = HandleMsg::HandleFirstHop {
+            borrow_amount,
+            hops: hops.clone(),
+            minimum_acceptable_amount: Some(borrow_amount),
+        };
+        handle(
+            &mut deps,
+            mock_env(HumanAddr::from(MOCK_ADMIN), &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        let route_state: RouteState = read_route_state(&deps.storage).unwrap().unwrap();
+        // === * it stores the send_excess_to as the initiator
+        assert_eq!(route_state.send_excess_to, HumanAddr::from("secretgary"));
+    }
+
+    #[test]
+    fn test_handle_hop() {
+        let (_init_result, mut deps) = init_helper(true);
+        let borrow_amount: Uint128 = Uint128(MOCK_AMOUNT);
+        let borrow_token: SecretContract = mock_butt();
+        let minimum_acceptable_amount: Uint128 = borrow_amount + Uint128(1);
+        create_order_helper(&mut deps);
+        create_order_helper(&mut deps);
+
+        // when route state does not exist
+        // * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract().address,
+            from: mock_contract().address,
+            amount: Uint128(MOCK_AMOUNT),
+            msg: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("cannot find route")
+        );
+
+        // when route state exists
+        let mut hops: VecDeque<Hop> = VecDeque::new();
+        hops.push_back(Hop {
+            from_token: mock_token(),
+            trade_smart_contract: mock_contract(),
+            position: Some(Uint128(1)),
+        });
+        let route_state: RouteState = RouteState {
+            current_hop: Some(Hop {
+                from_token: mock_butt(),
+                trade_smart_contract: mock_contract(),
+                position: Some(Uint128(2)),
+            }),
+            remaining_hops: hops,
+            borrow_token: borrow_token.clone(),
+            borrow_amount,
+            initiator: mock_user_address(),
+            minimum_acceptable_amount: Some(borrow_amount),
+            send_excess_to: mock_contract().address,
+        };
+        store_route_state(&mut deps.storage, &route_state).unwrap();
+
+        // = when not called by the current hop's trade smart contract
+        // = * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_butt().address,
+            from: mock_butt().address,
+            amount: Uint128(MOCK_AMOUNT),
+            msg: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Route called from wrong trade smart contract.")
+        );
+
+        // = when from current hop's trade smart contract
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract().address,
+            from: mock_contract().address,
+            amount: Uint128(MOCK_AMOUNT),
+            msg: None,
+        };
+        // == when there are hops
+        // === when called by a token different from the next hops from token
+        // === * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Route called by wrong token.")
+        );
+        // === when called by the from token of the next hop
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            handle_msg.clone(),
+        );
+        // === * it stores the route state with the appropriate info
+        let route_state: RouteState = read_route_state(&deps.storage).unwrap().unwrap();
+        assert_eq!(
+            route_state,
+            RouteState {
+                current_hop: Some(Hop {
+                    from_token: mock_token(),
+                    trade_smart_contract: mock_contract(),
+                    position: Some(Uint128(1)),
+                }),
+                borrow_token: borrow_token.clone(),
+                remaining_hops: VecDeque::new(),
+                borrow_amount,
+                initiator: mock_user_address(),
+                minimum_acceptable_amount: Some(borrow_amount),
+                send_excess_to: mock_contract().address,
+            }
+        );
+
+        // ==== when the next hop has a position value
+        // ===== when amount received is equal to or less than the next order's unfilled amount
+        // ===== * it sends the amount received to the next hop trade smart contract with the correct details
+        assert_eq!(
+            handle_result.unwrap().messages,
+            vec![snip20::send_msg(
+                mock_contract().address,
+                Uint128(MOCK_AMOUNT),
+                Some(
+                    to_binary(&ReceiveMsg::FillOrder {
+                        position: Uint128(1)
+                    })
+                    .unwrap()
+                ),
+                None,
+                BLOCK_SIZE,
+                mock_token().contract_hash,
+                mock_token().address,
+            )
+            .unwrap()]
+        );
+
+        // ===== when amount received is greater than the next order's unfilled amount
+        let mut hops: VecDeque<Hop> = VecDeque::new();
+        hops.push_back(Hop {
+            from_token: mock_token(),
+            trade_smart_contract: mock_contract(),
+            position: Some(Uint128(1)),
+        });
+        let route_state: RouteState = RouteState {
+            current_hop: Some(Hop {
+                from_token: mock_butt(),
+                trade_smart_contract: mock_contract(),
+                position: Some(Uint128(2)),
+            }),
+            remaining_hops: hops,
+            borrow_token: borrow_token.clone(),
+            borrow_amount,
+            initiator: mock_user_address(),
+            minimum_acceptable_amount: Some(borrow_amount),
+            send_excess_to: mock_contract().address,
+        };
+        store_route_state(&mut deps.storage, &route_state).unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract().address,
+            from: mock_contract().address,
+            amount: Uint128(MOCK_AMOUNT + 1),
+            msg: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            handle_msg.clone(),
+        );
+
+        // ===== * it sends the next order's unfilled amount
+        assert_eq!(
+            handle_result.unwrap().messages,
+            vec![snip20::send_msg(
+                mock_contract().address,
+                Uint128(MOCK_AMOUNT),
+                Some(
+                    to_binary(&ReceiveMsg::FillOrder {
+                        position: Uint128(1)
+                    })
+                    .unwrap()
+                ),
+                None,
+                BLOCK_SIZE,
+                mock_token().contract_hash,
+                mock_token().address,
+            )
+            .unwrap()]
+        );
+
+        // ==== when the next hop does not have a position value
+        let mut hops: VecDeque<Hop> = VecDeque::new();
+        hops.push_back(Hop {
+            from_token: mock_token(),
+            trade_smart_contract: mock_butt(),
+            position: None,
+        });
+        let route_state: RouteState = RouteState {
+            current_hop: Some(Hop {
+                from_token: mock_butt(),
+                trade_smart_contract: mock_contract(),
+                position: Some(Uint128(2)),
+            }),
+            remaining_hops: hops,
+            borrow_token: borrow_token.clone(),
+            borrow_amount,
+            initiator: mock_user_address(),
+            minimum_acceptable_amount: Some(borrow_amount),
+            send_excess_to: mock_contract().address,
+        };
+        store_route_state(&mut deps.storage, &route_state).unwrap();
+        // ==== * it sends the amount received to the next hop trade smart contract with the correct details
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract().address,
+            from: mock_contract().address,
+            amount: Uint128(MOCK_AMOUNT),
+            msg: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap().messages,
+            vec![snip20::send_msg(
+                mock_butt().address,
+                Uint128(MOCK_AMOUNT),
+                Some(
+                    to_binary(&Snip20Swap::Swap {
+                        expected_return: None,
+                        to: Some(mock_contract().address)
+                    })
+                    .unwrap()
+                ),
+                None,
+                BLOCK_SIZE,
+                mock_token().contract_hash,
+                mock_token().address,
+            )
+            .unwrap()]
+        );
+
+        // == when there are are no hops
+        let hops: VecDeque<Hop> = VecDeque::new();
+        let route_state: RouteState = RouteState {
+            current_hop: Some(Hop {
+                from_token: mock_butt(),
+                trade_smart_contract: mock_contract(),
+                position: Some(Uint128(2)),
+            }),
+            remaining_hops: hops.clone(),
+            borrow_token: borrow_token.clone(),
+            borrow_amount,
+            initiator: mock_user_address(),
+            minimum_acceptable_amount: Some(minimum_acceptable_amount),
+            send_excess_to: mock_contract().address,
+        };
+        store_route_state(&mut deps.storage, &route_state).unwrap();
+        // === when not called by the borrowed token
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            handle_msg.clone(),
+        );
+        // === * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Route called by wrong token.")
+        );
+        // === when called by the borrowed token
+        // ==== when amount sent in is less than the minimum acceptable amount
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract().address,
+            from: mock_contract().address,
+            amount: borrow_amount,
+            msg: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(borrow_token.address.clone(), &[]),
+            handle_msg.clone(),
+        );
+        // ==== * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Operation fell short of minimum_acceptable_amount.")
+        );
+
+        // ==== when amount sent in is less than the borrowed amount
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract().address,
+            from: mock_contract().address,
+            amount: (borrow_amount - Uint128(1)).unwrap(),
+            msg: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(borrow_token.address.clone(), &[]),
+            handle_msg.clone(),
+        );
+        // ==== * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Operation fell short of borrow_amount.")
+        );
+        // ==== when amount sent in is equal to the borrowed amount
+        let hops: VecDeque<Hop> = VecDeque::new();
+        let route_state: RouteState = RouteState {
+            current_hop: Some(Hop {
+                from_token: mock_butt(),
+                trade_smart_contract: mock_contract(),
+                position: Some(Uint128(2)),
+            }),
+            remaining_hops: hops.clone(),
+            borrow_token: borrow_token.clone(),
+            borrow_amount,
+            initiator: mock_user_address(),
+            minimum_acceptable_amount: None,
+            send_excess_to: mock_contract().address,
+        };
+        store_route_state(&mut deps.storage, &route_state).unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract().address,
+            from: mock_contract().address,
+            amount: borrow_amount,
+            msg: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(borrow_token.address.clone(), &[]),
+            handle_msg.clone(),
+        );
+        // ==== * it stores the current hop as None
+        let route_state: RouteState = read_route_state(&deps.storage).unwrap().unwrap();
+        // ==== * it stores the rest of the route state appropriately
+        assert_eq!(
+            route_state,
+            RouteState {
+                current_hop: None,
+                borrow_token: borrow_token.clone(),
+                remaining_hops: hops,
+                borrow_amount,
+                initiator: mock_user_address(),
+                minimum_acceptable_amount: None,
+                send_excess_to: mock_contract().address,
+            }
+        );
+        // ==== * it does not send any messages
+        assert_eq!(handle_result.unwrap().messages, vec![]);
+        // ==== when amount sent in is greater than the borrowed amount
+        let hops: VecDeque<Hop> = VecDeque::new();
+        let route_state: RouteState = RouteState {
+            current_hop: Some(Hop {
+                from_token: mock_butt(),
+                trade_smart_contract: mock_contract(),
+                position: Some(Uint128(2)),
+            }),
+            remaining_hops: hops.clone(),
+            borrow_token: borrow_token.clone(),
+            borrow_amount,
+            initiator: mock_user_address(),
+            minimum_acceptable_amount: Some(borrow_amount),
+            send_excess_to: mock_contract().address,
+        };
+        store_route_state(&mut deps.storage, &route_state).unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract().address,
+            from: mock_contract().address,
+            amount: borrow_amount + Uint128(1),
+            msg: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(borrow_token.address.clone(), &[]),
+            handle_msg.clone(),
+        );
+        // ==== * it sends the excess after paying the borrowed amount to the send_excess_to address
+        assert_eq!(
+            handle_result.unwrap().messages,
+            vec![snip20::transfer_msg(
+                route_state.send_excess_to,
+                Uint128(1),
+                None,
+                BLOCK_SIZE,
+                borrow_token.contract_hash.clone(),
+                borrow_token.address.clone(),
+            )
+            .unwrap()]
+        );
+        // ==== * it updates the initiator's stats
+        let filler: Filler = read_filler(
+            &deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(filler.total_routes, Uint128(2));
+        assert_eq!(
+            filler.last_active_block_height,
+            mock_env(MOCK_ADMIN, &[]).block.height
+        );
+    }
+
+    #[test]
+    fn test_import_state() {
+        let (_init_result, mut old_deps) = init_helper(true);
+        create_order_helper(&mut old_deps);
+        create_order_helper(&mut old_deps);
+        handle(
+            &mut old_deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::CancelOrder {
+                from_token_address: mock_butt().address,
+                position: Uint128(0),
+            },
+        )
+        .unwrap();
+        handle(
+            &mut old_deps,
+            mock_env(mock_butt().address, &[]),
+            HandleMsg::Receive {
+                sender: HumanAddr::from(MOCK_ADMIN),
+                from: HumanAddr::from(MOCK_ADMIN),
+                amount: Uint128(MOCK_AMOUNT),
+                msg: Some(to_binary(&ReceiveMsg::BondFiller {}).unwrap()),
+            },
+        )
+        .unwrap();
+        handle(
+            &mut old_deps,
+            mock_env(mock_token().address, &[]),
+            HandleMsg::Receive {
+                sender: HumanAddr::from(MOCK_ADMIN),
+                from: HumanAddr::from(MOCK_ADMIN),
+                amount: Uint128(MOCK_AMOUNT / 4),
+                msg: Some(
+                    to_binary(&ReceiveMsg::FillOrder {
+                        position: Uint128(1),
+                    })
+                    .unwrap(),
+                ),
+            },
+        )
+        .unwrap();
+        commit_order_helper(&mut old_deps);
+        let export = |section: StateSection| -> StatePage {
+            let res = query(
+                &old_deps,
+                QueryMsg::ExportState {
+                    key: MOCK_VIEWING_KEY.to_string(),
+                    section,
+                    pagination: pagination_helper(None, 10),
+                },
+            )
+            .unwrap();
+            match from_binary(&res).unwrap() {
+                QueryAnswer::ExportState { state_page, .. } => state_page,
+                _ => panic!("unexpected"),
+            }
+        };
+        let orders_page: StatePage = export(StateSection::Orders);
+        let (_init_result, mut deps) = init_helper_with_importing(true, true);
+
+        // = when the import is not complete
+        // = * orders can't be created
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(MOCK_AMOUNT),
+                msg: Some(
+                    to_binary(&ReceiveMsg::CreateOrder {
+                        to_amount: Uint128(MOCK_AMOUNT),
+                        to_token: mock_token().address,
+                    })
+                    .unwrap(),
+                ),
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Import is not complete.")
+        );
+
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::ImportState {
+                state_page: orders_page.clone(),
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // = when called by the admin
+        // == when orders are imported out of order
+        // == * it raises an error
+        let mut out_of_order_orders: Vec<Order> = match orders_page.clone() {
+            StatePage::Orders { orders } => orders,
+            _ => panic!("unexpected"),
+        };
+        out_of_order_orders.reverse();
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: StatePage::Orders {
+                    orders: out_of_order_orders,
+                },
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Orders must be imported in order.")
+        );
+        // == when importing orders in order
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: orders_page,
+            },
+        )
+        .unwrap();
+        // == * it recreates the contract and creator orders
+        let contract_canonical_address: CanonicalAddr = deps
+            .api
+            .canonical_address(&mock_contract().address)
+            .unwrap();
+        let user_canonical_address: CanonicalAddr =
+            deps.api.canonical_address(&mock_user_address()).unwrap();
+        for position in 0..2 {
+            assert_eq!(
+                order_at_position(&deps.storage, &contract_canonical_address, position).unwrap(),
+                order_at_position(&old_deps.storage, &contract_canonical_address, position)
+                    .unwrap()
+            );
+            assert_eq!(
+                order_at_position(&deps.storage, &user_canonical_address, position).unwrap(),
+                order_at_position(&old_deps.storage, &user_canonical_address, position).unwrap()
+            );
+        }
+        assert_eq!(
+            storage_count(&deps.storage, &user_canonical_address, PREFIX_ORDERS_COUNT).unwrap(),
+            2
+        );
+        // == when importing activity records
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: export(StateSection::CancelRecords),
+            },
+        )
+        .unwrap();
+        // == * it recreates the admin's activity records
+        let admin_canonical_address: CanonicalAddr = deps
+            .api
+            .canonical_address(&HumanAddr::from(MOCK_ADMIN))
+            .unwrap();
+        assert_eq!(
+            get_activity_records(
+                &deps.storage,
+                &admin_canonical_address,
+                &Page::new(pagination_helper(None, 10), SortOrder::Descending),
+                PREFIX_CANCEL_RECORDS
+            )
+            .unwrap(),
+            get_activity_records(
+                &old_deps.storage,
+                &admin_canonical_address,
+                &Page::new(pagination_helper(None, 10), SortOrder::Descending),
+                PREFIX_CANCEL_RECORDS
+            )
+            .unwrap()
+        );
+        // == when the same activity records are imported again
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: export(StateSection::CancelRecords),
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Activity records must be imported in order.")
+        );
+        // == when importing registered tokens
+        // === when a token is not registered
+        // === * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: StatePage::RegisteredTokens {
+                    registered_tokens: vec![RegisteredToken {
+                        address: mock_sscrt().address,
+                        contract_hash: mock_sscrt().contract_hash,
+                        sum_balance: Uint128(0),
+                        disabled: false,
+                        name: None,
+                        symbol: None,
+                        decimals: None,
+                    }],
+                },
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Token is not registered.")
+        );
+        // === when tokens are registered
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: export(StateSection::RegisteredTokens),
+            },
+        )
+        .unwrap();
+        // === * it sets the sum balances
+        let butt_canonical_address: CanonicalAddr =
+            deps.api.canonical_address(&mock_butt().address).unwrap();
+        assert_eq!(
+            read_registered_token(&deps.storage, &butt_canonical_address),
+            read_registered_token(&old_deps.storage, &butt_canonical_address)
+        );
+        // == when importing fillers
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: export(StateSection::Fillers),
+            },
+        )
+        .unwrap();
+        // == * it recreates the fillers and their bonded total
+        assert_eq!(
+            read_filler(&deps.storage, &admin_canonical_address),
+            read_filler(&old_deps.storage, &admin_canonical_address)
+        );
+        assert_eq!(read_fillers_count(&deps.storage), 1);
+        assert_eq!(
+            read_token_totals(&deps.storage, &mock_butt().address).bonded_amount,
+            Uint128(MOCK_AMOUNT)
+        );
+        // == when a filler is imported again
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: export(StateSection::Fillers),
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Filler is already imported.")
+        );
+        // == when importing commitments
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: export(StateSection::Commitments),
+            },
+        )
+        .unwrap();
+        // == * it recreates the commitments, the open commitments and their committed total
+        assert_eq!(
+            read_commitment(&deps.storage, 0),
+            read_commitment(&old_deps.storage, 0)
+        );
+        assert_eq!(
+            read_open_commitments_count(&deps.storage, &user_canonical_address),
+            1
+        );
+        assert_eq!(
+            read_token_totals(&deps.storage, &mock_butt().address).committed_amount,
+            Uint128(MOCK_AMOUNT)
+        );
+        // == when the same commitments are imported again
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: export(StateSection::Commitments),
+            },
+        );
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::generic_err("Amount sent in must equal execution fee.")
+            StdError::generic_err("Commitments must be imported in order.")
         );
-        // = when amount sent in is equal to execution fee
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_user_address(),
-            from: mock_user_address(),
-            amount: mock_execution_fee(),
-            msg: Some(to_binary(&receive_msg).unwrap()),
-        };
-        let handle_result = handle(&mut deps, env.clone(), handle_msg);
-        // == when user does not have any orders
+        // == when importing pair stats
+        // === when a pair is not registered
         // === * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: StatePage::PairStats {
+                    pair_stats: vec![PairStats {
+                        from_token: mock_sscrt().address,
+                        to_token: mock_token().address,
+                        ..PairStats::default()
+                    }],
+                },
+            },
+        );
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::generic_err("Order does not exist.")
+            StdError::generic_err("Pair is not registered.")
         );
-        // == when user has at least one order
-        create_order_helper(&mut deps);
-        // === when current block is the same as the block when the order is created
-        // ==== when order has fee set already
-        let mut creator_order = order_at_position(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
+        // === when pairs are registered
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: export(StateSection::PairStats),
+            },
         )
         .unwrap();
-        creator_order.execution_fee = Some(Uint128(1));
-        update_creator_order_and_associated_contract_order(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            creator_order.clone(),
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
+        // === * it sets the pair stats
+        let token_canonical_address: CanonicalAddr =
+            deps.api.canonical_address(&mock_token().address).unwrap();
+        assert_eq!(
+            read_pair_stats(
+                &deps.storage,
+                &butt_canonical_address,
+                &token_canonical_address
+            ),
+            read_pair_stats(
+                &old_deps.storage,
+                &butt_canonical_address,
+                &token_canonical_address
+            )
+        );
+        // == when importing candles
+        let candles_section: StateSection = StateSection::Candles {
+            pair: TokenPair {
+                from_token: mock_butt().address,
+                to_token: mock_token().address,
+            },
+            interval: DEFAULT_CANDLE_INTERVALS[0],
+        };
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: export(candles_section.clone()),
+            },
         )
         .unwrap();
-        // ==== * it raises an error
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_user_address(),
-            from: mock_user_address(),
-            amount: mock_execution_fee(),
-            msg: Some(to_binary(&receive_msg).unwrap()),
-        };
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        // == * it recreates the candles
+        assert_eq!(
+            read_candles_count(
+                &deps.storage,
+                &butt_canonical_address,
+                &token_canonical_address,
+                DEFAULT_CANDLE_INTERVALS[0]
+            ),
+            1
+        );
+        assert_eq!(
+            read_candle(
+                &deps.storage,
+                &butt_canonical_address,
+                &token_canonical_address,
+                DEFAULT_CANDLE_INTERVALS[0],
+                0
+            ),
+            read_candle(
+                &old_deps.storage,
+                &butt_canonical_address,
+                &token_canonical_address,
+                DEFAULT_CANDLE_INTERVALS[0],
+                0
+            )
+        );
+        // == when the same candles are imported again
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: export(candles_section),
+            },
+        );
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::generic_err("Execution fee already set for order.")
+            StdError::generic_err("Candles must be imported in order.")
         );
-        // ==== when order does not have execution fee set already
-        // ===== when order is cancelled
-        creator_order.execution_fee = None;
-        creator_order.cancelled = true;
-        update_creator_order_and_associated_contract_order(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            creator_order.clone(),
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
+        // == when importing a user's activity records
+        let activity_section: StateSection = StateSection::Activity {
+            address: mock_user_address(),
+        };
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: export(activity_section.clone()),
+            },
         )
         .unwrap();
-        // ===== * it raises an error
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        // == * it recreates the user's activity records and their order index
+        assert_eq!(
+            get_activity_records(
+                &deps.storage,
+                &user_canonical_address,
+                &Page::new(pagination_helper(None, 10), SortOrder::Descending),
+                PREFIX_ACTIVITY_RECORDS
+            )
+            .unwrap(),
+            get_activity_records(
+                &old_deps.storage,
+                &user_canonical_address,
+                &Page::new(pagination_helper(None, 10), SortOrder::Descending),
+                PREFIX_ACTIVITY_RECORDS
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            order_activity_record_positions(&deps.storage, &user_canonical_address, 1),
+            order_activity_record_positions(&old_deps.storage, &user_canonical_address, 1)
+        );
+        // == when the same activity records are imported again
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: export(activity_section),
+            },
+        );
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::generic_err("Order already cancelled.")
+            StdError::generic_err("Activity records must be imported in order.")
         );
-        // ===== when order is filled
-        creator_order.cancelled = false;
-        creator_order.from_amount_filled = creator_order.from_amount;
-        update_creator_order_and_associated_contract_order(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            creator_order.clone(),
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
+
+        // = when import is complete
+        write_import_complete(&mut deps.storage, true).unwrap();
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ImportState {
+                state_page: export(StateSection::FillRecords),
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Import is complete.")
+        );
+    }
+
+    #[test]
+    fn test_my_activity() {
+        let (_init_result, mut deps) = init_helper(true);
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        create_order_helper(&mut deps);
+        create_order_helper(&mut deps);
+        let fill_msg = HandleMsg::Receive {
+            sender: config.admin.clone(),
+            from: config.admin.clone(),
+            amount: Uint128(MOCK_AMOUNT / 4),
+            msg: Some(
+                to_binary(&ReceiveMsg::FillOrder {
+                    position: Uint128(0),
+                })
                 .unwrap(),
+            ),
+        };
+        handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            fill_msg.clone(),
+        )
+        .unwrap();
+        handle(
+            &mut deps,
+            mock_env(config.admin.clone(), &[]),
+            HandleMsg::SetFillerAddressPublic {
+                address_public: true,
+            },
+        )
+        .unwrap();
+        handle(&mut deps, mock_env(mock_token().address, &[]), fill_msg).unwrap();
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::CancelOrder {
+                from_token_address: mock_butt().address,
+                position: Uint128(1),
+            },
+        )
+        .unwrap();
+
+        // = when there are no filters
+        // = * it returns the user's activity records, newest first
+        let res = query(
+            &deps,
+            QueryMsg::MyActivity {
+                address: mock_user_address(),
+                key: MOCK_VIEWING_KEY.to_string(),
+                filters: ActivityFilters::default(),
+                pagination: pagination_helper(None, 2),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::ActivityRecords {
+                activity_records,
+                total,
+                ..
+            } => {
+                assert_eq!(total, Some(Uint128(5)));
+                assert_eq!(activity_records.len(), 2);
+                assert_eq!(activity_records[0].position, Uint128(4));
+                assert_eq!(activity_records[0].order_position, Uint128(1));
+                assert_eq!(activity_records[0].activity, ActivityKind::Cancelled);
+                assert_eq!(
+                    activity_records[0].refund_amount,
+                    Some(Uint128(MOCK_AMOUNT))
+                );
+                assert_eq!(activity_records[1].position, Uint128(3));
+                assert_eq!(activity_records[1].order_position, Uint128(0));
+                assert_eq!(activity_records[1].activity, ActivityKind::Filled);
+                assert_eq!(activity_records[1].price, Some(Uint128(PRICE_SCALE)));
+                assert_eq!(activity_records[1].execution_fee_paid, None);
+                assert!(!activity_records[1].routed);
+                assert_eq!(
+                    activity_records[1].from_amount_filled_delta,
+                    Some(Uint128(MOCK_AMOUNT / 4))
+                );
+                assert_eq!(
+                    activity_records[1].net_to_amount_filled_delta,
+                    Some(Uint128(MOCK_AMOUNT / 4))
+                );
+                assert_eq!(
+                    activity_records[1].result_from_amount_filled,
+                    Some(Uint128(MOCK_AMOUNT / 2))
+                );
+                // = * it includes the filler when the filler's address is public
+                assert_eq!(activity_records[1].filler, Some(config.admin.clone()));
+            }
+            _ => panic!("unexpected"),
+        };
+
+        // = when filtering by order position
+        // = * it returns the records for that order
+        let res = query(
+            &deps,
+            QueryMsg::MyActivity {
+                address: mock_user_address(),
+                key: MOCK_VIEWING_KEY.to_string(),
+                filters: ActivityFilters {
+                    order_position: Some(Uint128(0)),
+                    ..ActivityFilters::default()
+                },
+                pagination: pagination_helper(Some(3), 1),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::ActivityRecords {
+                activity_records,
+                total,
+                ..
+            } => {
+                assert_eq!(total, Some(Uint128(3)));
+                assert_eq!(activity_records.len(), 1);
+                assert_eq!(activity_records[0].position, Uint128(2));
+                // = * it hides the filler when the filler's address is not public
+                assert_eq!(activity_records[0].filler, None);
+            }
+            _ => panic!("unexpected"),
+        };
+
+        // = when filtering by activity
+        // = * it returns the records of that kind
+        let res = query(
+            &deps,
+            QueryMsg::MyActivity {
+                address: mock_user_address(),
+                key: MOCK_VIEWING_KEY.to_string(),
+                filters: ActivityFilters {
+                    activity: Some(ActivityKind::Filled),
+                    ..ActivityFilters::default()
+                },
+                pagination: pagination_helper(None, 10),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::ActivityRecords {
+                activity_records,
+                total,
+                ..
+            } => {
+                assert_eq!(total, Some(Uint128(2)));
+                assert_eq!(activity_records[0].position, Uint128(3));
+                assert_eq!(activity_records[1].position, Uint128(2));
+            }
+            _ => panic!("unexpected"),
+        };
+        // = * it pages over the records of that kind
+        let query_filled_records =
+            |pagination: Option<Pagination>| -> (Vec<u128>, Option<Uint128>) {
+                let res = query(
+                    &deps,
+                    QueryMsg::MyActivity {
+                        address: mock_user_address(),
+                        key: MOCK_VIEWING_KEY.to_string(),
+                        filters: ActivityFilters {
+                            activity: Some(ActivityKind::Filled),
+                            pair: Some(TokenPair {
+                                from_token: mock_butt().address,
+                                to_token: mock_token().address,
+                            }),
+                            ..ActivityFilters::default()
+                        },
+                        pagination,
+                    },
+                )
+                .unwrap();
+                match from_binary(&res).unwrap() {
+                    QueryAnswer::ActivityRecords {
+                        activity_records,
+                        next_start_after,
+                        ..
+                    } => (
+                        activity_records
+                            .iter()
+                            .map(|activity_record| activity_record.position.u128())
+                            .collect(),
+                        next_start_after,
+                    ),
+                    _ => panic!("unexpected"),
+                }
+            };
+        assert_eq!(
+            query_filled_records(pagination_helper(None, 1)),
+            (vec![3], Some(Uint128(3)))
+        );
+        assert_eq!(
+            query_filled_records(pagination_helper(Some(3), 1)),
+            (vec![2], None)
+        );
+        assert_eq!(
+            query_filled_records(pagination_helper(Some(2), 1)),
+            (vec![], None)
+        );
+
+        // = when the user has no activity
+        // = * it returns no records
+        let res = query(
+            &deps,
+            QueryMsg::MyActivity {
+                address: config.admin,
+                key: MOCK_VIEWING_KEY.to_string(),
+                filters: ActivityFilters::default(),
+                pagination: pagination_helper(None, 10),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::ActivityRecords {
+                activity_records,
+                total,
+                ..
+            } => {
+                assert_eq!(total, Some(Uint128(0)));
+                assert_eq!(activity_records, vec![]);
+            }
+            _ => panic!("unexpected"),
+        };
+    }
+
+    #[test]
+    fn test_order_book() {
+        let (_init_result, mut deps) = init_helper(true);
+        create_order_helper(&mut deps);
+        create_order_helper(&mut deps);
+        let receive_msg = ReceiveMsg::CreateOrder {
+            to_amount: Uint128(987_654_321),
+            to_token: mock_butt().address,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(123_456_789),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        handle(&mut deps, mock_env(mock_token().address, &[]), handle_msg).unwrap();
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::CancelOrder {
+                from_token_address: mock_butt().address,
+                position: Uint128(0),
+            },
         )
         .unwrap();
-        // ===== * it raises an error
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        let query_order_book = |pair: Option<TokenPair>,
+                                significant_figures: Option<u8>|
+         -> StdResult<(Vec<BookOrder>, Uint128)> {
+            let res = query(
+                &deps,
+                QueryMsg::OrderBook {
+                    pair,
+                    significant_figures,
+                    pagination: None,
+                },
+            )?;
+            match from_binary(&res).unwrap() {
+                QueryAnswer::OrderBook { orders, total, .. } => Ok((orders, total.unwrap())),
+                _ => panic!("unexpected"),
+            }
+        };
+
+        // = when pair and significant figures are not set
+        // = * it returns the open contract orders, newest first, without creators
+        let (orders, total) = query_order_book(None, None).unwrap();
+        assert_eq!(total, Uint128(2));
         assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::generic_err("Order already filled.")
+            orders
+                .iter()
+                .map(|order| order.position.u128())
+                .collect::<Vec<u128>>(),
+            vec![2, 1]
         );
-        // ===== when order is open
-        creator_order.from_amount_filled = Uint128(0);
-        update_creator_order_and_associated_contract_order(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            creator_order.clone(),
+        let contract_order: Order = order_at_position(
+            &deps.storage,
             &deps
                 .api
                 .canonical_address(&mock_contract().address)
                 .unwrap(),
+            2,
         )
         .unwrap();
-        // ===== * it sets the execution fee for the user order
-        let handle_unwrapped = handle(&mut deps, env.clone(), handle_msg.clone()).unwrap();
-        creator_order = order_at_position(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
-        )
-        .unwrap();
-        assert_eq!(creator_order.execution_fee, Some(mock_execution_fee()));
-        // ===== * it sets the execution fee for the contract order
-        let contract_order = order_at_position(
-            &mut deps.storage,
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
-            creator_order.other_storage_position.u128(),
+        assert_eq!(
+            orders[0],
+            BookOrder {
+                position: Uint128(2),
+                from_token: mock_token().address,
+                to_token: mock_butt().address,
+                from_amount_remaining: Uint128(123_456_789),
+                net_to_amount_remaining: contract_order.net_to_amount,
+                execution_fee: None,
+            }
+        );
+
+        // = when filtering by pair
+        // = * it returns the open orders for the pair
+        let (orders, total) = query_order_book(
+            Some(TokenPair {
+                from_token: mock_butt().address,
+                to_token: mock_token().address,
+            }),
+            None,
         )
         .unwrap();
-        assert_eq!(contract_order.execution_fee, Some(mock_execution_fee()));
-        // ===== * it sends the humanized creator order back as data
+        assert_eq!(total, Uint128(1));
+        assert_eq!(orders[0].position, Uint128(1));
+
+        // = when significant figures are set
+        // == when significant figures is zero
+        // == * it raises an error
         assert_eq!(
-            handle_unwrapped.data,
-            pad_response(Ok(HandleResponse {
-                messages: vec![],
-                log: vec![],
-                data: Some(
-                    to_binary(&creator_order.clone().into_humanized(&deps.api).unwrap()).unwrap()
+            query_order_book(None, Some(0)).unwrap_err(),
+            StdError::generic_err("Significant figures must be greater than zero.")
+        );
+        // == when significant figures is greater than zero
+        // == * it rounds the remaining amounts down
+        let (orders, _) = query_order_book(None, Some(3)).unwrap();
+        assert_eq!(orders[0].from_amount_remaining, Uint128(123_000_000));
+        assert_eq!(orders[1].from_amount_remaining, Uint128(MOCK_AMOUNT));
+
+        // = when paging
+        let query_page = |deps: &Extern<MockStorage, MockApi, MockQuerier>,
+                          start_after: Option<u128>,
+                          order: SortOrder|
+         -> (Vec<u128>, Option<Uint128>, Uint128) {
+            let res = query(
+                deps,
+                QueryMsg::OrderBook {
+                    pair: None,
+                    significant_figures: None,
+                    pagination: Some(Pagination {
+                        order: Some(order),
+                        ..pagination_helper(start_after, 1).unwrap()
+                    }),
+                },
+            )
+            .unwrap();
+            match from_binary(&res).unwrap() {
+                QueryAnswer::OrderBook {
+                    orders,
+                    total,
+                    next_start_after,
+                    ..
+                } => (
+                    orders.iter().map(|order| order.position.u128()).collect(),
+                    next_start_after,
+                    total.unwrap(),
                 ),
-            }))
-            .unwrap()
-            .data
+                _ => panic!("unexpected"),
+            }
+        };
+        // = * it returns the cursor for the next page
+        assert_eq!(
+            query_page(&deps, None, SortOrder::Descending),
+            (vec![2], Some(Uint128(2)), Uint128(2))
         );
-
-        // === when current block is different from the block when the order is created
-        let mut creator_order = order_at_position(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
+        // == when the order at the cursor has been closed since
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::CancelOrder {
+                from_token_address: mock_token().address,
+                position: Uint128(2),
+            },
         )
         .unwrap();
-        creator_order.created_at_block_height = 1;
-        update_creator_order_and_associated_contract_order(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            creator_order.clone(),
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
+        // == * it continues from the cursor without the closed order
+        assert_eq!(
+            query_page(&deps, Some(2), SortOrder::Descending),
+            (vec![1], None, Uint128(1))
+        );
+        assert_eq!(
+            query_page(&deps, None, SortOrder::Descending),
+            (vec![1], None, Uint128(1))
+        );
+        // == when the last order is closed after its page and a new one is created
+        create_order_helper(&mut deps);
+        assert_eq!(
+            query_page(&deps, Some(1), SortOrder::Ascending),
+            (vec![3], None, Uint128(2))
+        );
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::CancelOrder {
+                from_token_address: mock_butt().address,
+                position: Uint128(3),
+            },
         )
         .unwrap();
-        // === * it raises an error
-        let handle_result = handle(&mut deps, env.clone(), handle_msg);
+        create_order_helper(&mut deps);
+        // == * it continues from the cursor to the new order
         assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::generic_err(
-                "Execution fee must be set at the same block as when order is created."
-            )
+            query_page(&deps, Some(3), SortOrder::Ascending),
+            (vec![4], None, Uint128(2))
+        );
+        assert_eq!(
+            query_page(&deps, Some(3), SortOrder::Descending),
+            (vec![1], None, Uint128(2))
         );
     }
 
     #[test]
-    fn test_cancel_order() {
+    fn test_orders() {
         let (_init_result, mut deps) = init_helper(true);
-        let env = mock_env(mock_user_address(), &[]);
+        for _ in 0..3 {
+            create_order_helper(&mut deps);
+        }
+        let query_orders = |pagination: Option<Pagination>| -> (Vec<u128>, Option<Uint128>) {
+            let res = query(
+                &deps,
+                QueryMsg::Orders {
+                    address: mock_user_address(),
+                    key: MOCK_VIEWING_KEY.to_string(),
+                    pagination,
+                },
+            )
+            .unwrap();
+            match from_binary(&res).unwrap() {
+                QueryAnswer::Orders {
+                    orders,
+                    total,
+                    next_start_after,
+                    ..
+                } => {
+                    assert_eq!(total, Some(Uint128(3)));
+                    (
+                        orders.iter().map(|order| order.position.u128()).collect(),
+                        next_start_after,
+                    )
+                }
+                _ => panic!("unexpected"),
+            }
+        };
 
-        // = when order at position does not exist
-        let mut handle_msg = HandleMsg::CancelOrder {
-            from_token_address: mock_token().address,
-            position: Uint128(0),
+        // = when pagination is not set
+        // = * it returns the orders newest first
+        assert_eq!(query_orders(None), (vec![2, 1, 0], None));
+
+        // = when there are more orders than the limit
+        // = * it returns the cursor for the next page
+        assert_eq!(
+            query_orders(pagination_helper(None, 2)),
+            (vec![2, 1], Some(Uint128(1)))
+        );
+        assert_eq!(query_orders(pagination_helper(Some(1), 2)), (vec![0], None));
+
+        // = when ordered ascending
+        // = * it returns the orders oldest first
+        let ascending = |start_after: Option<u128>| {
+            Some(Pagination {
+                order: Some(SortOrder::Ascending),
+                ..pagination_helper(start_after, 2).unwrap()
+            })
         };
-        let mut handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            query_orders(ascending(None)),
+            (vec![0, 1], Some(Uint128(1)))
+        );
+        assert_eq!(query_orders(ascending(Some(1))), (vec![2], None));
+
+        // = when start_after is past the end
+        // = * it returns no orders
+        assert_eq!(query_orders(ascending(Some(5))), (vec![], None));
+        assert_eq!(query_orders(pagination_helper(Some(0), 2)), (vec![], None));
+
+        // = * it pads the answer to a multiple of the block size
+        let query_length = |pagination: Option<Pagination>| -> usize {
+            query(
+                &deps,
+                QueryMsg::Orders {
+                    address: mock_user_address(),
+                    key: MOCK_VIEWING_KEY.to_string(),
+                    pagination,
+                },
+            )
+            .unwrap()
+            .len()
+        };
+        let length: usize = query_length(pagination_helper(None, 3));
+        assert_eq!(length % BLOCK_SIZE, 0);
+        // = when bucketed
+        // = * it pads the answer to a power of two number of blocks
+        let bucketed = |limit: u128| {
+            Some(Pagination {
+                bucketed: Some(true),
+                ..pagination_helper(None, limit).unwrap()
+            })
+        };
+        for limit in 1..=3 {
+            let length: usize = query_length(bucketed(limit));
+            assert_eq!(length % BLOCK_SIZE, 0);
+            assert!((length / BLOCK_SIZE).is_power_of_two());
+        }
+        assert!(query_length(bucketed(3)) >= length);
+
+        // = when the limit is above the maximum page size
+        // = * it uses the maximum page size
+        assert_eq!(
+            Page::new(
+                pagination_helper(None, MAX_PAGE_SIZE + 1),
+                SortOrder::Ascending
+            )
+            .limit,
+            MAX_PAGE_SIZE
+        );
+    }
+
+    #[test]
+    fn test_orders_by_positions() {
+        let (_init_result, mut deps) = init_helper(true);
 
+        // when user's address and butt viewing key combo is correct
+        // = when user does not have any orders yet
         // = * it raises an error
+        let mut res = query(
+            &deps,
+            QueryMsg::OrdersByPositions {
+                address: mock_user_address(),
+                key: MOCK_VIEWING_KEY.to_string(),
+                positions: vec![Uint128(0)],
+            },
+        );
         assert_eq!(
-            handle_result.unwrap_err(),
+            res.unwrap_err(),
             NotFound {
                 kind: "cw_secret_network_limit_orders::state::Order".to_string(),
                 backtrace: None
             }
         );
 
-        // = when order at position exists
+        // = when user has orders
         create_order_helper(&mut deps);
-        // == when token used to cancel doesn't match the from_token
-        handle_result = handle(&mut deps, env.clone(), handle_msg);
-        // == * it raises an error
-        assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::generic_err("From token address does not match the order's from token.")
+        create_order_helper(&mut deps);
+        create_order_helper(&mut deps);
+        create_order_helper(&mut deps);
+        create_order_helper(&mut deps);
+        // == when position requested is unavailable
+        res = query(
+            &deps,
+            QueryMsg::OrdersByPositions {
+                address: mock_user_address(),
+                key: MOCK_VIEWING_KEY.to_string(),
+                positions: vec![Uint128(1), Uint128(2), Uint128(3), Uint128(5)],
+            },
         );
-        // == when token used to cancel matches the from_token
-        handle_msg = HandleMsg::CancelOrder {
-            from_token_address: mock_butt().address,
-            position: Uint128(0),
-        };
-        // === when order is cancelled
-        let mut creator_order = order_at_position(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
-        )
-        .unwrap();
-        creator_order.cancelled = true;
-        update_creator_order_and_associated_contract_order(
-            &mut deps.storage,
-            &creator_order.creator,
-            creator_order.clone(),
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
-        )
-        .unwrap();
-        // === * it raises an error
-        handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
         assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::generic_err("Order already cancelled.")
+            res.unwrap_err(),
+            NotFound {
+                kind: "cw_secret_network_limit_orders::state::Order".to_string(),
+                backtrace: None
+            }
         );
-        // === when order is filled
-        creator_order.cancelled = false;
-        creator_order.from_amount_filled = creator_order.from_amount;
-        update_creator_order_and_associated_contract_order(
-            &mut deps.storage,
-            &creator_order.creator,
-            creator_order.clone(),
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
+        // == when position requested is available
+        res = query(
+            &deps,
+            QueryMsg::OrdersByPositions {
+                address: mock_user_address(),
+                key: MOCK_VIEWING_KEY.to_string(),
+                positions: vec![Uint128(1), Uint128(3), Uint128(4)],
+            },
+        );
+        // == * it returns the humanized orders at those positions
+        let query_answer: QueryAnswer = from_binary(&res.unwrap()).unwrap();
+        match query_answer {
+            QueryAnswer::Orders { orders, total, .. } => {
+                assert_eq!(total, None);
+                assert_eq!(orders[0].creator, mock_user_address());
+                assert_eq!(orders[0].position, Uint128(1));
+                assert_eq!(orders[1].position, Uint128(3));
+                assert_eq!(orders[2].position, Uint128(4));
+                // == * it returns the status and remaining amounts of each order
+                assert_eq!(orders[0].status, OrderStatus::Open);
+                assert_eq!(orders[0].from_amount_remaining, orders[0].from_amount);
+                assert_eq!(orders[0].net_to_amount_remaining, orders[0].net_to_amount);
+                assert_eq!(orders[0].fill_percentage, Uint128(0));
+                // == * it returns the limit price in whole tokens
+                assert_eq!(
+                    orders[0].limit_price,
+                    Some(Uint128(
+                        orders[0].net_to_amount.u128() * PRICE_SCALE / orders[0].from_amount.u128()
+                    ))
+                );
+            }
+            _ => panic!("unexpected"),
+        };
+
+        // == when orders have been filled or cancelled and the tokens have different decimals
+        for (token, decimals) in [(mock_butt(), 8), (mock_token(), 6)].iter() {
+            handle(
+                &mut deps,
+                mock_env(MOCK_ADMIN, &[]),
+                HandleMsg::UpdateToken {
+                    address: token.address.clone(),
+                    contract_hash: token.contract_hash.clone(),
+                    decimals: Some(*decimals),
+                },
+            )
+            .unwrap();
+        }
+        let user_canonical_address: CanonicalAddr =
+            deps.api.canonical_address(&mock_user_address()).unwrap();
+        let order: Order = order_at_position(&deps.storage, &user_canonical_address, 1).unwrap();
+        for (position, amount) in [
+            (1, order.net_to_amount.u128() / 4),
+            (2, order.net_to_amount.u128()),
+        ]
+        .iter()
+        {
+            let receive_msg = ReceiveMsg::FillOrder {
+                position: Uint128(*position),
+            };
+            handle(
+                &mut deps,
+                mock_env(mock_token().address, &[]),
+                HandleMsg::Receive {
+                    sender: HumanAddr::from(MOCK_ADMIN),
+                    from: HumanAddr::from(MOCK_ADMIN),
+                    amount: Uint128(*amount),
+                    msg: Some(to_binary(&receive_msg).unwrap()),
+                },
+            )
+            .unwrap();
+        }
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::CancelOrder {
+                from_token_address: mock_butt().address,
+                position: Uint128(3),
+            },
         )
         .unwrap();
-        // === * it raises an error
-        handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
-        assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::generic_err("Order already filled.")
+        res = query(
+            &deps,
+            QueryMsg::OrdersByPositions {
+                address: mock_user_address(),
+                key: MOCK_VIEWING_KEY.to_string(),
+                positions: vec![Uint128(1), Uint128(2), Uint128(3)],
+            },
+        );
+        let query_answer: QueryAnswer = from_binary(&res.unwrap()).unwrap();
+        match query_answer {
+            QueryAnswer::Orders { orders, .. } => {
+                // == * it returns the status of each order
+                assert_eq!(
+                    orders.iter().map(|order| order.status).collect::<Vec<_>>(),
+                    vec![
+                        OrderStatus::PartiallyFilled,
+                        OrderStatus::Filled,
+                        OrderStatus::Cancelled
+                    ]
+                );
+                // == * it returns the remaining amounts and the fill percentage
+                assert_eq!(
+                    orders[0].net_to_amount_remaining,
+                    (order.net_to_amount - Uint128(order.net_to_amount.u128() / 4)).unwrap()
+                );
+                assert_eq!(
+                    orders[0].from_amount_remaining,
+                    (orders[0].from_amount - orders[0].from_amount_filled).unwrap()
+                );
+                assert_eq!(orders[0].fill_percentage, Uint128(2_500));
+                assert_eq!(orders[1].net_to_amount_remaining, Uint128(0));
+                assert_eq!(orders[1].fill_percentage, Uint128(10_000));
+                // == * it adjusts the limit price for the decimals
+                assert_eq!(
+                    orders[0].limit_price,
+                    Some(Uint128(
+                        order.net_to_amount.u128() * PRICE_SCALE * 100 / order.from_amount.u128()
+                    ))
+                );
+            }
+            _ => panic!("unexpected"),
+        };
+    }
+
+    #[test]
+    fn test_pair_stats() {
+        let (_init_result, mut deps) = init_helper(true);
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+
+        // = when pair is not registered
+        // = * it raises an error
+        let query_result = query(
+            &deps,
+            QueryMsg::PairStats {
+                from_token: mock_butt().address,
+                to_token: mock_sscrt().address,
+            },
         );
-        // === when order can be cancelled
-        creator_order.from_amount_filled = Uint128(5);
-        update_creator_order_and_associated_contract_order(
-            &mut deps.storage,
-            &creator_order.creator,
-            creator_order.clone(),
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
-        )
-        .unwrap();
-        let from_registered_token: RegisteredToken = read_registered_token(
-            &deps.storage,
-            &deps
-                .api
-                .canonical_address(&creator_order.from_token)
-                .unwrap(),
-        )
-        .unwrap();
-        let sum_balance_before_cancel: Uint128 = from_registered_token.sum_balance;
-        handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
-        // === * it reduces the from token's sum_balance by the unfilled amount
-        let from_registered_token: RegisteredToken = read_registered_token(
-            &deps.storage,
-            &deps
-                .api
-                .canonical_address(&creator_order.from_token)
-                .unwrap(),
-        )
-        .unwrap();
-        let unfilled_amount: Uint128 =
-            (creator_order.from_amount - creator_order.from_amount_filled).unwrap();
         assert_eq!(
-            from_registered_token.sum_balance,
-            (sum_balance_before_cancel - unfilled_amount).unwrap()
+            query_result.unwrap_err(),
+            StdError::generic_err("Pair is not registered.")
         );
 
-        // === * it sends the unfilled from token amount back to the creator
-        let handle_result_unwrapped = handle_result.unwrap();
+        // = when pair is registered
+        let query_msg = QueryMsg::PairStats {
+            from_token: mock_butt().address,
+            to_token: mock_token().address,
+        };
+        // == when pair has no fills
+        // == * it returns empty stats
+        let res = query(&deps, query_msg.clone()).unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::PairStats { pair_stats, tokens } => {
+                assert_eq!(
+                    pair_stats,
+                    PairStats {
+                        from_token: mock_butt().address,
+                        to_token: mock_token().address,
+                        ..PairStats::default()
+                    }
+                );
+                // == * it returns the metadata of both tokens
+                assert_eq!(
+                    tokens
+                        .iter()
+                        .map(|token| (token.address.clone(), token.decimals))
+                        .collect::<Vec<_>>(),
+                    vec![
+                        (mock_butt().address, Some(6)),
+                        (mock_token().address, Some(6))
+                    ]
+                );
+            }
+            _ => panic!("unexpected"),
+        };
+
+        // == when pair has fills
+        create_order_helper(&mut deps);
+        let mut env = mock_env(mock_token().address, &[]);
+        let handle_msg = HandleMsg::Receive {
+            sender: config.admin.clone(),
+            from: config.admin.clone(),
+            amount: Uint128(MOCK_AMOUNT / 4),
+            msg: Some(
+                to_binary(&ReceiveMsg::FillOrder {
+                    position: Uint128(0),
+                })
+                .unwrap(),
+            ),
+        };
+        handle(&mut deps, env.clone(), handle_msg.clone()).unwrap();
+        env.block.time += ROLLING_VOLUME_PERIOD_IN_SECONDS - ROLLING_VOLUME_BUCKET_IN_SECONDS;
+        handle(&mut deps, env.clone(), handle_msg.clone()).unwrap();
+        // == * it returns the stats including the rolling volume since the last fill
+        let res = query(&deps, query_msg.clone()).unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::PairStats { pair_stats, .. } => {
+                assert_eq!(pair_stats.trade_count, Uint128(2));
+                assert_eq!(pair_stats.last_fill_block_time, env.block.time);
+                assert_eq!(pair_stats.last_fill_price, Uint128(PRICE_SCALE));
+                assert_eq!(pair_stats.vwap, Uint128(PRICE_SCALE));
+                assert_eq!(pair_stats.cumulative_from_volume, Uint128(MOCK_AMOUNT / 2));
+                assert_eq!(pair_stats.cumulative_to_volume, Uint128(MOCK_AMOUNT / 2));
+                assert_eq!(pair_stats.rolling_from_volume, Uint128(MOCK_AMOUNT / 2));
+                assert_eq!(pair_stats.rolling_to_volume, Uint128(MOCK_AMOUNT / 2));
+                assert_eq!(pair_stats.rolling_volume_buckets.len(), 2);
+            }
+            _ => panic!("unexpected"),
+        };
+        // === when a fill happens more than 24 hours after earlier fills
+        env.block.time += ROLLING_VOLUME_BUCKET_IN_SECONDS;
+        handle(&mut deps, env, handle_msg).unwrap();
+        // === * it drops the earlier fills from the rolling volume
+        let res = query(&deps, query_msg).unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::PairStats { pair_stats, .. } => {
+                assert_eq!(pair_stats.trade_count, Uint128(3));
+                assert_eq!(
+                    pair_stats.cumulative_from_volume,
+                    Uint128(MOCK_AMOUNT * 3 / 4)
+                );
+                assert_eq!(pair_stats.rolling_from_volume, Uint128(MOCK_AMOUNT / 2));
+                assert_eq!(pair_stats.rolling_to_volume, Uint128(MOCK_AMOUNT / 2));
+            }
+            _ => panic!("unexpected"),
+        };
+
+        // = when the price of a fill can't be represented
+        let mut pair_stats: PairStats = PairStats::default();
+        pair_stats
+            .add_fill(Uint128(1), Uint128(MOCK_AMOUNT), 0)
+            .unwrap();
+        pair_stats
+            .add_fill(Uint128(1), Uint128(u128::MAX - MOCK_AMOUNT), 0)
+            .unwrap();
+        // = * it keeps the previous prices
         assert_eq!(
-            handle_result_unwrapped.messages,
-            vec![snip20::transfer_msg(
-                deps.api.human_address(&creator_order.creator).unwrap(),
-                unfilled_amount,
-                None,
-                BLOCK_SIZE,
-                from_registered_token.contract_hash.clone(),
-                from_registered_token.address.clone(),
-            )
-            .unwrap()]
+            pair_stats.last_fill_price,
+            Uint128(MOCK_AMOUNT * PRICE_SCALE)
         );
-        // === * it sends the creator order as humanized back as data
-        let creator_order = order_at_position(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
-        )
-        .unwrap();
+        assert_eq!(pair_stats.vwap, Uint128(MOCK_AMOUNT * PRICE_SCALE));
+        assert_eq!(pair_stats.cumulative_to_volume, Uint128(u128::MAX));
+        // = when the volume can't be represented
+        // = * it raises an error
         assert_eq!(
-            handle_result_unwrapped.data,
-            pad_response(Ok(HandleResponse {
-                messages: vec![],
-                log: vec![],
-                data: Some(
-                    to_binary(&creator_order.clone().into_humanized(&deps.api).unwrap()).unwrap()
-                ),
-            }))
-            .unwrap()
-            .data
+            pair_stats.add_fill(Uint128(1), Uint128(1), 0).unwrap_err(),
+            StdError::generic_err("Reached implementation limit for volume.")
         );
+    }
 
-        // === * it sets cancelled to true
-        let mut creator_order = order_at_position(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
+    #[test]
+    fn test_pairs() {
+        let (_init_result, mut deps) = init_helper(true);
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::RegisterTokens {
+                tokens: vec![mock_sscrt()],
+                viewing_key: MOCK_VIEWING_KEY.to_string(),
+            },
         )
         .unwrap();
-        let contract_order = order_at_position(
-            &mut deps.storage,
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
-            creator_order.other_storage_position.u128(),
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetPairs {
+                pairs: vec![mock_pair(mock_sscrt().address, mock_butt().address)],
+            },
         )
         .unwrap();
-        assert_eq!(creator_order.cancelled, true);
-        assert_eq!(contract_order.cancelled, true);
 
-        // ===== * it creates an activity record
-        let (activity_records, total) = get_activity_records(
-            &deps.storage,
-            &deps
-                .api
-                .canonical_address(&HumanAddr::from(MOCK_ADMIN))
-                .unwrap(),
-            0,
-            50,
-            PREFIX_CANCEL_RECORDS,
+        // = when page is within range
+        // = * it returns the pairs in the order they were added
+        let res = query(
+            &deps,
+            QueryMsg::Pairs {
+                pagination: pagination_helper(None, 2),
+            },
         )
         .unwrap();
-        assert_eq!(total, 1);
-        assert_eq!(
-            activity_records[0],
-            ActivityRecord {
-                position: Uint128(0),
-                order_position: contract_order.position,
-                activity: 0,
-                result_from_amount_filled: None,
-                result_net_to_amount_filled: None,
-                updated_at_block_height: env.block.height.clone(),
-                updated_at_block_time: env.block.time
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::Pairs { pairs, total, .. } => {
+                assert_eq!(total, Some(Uint128(3)));
+                assert_eq!(
+                    pairs,
+                    vec![
+                        mock_pair(mock_butt().address, mock_token().address),
+                        mock_pair(mock_token().address, mock_butt().address)
+                    ]
+                );
             }
-        );
-        // ==== when order has an execution fee
-        creator_order.execution_fee = Some(Uint128(1));
-        creator_order.cancelled = false;
-        creator_order.from_amount_filled = Uint128(999999999999);
-        update_creator_order_and_associated_contract_order(
-            &mut deps.storage,
-            &creator_order.creator,
-            creator_order.clone(),
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
+            _ => panic!("unexpected"),
+        };
+        let res = query(
+            &deps,
+            QueryMsg::Pairs {
+                pagination: pagination_helper(Some(1), 2),
+            },
         )
         .unwrap();
-        // ===== when order is partially filled
-        // ===== * it does not send the execution fee back to the creator
-        handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
-        assert_eq!(
-            handle_result.unwrap().messages,
-            vec![snip20::transfer_msg(
-                deps.api.human_address(&creator_order.creator).unwrap(),
-                (creator_order.from_amount - creator_order.from_amount_filled).unwrap(),
-                None,
-                BLOCK_SIZE,
-                from_registered_token.contract_hash.clone(),
-                from_registered_token.address.clone(),
-            )
-            .unwrap()]
-        );
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::Pairs { pairs, .. } => {
+                assert_eq!(
+                    pairs,
+                    vec![mock_pair(mock_sscrt().address, mock_butt().address)]
+                );
+            }
+            _ => panic!("unexpected"),
+        };
 
-        // ===== when order has not been partially filled
-        creator_order.from_amount = Uint128(1);
-        creator_order.execution_fee = Some(Uint128(1));
-        creator_order.cancelled = false;
-        creator_order.from_amount_filled = Uint128(0);
-        update_creator_order_and_associated_contract_order(
-            &mut deps.storage,
-            &creator_order.creator,
-            creator_order.clone(),
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
+        // = when start_after is past the end
+        // = * it returns no pairs
+        let res = query(
+            &deps,
+            QueryMsg::Pairs {
+                pagination: pagination_helper(Some(3), 2),
+            },
         )
         .unwrap();
-
-        // ===== * it sends the execution fee back to the creator
-        handle_result = handle(&mut deps, env.clone(), handle_msg);
-        assert_eq!(
-            handle_result.unwrap().messages,
-            vec![
-                snip20::transfer_msg(
-                    deps.api.human_address(&creator_order.creator).unwrap(),
-                    (creator_order.from_amount - creator_order.from_amount_filled).unwrap(),
-                    None,
-                    BLOCK_SIZE,
-                    from_registered_token.contract_hash,
-                    from_registered_token.address,
-                )
-                .unwrap(),
-                snip20::transfer_msg(
-                    deps.api.human_address(&creator_order.creator).unwrap(),
-                    creator_order.execution_fee.unwrap(),
-                    None,
-                    BLOCK_SIZE,
-                    mock_sscrt().contract_hash,
-                    mock_sscrt().address,
-                )
-                .unwrap()
-            ]
-        );
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::Pairs { pairs, total, .. } => {
+                assert_eq!(total, Some(Uint128(3)));
+                assert_eq!(pairs, vec![]);
+            }
+            _ => panic!("unexpected"),
+        };
     }
 
     #[test]
-    fn test_config() {
-        let (_init_result, deps) = init_helper(false);
+    fn test_propose_admin() {
+        let (_init_result, mut deps) = init_helper(false);
+        let handle_msg = HandleMsg::ProposeAdmin {
+            address: mock_user_address(),
+        };
 
-        let res = query(&deps, QueryMsg::Config {}).unwrap();
-        let value: Config = from_binary(&res).unwrap();
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
         assert_eq!(
-            Config {
-                addresses_allowed_to_fill: vec![
-                    HumanAddr::from(MOCK_ADMIN),
-                    mock_contract().address
-                ],
-                admin: HumanAddr::from(MOCK_ADMIN),
-                butt: mock_butt(),
-                execution_fee: mock_execution_fee(),
-                sscrt: mock_sscrt(),
-            },
-            value
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
         );
+
+        // = when called by the admin
+        handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg).unwrap();
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        // = * it sets the pending admin
+        assert_eq!(config.pending_admin, Some(mock_user_address()));
+        // = * it does not change the admin
+        assert_eq!(config.admin, HumanAddr::from(MOCK_ADMIN));
     }
 
     #[test]
-    fn test_create_order() {
-        let (_init_result, mut deps) = init_helper(true);
-
-        // = when to_token isn't registered
-        let receive_msg = ReceiveMsg::CreateOrder {
-            to_amount: Uint128(MOCK_AMOUNT),
-            to_token: mock_user_address(),
-        };
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_user_address(),
-            from: mock_user_address(),
-            amount: Uint128(MOCK_AMOUNT),
-            msg: Some(to_binary(&receive_msg).unwrap()),
+    fn test_propose_config_change() {
+        let (_init_result, mut deps) = init_helper(false);
+        let handle_msg = HandleMsg::ProposeConfigChange {
+            addresses_allowed_to_fill: Some(vec![mock_user_address()]),
+            config_change_delay_in_blocks: None,
+            execution_fee: Some(Uint128(MOCK_AMOUNT)),
+            open_filling: None,
         };
-        // = * it raises an error
+        let env = mock_env(mock_user_address(), &[]);
+
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // = when called by the admin
+        // == when nothing is being changed
+        // == * it raises an error
         let handle_result = handle(
             &mut deps,
-            mock_env(mock_butt().address, &[]),
-            handle_msg.clone(),
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::ProposeConfigChange {
+                addresses_allowed_to_fill: None,
+                config_change_delay_in_blocks: None,
+                execution_fee: None,
+                open_filling: None,
+            },
         );
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::generic_err("To token is not registered.")
+            StdError::generic_err("Config change is empty.")
         );
-
-        // = when to_token is registered
-        let receive_msg = ReceiveMsg::CreateOrder {
-            to_amount: Uint128(MOCK_AMOUNT),
-            to_token: mock_token().address,
-        };
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_user_address(),
-            from: mock_user_address(),
-            amount: Uint128(MOCK_AMOUNT),
-            msg: Some(to_binary(&receive_msg).unwrap()),
+        // == when something is being changed
+        let handle_result_unwrapped =
+            handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg).unwrap();
+        let pending_config_change = PendingConfigChange {
+            id: Uint128(0),
+            addresses_allowed_to_fill: Some(vec![mock_user_address()]),
+            config_change_delay_in_blocks: None,
+            execution_fee: Some(Uint128(MOCK_AMOUNT)),
+            open_filling: None,
+            proposed_at_block_height: env.block.height,
+            executable_at_block_height: env.block.height + DEFAULT_CONFIG_CHANGE_DELAY_IN_BLOCKS,
         };
-
-        // == when order is created
-        // === * it increases the sum balance for the from_token
+        // == * it does not change the config
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(config.execution_fee, mock_execution_fee());
+        // == * it sends the pending config change back as data
         assert_eq!(
-            read_registered_token(
-                &deps.storage,
-                &deps.api.canonical_address(&mock_butt().address).unwrap()
-            )
-            .unwrap()
-            .sum_balance,
-            Uint128(0)
+            handle_result_unwrapped.data,
+            Some(to_binary(&pending_config_change).unwrap())
         );
-        let handle_unwrapped = handle(
+        // == * it queues the config change
+        let res = query(&deps, QueryMsg::PendingConfigChanges { pagination: None }).unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::PendingConfigChanges {
+                pending_config_changes,
+                total,
+                next_start_after,
+            } => {
+                assert_eq!(pending_config_changes, vec![pending_config_change]);
+                assert_eq!(total, Some(Uint128(1)));
+                assert_eq!(next_start_after, None);
+            }
+            _ => panic!("unexpected"),
+        };
+
+        // = when called by a filler manager
+        handle(
             &mut deps,
-            mock_env(mock_butt().address, &[]),
-            handle_msg.clone(),
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::GrantRole {
+                role: Role::FillerManager,
+                address: mock_user_address(),
+            },
         )
         .unwrap();
-        assert_eq!(
-            read_registered_token(
-                &deps.storage,
-                &deps.api.canonical_address(&mock_butt().address).unwrap()
-            )
-            .unwrap()
-            .sum_balance,
-            Uint128(MOCK_AMOUNT)
+        // == when changing the execution fee
+        // == * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::ProposeConfigChange {
+                addresses_allowed_to_fill: None,
+                config_change_delay_in_blocks: None,
+                execution_fee: Some(mock_execution_fee()),
+                open_filling: None,
+            },
         );
-        // === * it sends the humanized creator order back as data
-        let order: Order = Order {
-            position: Uint128(0),
-            execution_fee: None,
-            other_storage_position: Uint128(0),
-            from_token: mock_butt().address,
-            to_token: mock_token().address,
-            creator: deps.api.canonical_address(&mock_user_address()).unwrap(),
-            from_amount: Uint128(MOCK_AMOUNT),
-            from_amount_filled: Uint128(0),
-            net_to_amount: Uint128(MOCK_AMOUNT),
-            net_to_amount_filled: Uint128(0),
-            cancelled: false,
-            fee: Uint128(0),
-            created_at_block_time: mock_env(MOCK_ADMIN, &[]).block.time,
-            created_at_block_height: mock_env(MOCK_ADMIN, &[]).block.height,
-        };
         assert_eq!(
-            handle_unwrapped.data,
-            pad_response(Ok(HandleResponse {
-                messages: vec![],
-                log: vec![],
-                data: Some(to_binary(&order.clone().into_humanized(&deps.api).unwrap()).unwrap()),
-            }))
-            .unwrap()
-            .data
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+        // == when changing the config change delay
+        // == * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::ProposeConfigChange {
+                addresses_allowed_to_fill: None,
+                config_change_delay_in_blocks: Some(1),
+                execution_fee: None,
+                open_filling: None,
+            },
         );
-
-        // === * it stores the order for the creator
-        // === * it stores the order for the smart_contract
         assert_eq!(
-            order_at_position(
-                &mut deps.storage,
-                &deps.api.canonical_address(&mock_user_address()).unwrap(),
-                0
-            )
-            .unwrap(),
-            order
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
         );
+        // == when changing the addresses allowed to fill
+        // == * it queues the config change with the next id
+        handle(
+            &mut deps,
+            env,
+            HandleMsg::ProposeConfigChange {
+                addresses_allowed_to_fill: Some(vec![]),
+                config_change_delay_in_blocks: None,
+                execution_fee: None,
+                open_filling: None,
+            },
+        )
+        .unwrap();
         assert_eq!(
-            order_at_position(
-                &mut deps.storage,
-                &deps
-                    .api
-                    .canonical_address(&mock_contract().address)
-                    .unwrap(),
-                0
-            )
-            .unwrap(),
-            order
+            read_pending_config_change(&deps.storage, 1).unwrap().id,
+            Uint128(1)
+        );
+        // == * it lists the pending config changes by id
+        let res = query(
+            &deps,
+            QueryMsg::PendingConfigChanges {
+                pagination: pagination_helper(None, 1),
+            },
         )
+        .unwrap();
+        match from_binary(&res).unwrap() {
+            QueryAnswer::PendingConfigChanges {
+                pending_config_changes,
+                total,
+                next_start_after,
+            } => {
+                assert_eq!(pending_config_changes[0].id, Uint128(0));
+                assert_eq!(total, Some(Uint128(2)));
+                assert_eq!(next_start_after, Some(Uint128(0)));
+            }
+            _ => panic!("unexpected"),
+        };
     }
 
     #[test]
-    fn test_fill_order() {
+    fn test_reclaim_commitment() {
         let (_init_result, mut deps) = init_helper(true);
-        let env = mock_env(mock_butt().address, &[]);
-
-        // when called by a non-admin
-        let receive_msg = ReceiveMsg::FillOrder {
+        commit_order_helper(&mut deps);
+        let handle_msg = HandleMsg::ReclaimCommitment {
             position: Uint128(0),
         };
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_user_address(),
-            from: mock_user_address(),
-            amount: Uint128(MOCK_AMOUNT),
-            msg: Some(to_binary(&receive_msg).unwrap()),
-        };
-        // * it raises an error
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+
+        // = when the commitment does not exist
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::ReclaimCommitment {
+                position: Uint128(1),
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Commitment not found.")
+        );
+
+        // = when called by someone other than the creator
+        // = * it raises an Unauthorized error
+        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg.clone());
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::Unauthorized { backtrace: None }
         );
 
-        // when called by an address that's allowed
-        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
-        // = when amount sent in is zero
-        let handle_msg = HandleMsg::Receive {
-            sender: config.admin.clone(),
-            from: env.contract.address.clone(),
-            amount: Uint128(0),
-            msg: Some(to_binary(&receive_msg).unwrap()),
-        };
-        let handle_result = handle(&mut deps, env.clone(), handle_msg);
+        // = when called by the creator
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        // = * it sends the committed amount back to the creator
+        assert_eq!(
+            handle_result.messages,
+            vec![snip20::transfer_msg(
+                mock_user_address(),
+                Uint128(MOCK_AMOUNT),
+                None,
+                BLOCK_SIZE,
+                mock_butt().contract_hash,
+                mock_butt().address,
+            )
+            .unwrap()]
+        );
+        // = * it closes the commitment
+        assert!(read_commitment(&deps.storage, 0).unwrap().closed);
+        // = * it decreases the sum balance of the from token
+        assert_eq!(
+            read_registered_token(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_butt().address).unwrap()
+            )
+            .unwrap()
+            .sum_balance,
+            Uint128(0)
+        );
+
+        // = when the commitment is closed
         // = * it raises an error
+        let handle_result = handle(&mut deps, mock_env(mock_user_address(), &[]), handle_msg);
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::generic_err("Amount must be greater than zero.")
+            StdError::generic_err("Commitment already closed.")
         );
-        // = when amount sent in is positive
-        let handle_msg = HandleMsg::Receive {
-            sender: config.admin.clone(),
-            from: config.admin.clone(),
-            amount: Uint128(1),
-            msg: Some(to_binary(&receive_msg).unwrap()),
+    }
+
+    #[test]
+    fn test_refresh_token_metadata() {
+        let (_init_result, mut deps) = init_helper(true);
+        let handle_msg = HandleMsg::RefreshTokenMetadata {
+            address: mock_token().address,
         };
-        // == when order does not exist
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
-        // == * it raises an error
+
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
         assert_eq!(
             handle_result.unwrap_err(),
-            NotFound {
-                kind: "cw_secret_network_limit_orders::state::Order".to_string(),
-                backtrace: None
-            }
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // = when called by the admin
+        // == when token is not registered
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::RefreshTokenMetadata {
+                address: mock_sscrt().address,
+            },
         );
-        // == when order exists
-        create_order_helper(&mut deps);
-        // === when to_token does not match the token sent in
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
-        // === * it raises an error
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::generic_err("To token does not match the token sent in.")
+            StdError::generic_err("Token is not registered.")
         );
-        // === when to token matches the token sent in
-        // ==== when order is cancelled
-        let mut creator_order = order_at_position(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
+        // == when token is registered
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::UpdateToken {
+                address: mock_token().address,
+                contract_hash: mock_token().contract_hash,
+                decimals: Some(18),
+            },
         )
         .unwrap();
-        creator_order.cancelled = true;
-        update_creator_order_and_associated_contract_order(
-            &mut deps.storage,
-            &creator_order.creator,
-            creator_order.clone(),
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
+        handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg).unwrap();
+        // == * it reads the metadata from the token again
+        let registered_token: RegisteredToken = read_registered_token(
+            &deps.storage,
+            &deps.api.canonical_address(&mock_token().address).unwrap(),
         )
         .unwrap();
+        assert_eq!(registered_token.name, Some("Mock Token".to_string()));
+        assert_eq!(registered_token.symbol, Some("MOCK".to_string()));
+        assert_eq!(registered_token.decimals, Some(6));
+    }
 
-        // ==== * it raises an error
-        let handle_result = handle(&mut deps, mock_env(mock_token().address, &[]), handle_msg);
-        assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::generic_err("Order already cancelled.")
-        );
-        // ==== when order is not cancelled
-        creator_order.cancelled = false;
-        update_creator_order_and_associated_contract_order(
-            &mut deps.storage,
-            &creator_order.creator,
-            creator_order.clone(),
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
+    #[test]
+    fn test_registered_tokens() {
+        let (_init_result, mut deps) = init_helper(true);
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::RegisterTokens {
+                tokens: vec![mock_sscrt(), mock_butt()],
+                viewing_key: MOCK_VIEWING_KEY.to_string(),
+            },
         )
         .unwrap();
+        create_order_helper(&mut deps);
 
-        // ===== when amount sent in is greater than unfilled amount
-        let handle_msg = HandleMsg::Receive {
-            sender: config.admin.clone(),
-            from: config.admin.clone(),
-            amount: Uint128(MOCK_AMOUNT + 1),
-            msg: Some(to_binary(&receive_msg).unwrap()),
+        // = when page is within range
+        // = * it returns the registered tokens in the order they were registered
+        let res = query(
+            &deps,
+            QueryMsg::RegisteredTokens {
+                pagination: pagination_helper(None, 2),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::RegisteredTokens {
+                registered_tokens,
+                total,
+                ..
+            } => {
+                assert_eq!(total, Some(Uint128(3)));
+                assert_eq!(
+                    registered_tokens,
+                    vec![
+                        RegisteredToken {
+                            address: mock_butt().address,
+                            contract_hash: mock_butt().contract_hash,
+                            sum_balance: Uint128(MOCK_AMOUNT),
+                            disabled: false,
+                            name: Some("Mock Token".to_string()),
+                            symbol: Some("MOCK".to_string()),
+                            decimals: Some(6),
+                        },
+                        RegisteredToken {
+                            address: mock_token().address,
+                            contract_hash: mock_token().contract_hash,
+                            sum_balance: Uint128(0),
+                            disabled: false,
+                            name: Some("Mock Token".to_string()),
+                            symbol: Some("MOCK".to_string()),
+                            decimals: Some(6),
+                        }
+                    ]
+                );
+            }
+            _ => panic!("unexpected"),
+        };
+        let res = query(
+            &deps,
+            QueryMsg::RegisteredTokens {
+                pagination: pagination_helper(Some(1), 2),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::RegisteredTokens {
+                registered_tokens, ..
+            } => {
+                assert_eq!(registered_tokens.len(), 1);
+                assert_eq!(registered_tokens[0].address, mock_sscrt().address);
+            }
+            _ => panic!("unexpected"),
         };
-        // ===== * it raises an error
-        let handle_result = handle(&mut deps, mock_env(mock_token().address, &[]), handle_msg);
-        assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::generic_err("Amount is greater than unfilled amount.")
-        );
 
-        // ===== when amount sent in is less than or equal to the net unfilled to amount
-        let handle_msg = HandleMsg::Receive {
-            sender: config.admin.clone(),
-            from: config.admin.clone(),
-            amount: Uint128(MOCK_AMOUNT / 2),
-            msg: Some(to_binary(&receive_msg).unwrap()),
+        // = when start_after is past the end
+        // = * it returns no registered tokens
+        let res = query(
+            &deps,
+            QueryMsg::RegisteredTokens {
+                pagination: pagination_helper(Some(9), 2),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&res).unwrap();
+        match query_answer {
+            QueryAnswer::RegisteredTokens {
+                registered_tokens, ..
+            } => {
+                assert_eq!(registered_tokens, vec![]);
+            }
+            _ => panic!("unexpected"),
+        };
+    }
+
+    #[test]
+    fn test_register_tokens() {
+        let (_init_result, mut deps) = init_helper(false);
+
+        // When tokens are in the parameter
+        let handle_msg = HandleMsg::RegisterTokens {
+            tokens: vec![mock_butt(), mock_token()],
+            viewing_key: MOCK_VIEWING_KEY.to_string(),
         };
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
         let handle_result = handle(
             &mut deps,
-            mock_env(mock_token().address, &[]),
+            mock_env(mock_user_address(), &[]),
             handle_msg.clone(),
         );
-        // ===== * it updates the from amount filled for both orders
-        // ===== * it updates the net to amount filled
-        let mut creator_order = order_at_position(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
-        )
-        .unwrap();
-        let contract_order = order_at_position(
-            &mut deps.storage,
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
-            creator_order.other_storage_position.u128(),
-        )
-        .unwrap();
         assert_eq!(
-            creator_order.from_amount_filled,
-            creator_order
-                .from_amount
-                .multiply_ratio(Uint128(1), Uint128(2))
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
         );
+
+        // = when called by the admin
+        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg.clone());
+        let handle_result_unwrapped = handle_result.unwrap();
+        // == when tokens are not registered
+        // == * it stores the registered tokens
         assert_eq!(
-            contract_order.from_amount_filled,
-            contract_order
-                .from_amount
-                .multiply_ratio(Uint128(1), Uint128(2))
+            read_registered_token(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_butt().address).unwrap()
+            )
+            .is_some(),
+            true
         );
         assert_eq!(
-            creator_order.net_to_amount_filled,
-            creator_order
-                .net_to_amount
-                .multiply_ratio(Uint128(1), Uint128(2))
+            read_registered_token(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_token().address).unwrap()
+            )
+            .is_some(),
+            true
         );
+        // == * it caches the name, symbol and decimals of the tokens
+        let registered_token: RegisteredToken = read_registered_token(
+            &deps.storage,
+            &deps.api.canonical_address(&mock_token().address).unwrap(),
+        )
+        .unwrap();
         assert_eq!(
-            contract_order.net_to_amount_filled,
-            contract_order
-                .net_to_amount
-                .multiply_ratio(Uint128(1), Uint128(2))
+            (
+                registered_token.name,
+                registered_token.symbol,
+                registered_token.decimals
+            ),
+            (
+                Some("Mock Token".to_string()),
+                Some("MOCK".to_string()),
+                Some(6)
+            )
         );
 
-        // ===== * it sends the correct ratio of the from_token to the admin
-        // ===== * it sends the amount to the creator
+        // == * it registers the contract with the tokens
+        // == * it sets the viewing key for the contract with the tokens
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![
+                snip20::register_receive_msg(
+                    mock_contract().contract_hash.clone(),
+                    None,
+                    BLOCK_SIZE,
+                    mock_butt().contract_hash,
+                    mock_butt().address,
+                )
+                .unwrap(),
+                snip20::set_viewing_key_msg(
+                    MOCK_VIEWING_KEY.to_string(),
+                    None,
+                    BLOCK_SIZE,
+                    mock_butt().contract_hash,
+                    mock_butt().address,
+                )
+                .unwrap(),
+                snip20::register_receive_msg(
+                    mock_contract().contract_hash,
+                    None,
+                    BLOCK_SIZE,
+                    mock_token().contract_hash,
+                    mock_token().address,
+                )
+                .unwrap(),
+                snip20::set_viewing_key_msg(
+                    MOCK_VIEWING_KEY.to_string(),
+                    None,
+                    BLOCK_SIZE,
+                    mock_token().contract_hash,
+                    mock_token().address,
+                )
+                .unwrap()
+            ]
+        );
+
+        // === context when tokens are registered
+        let mut registered_token: RegisteredToken = read_registered_token(
+            &deps.storage,
+            &deps.api.canonical_address(&mock_token().address).unwrap(),
+        )
+        .unwrap();
+        registered_token.sum_balance = Uint128(MOCK_AMOUNT);
+        write_registered_token(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_token().address).unwrap(),
+            &registered_token,
+        )
+        .unwrap();
+        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg);
+        let handle_result_unwrapped = handle_result.unwrap();
+        // === * it does not change the registered token's sum_balance
+        let registered_token: RegisteredToken = read_registered_token(
+            &deps.storage,
+            &deps.api.canonical_address(&mock_token().address).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(registered_token.sum_balance, Uint128(MOCK_AMOUNT));
+        // === * it sets the viewing key for the contract with the tokens
         assert_eq!(
-            handle_result.unwrap().messages,
+            handle_result_unwrapped.messages,
             vec![
-                snip20::send_msg(
-                    config.admin.clone(),
-                    Uint128(MOCK_AMOUNT / 2),
-                    None,
+                snip20::set_viewing_key_msg(
+                    MOCK_VIEWING_KEY.to_string(),
                     None,
                     BLOCK_SIZE,
                     mock_butt().contract_hash,
                     mock_butt().address,
                 )
                 .unwrap(),
-                snip20::transfer_msg(
-                    mock_user_address(),
-                    Uint128(MOCK_AMOUNT / 2),
+                snip20::set_viewing_key_msg(
+                    MOCK_VIEWING_KEY.to_string(),
                     None,
                     BLOCK_SIZE,
                     mock_token().contract_hash,
                     mock_token().address,
                 )
-                .unwrap(),
+                .unwrap()
             ]
         );
+    }
 
-        // ===== * it updates the from tokens sum balance
-        let from_registered_token: RegisteredToken = read_registered_token(
+    #[test]
+    fn test_reveal_order() {
+        let (_init_result, mut deps) = init_helper(true);
+        commit_order_helper(&mut deps);
+        let reveal_msg = |salt: &str| -> HandleMsg {
+            HandleMsg::RevealOrder {
+                position: Uint128(0),
+                to_amount: Uint128(MOCK_AMOUNT),
+                to_token: mock_token().address,
+                salt: salt.to_string(),
+            }
+        };
+        let mut env = mock_env(mock_user_address(), &[]);
+
+        // = when called by someone other than the creator
+        // = * it raises an Unauthorized error
+        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), reveal_msg(MOCK_SALT));
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // = when called by the creator
+        // == when the reveal delay has not passed
+        // == * it raises an error
+        env.block.height += REVEAL_DELAY_IN_BLOCKS - 1;
+        let handle_result = handle(&mut deps, env.clone(), reveal_msg(MOCK_SALT));
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Order can not be revealed yet.")
+        );
+        // == when the reveal delay has passed
+        env.block.height += 1;
+        // === when the order does not match the commitment
+        // === * it raises an error
+        let handle_result = handle(&mut deps, env.clone(), reveal_msg("wrong-salt"));
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Order does not match the commitment.")
+        );
+        // === when the order matches the commitment
+        handle(&mut deps, env.clone(), reveal_msg(MOCK_SALT)).unwrap();
+        // === * it creates the order with the committed amount
+        let contract_order: Order = order_at_position(
             &deps.storage,
             &deps
                 .api
-                .canonical_address(&creator_order.from_token)
+                .canonical_address(&mock_contract().address)
                 .unwrap(),
+            0,
         )
         .unwrap();
+        assert_eq!(contract_order.from_token, mock_butt().address);
+        assert_eq!(contract_order.from_amount, Uint128(MOCK_AMOUNT));
+        assert_eq!(contract_order.to_token, mock_token().address);
+        assert_eq!(contract_order.net_to_amount, Uint128(MOCK_AMOUNT));
         assert_eq!(
-            from_registered_token.sum_balance,
-            creator_order
-                .net_to_amount
-                .multiply_ratio(Uint128(1), Uint128(2))
+            contract_order.creator,
+            deps.api.canonical_address(&mock_user_address()).unwrap()
         );
-        // ===== * it does not update the to tokens sum balance
-        let to_registered_token: RegisteredToken = read_registered_token(
+        assert_eq!(contract_order.created_at_block_height, env.block.height);
+        // === * it closes the commitment
+        assert!(read_commitment(&deps.storage, 0).unwrap().closed);
+        // === * it does not count the committed amount twice in the sum balance
+        assert_eq!(
+            read_registered_token(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_butt().address).unwrap()
+            )
+            .unwrap()
+            .sum_balance,
+            Uint128(MOCK_AMOUNT)
+        );
+
+        // = when the commitment is closed
+        // = * it raises an error
+        let handle_result = handle(&mut deps, env, reveal_msg(MOCK_SALT));
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Commitment already closed.")
+        );
+    }
+
+    #[test]
+    fn test_revoke_permit() {
+        let (_init_result, mut deps) = init_helper(false);
+
+        // = * it revokes the permit for the sender
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::RevokePermit {
+                permit_name: "activity".to_string(),
+            },
+        )
+        .unwrap();
+        assert!(RevokedPermits::is_permit_revoked(
             &deps.storage,
-            &deps.api.canonical_address(&creator_order.to_token).unwrap(),
+            PREFIX_REVOKED_PERMITS,
+            &mock_user_address(),
+            "activity"
+        ));
+        assert!(!RevokedPermits::is_permit_revoked(
+            &deps.storage,
+            PREFIX_REVOKED_PERMITS,
+            &HumanAddr::from(MOCK_ADMIN),
+            "activity"
+        ));
+    }
+
+    #[test]
+    fn test_revoke_role() {
+        let (_init_result, mut deps) = init_helper(false);
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::GrantRole {
+                role: Role::Treasury,
+                address: mock_user_address(),
+            },
         )
         .unwrap();
-        assert_eq!(to_registered_token.sum_balance, Uint128(0));
-        // ===== * it creates an activity record
+        let handle_msg = HandleMsg::RevokeRole {
+            role: Role::Treasury,
+            address: mock_user_address(),
+        };
+
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // = when called by the admin
+        handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg).unwrap();
+        // = * it removes the address from the role
+        assert_eq!(
+            read_role_addresses(&deps.storage, Role::Treasury),
+            Vec::<HumanAddr>::new()
+        );
+    }
+
+    #[test]
+    fn test_role() {
+        let (_init_result, mut deps) = init_helper(false);
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::GrantRole {
+                role: Role::FeeManager,
+                address: mock_user_address(),
+            },
+        )
+        .unwrap();
+
+        // * it returns the addresses with the role
         let res = query(
             &deps,
-            QueryMsg::FillRecords {
-                key: MOCK_VIEWING_KEY.to_string(),
-                page: Uint128(0),
-                page_size: Uint128(50),
+            QueryMsg::Role {
+                role: Role::FeeManager,
             },
         )
         .unwrap();
         let query_answer: QueryAnswer = from_binary(&res).unwrap();
         match query_answer {
-            QueryAnswer::ActivityRecords {
-                activity_records,
-                total,
-            } => {
-                assert_eq!(total, Some(Uint128(1)));
-                assert_eq!(
-                    activity_records[0],
-                    ActivityRecord {
-                        position: Uint128(0),
-                        order_position: contract_order.position,
-                        activity: 1,
-                        result_from_amount_filled: Some(creator_order.from_amount_filled),
-                        result_net_to_amount_filled: Some(creator_order.net_to_amount_filled),
-                        updated_at_block_height: env.block.height.clone(),
-                        updated_at_block_time: env.block.time
-                    }
-                )
+            QueryAnswer::Role { role, addresses } => {
+                assert_eq!(role, Role::FeeManager);
+                assert_eq!(addresses, vec![mock_user_address()]);
             }
             _ => panic!("unexpected"),
         };
-        // ====== when order has an execution fee
-        creator_order.execution_fee = Some(Uint128(1));
-        // ======= when order is partially filled
-        update_creator_order_and_associated_contract_order(
-            &mut deps.storage,
-            &creator_order.creator,
-            creator_order.clone(),
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
-        )
-        .unwrap();
+    }
 
-        // ====== * it does not send the execution fee
-        let handle_msg = HandleMsg::Receive {
-            sender: config.admin.clone(),
-            from: config.admin.clone(),
-            amount: Uint128(MOCK_AMOUNT / 4),
-            msg: Some(to_binary(&receive_msg).unwrap()),
+    #[test]
+    fn test_rescue_tokens() {
+        let (_init_result, mut deps) = init_helper(true);
+        let handle_msg = HandleMsg::RescueTokens {
+            denom: Some("uscrt".to_string()),
+            key: Some(MOCK_VIEWING_KEY.to_string()),
+            token_address: Some(mock_butt().address),
         };
-        let handle_result = handle(&mut deps, mock_env(mock_token().address, &[]), handle_msg);
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
         assert_eq!(
-            handle_result.unwrap().messages,
-            vec![
-                snip20::send_msg(
-                    config.admin.clone(),
-                    Uint128(MOCK_AMOUNT / 4),
-                    None,
-                    None,
-                    BLOCK_SIZE,
-                    mock_butt().contract_hash,
-                    mock_butt().address,
-                )
-                .unwrap(),
-                snip20::transfer_msg(
-                    mock_user_address(),
-                    Uint128(MOCK_AMOUNT / 4),
-                    None,
-                    BLOCK_SIZE,
-                    mock_token().contract_hash,
-                    mock_token().address,
-                )
-                .unwrap(),
-            ]
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
         );
 
-        // ======= when order is completely unfilled
-        creator_order.from_amount_filled = Uint128(0);
-        creator_order.net_to_amount_filled = Uint128(0);
-        creator_order.execution_fee = Some(Uint128(1));
-        update_creator_order_and_associated_contract_order(
+        // = when called by the admin
+        // == when only denom is specified
+        let handle_msg = HandleMsg::RescueTokens {
+            denom: Some("uscrt".to_string()),
+            key: None,
+            token_address: None,
+        };
+        // === when the contract does not have the coin in it
+        // === * it sends a transfer with the balance of the coin for the contract
+        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg.clone());
+        let handle_result_unwrapped = handle_result.unwrap();
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: mock_contract().address,
+                to_address: HumanAddr(MOCK_ADMIN.to_string()),
+                amount: vec![Coin {
+                    denom: "uscrt".to_string(),
+                    amount: Uint128(0)
+                }],
+            })]
+        );
+
+        // == when only token address and key are specified
+        let handle_msg = HandleMsg::RescueTokens {
+            denom: None,
+            key: Some(MOCK_VIEWING_KEY.to_string()),
+            token_address: Some(mock_butt().address),
+        };
+        // == * it sends the excess amount of token
+        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg.clone());
+        let handle_result_unwrapped = handle_result.unwrap();
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                HumanAddr::from(MOCK_ADMIN),
+                Uint128(MOCK_AMOUNT),
+                None,
+                BLOCK_SIZE,
+                mock_butt().contract_hash,
+                mock_butt().address,
+            )
+            .unwrap()]
+        );
+
+        // == when the token has escrowed execution fees
+        write_token_totals(
             &mut deps.storage,
-            &creator_order.creator,
-            creator_order.clone(),
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
+            &mock_butt().address,
+            &TokenTotals {
+                execution_fee_amount: mock_execution_fee(),
+                ..TokenTotals::default()
+            },
         )
         .unwrap();
+        // == * it leaves the execution fees for the fillers
+        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap().messages,
+            vec![snip20::transfer_msg(
+                HumanAddr::from(MOCK_ADMIN),
+                (Uint128(MOCK_AMOUNT) - mock_execution_fee()).unwrap(),
+                None,
+                BLOCK_SIZE,
+                mock_butt().contract_hash,
+                mock_butt().address,
+            )
+            .unwrap()]
+        );
+    }
 
-        // ======== when route state exists
-        let route_state: RouteState = RouteState {
-            current_hop: None,
-            remaining_hops: VecDeque::new(),
-            borrow_amount: Uint128(5),
-            borrow_token: mock_sscrt(),
-            initiator: mock_contract().address,
-            minimum_acceptable_amount: Some(Uint128(5)),
-            send_excess_to: mock_contract().address,
+    #[test]
+    fn test_set_amm_pairs() {
+        let (_init_result, mut deps) = init_helper(true);
+        let amm_pair: AmmPair = AmmPair {
+            contract: mock_sscrt(),
+            token_a: mock_butt().address,
+            token_b: mock_token().address,
         };
-        store_route_state(&mut deps.storage, &route_state).unwrap();
-        // ======== * it sends the execution fee to the route initiator
-        let handle_msg = HandleMsg::Receive {
-            sender: config.admin.clone(),
-            from: config.admin.clone(),
-            amount: Uint128(MOCK_AMOUNT / 8),
-            msg: Some(to_binary(&receive_msg).unwrap()),
+        let handle_msg = HandleMsg::SetAmmPairs {
+            amm_pairs: vec![amm_pair.clone()],
         };
+
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
         let handle_result = handle(
             &mut deps,
-            mock_env(mock_token().address, &[]),
+            mock_env(mock_user_address(), &[]),
             handle_msg.clone(),
         );
         assert_eq!(
-            handle_result.unwrap().messages,
-            vec![
-                snip20::send_msg(
-                    config.admin.clone(),
-                    Uint128(MOCK_AMOUNT / 8),
-                    None,
-                    None,
-                    BLOCK_SIZE,
-                    mock_butt().contract_hash,
-                    mock_butt().address,
-                )
-                .unwrap(),
-                snip20::transfer_msg(
-                    mock_user_address(),
-                    Uint128(MOCK_AMOUNT / 8),
-                    None,
-                    BLOCK_SIZE,
-                    mock_token().contract_hash,
-                    mock_token().address,
-                )
-                .unwrap(),
-                snip20::transfer_msg(
-                    mock_contract().address,
-                    creator_order.execution_fee.unwrap(),
-                    None,
-                    BLOCK_SIZE,
-                    mock_sscrt().contract_hash,
-                    mock_sscrt().address,
-                )
-                .unwrap(),
-            ]
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
         );
 
-        // ======== when route state does not exist
-        creator_order.from_amount_filled = Uint128(0);
-        creator_order.net_to_amount_filled = Uint128(0);
-        creator_order.execution_fee = Some(Uint128(1));
-        update_creator_order_and_associated_contract_order(
-            &mut deps.storage,
-            &creator_order.creator,
-            creator_order.clone(),
-            &deps
-                .api
-                .canonical_address(&mock_contract().address)
-                .unwrap(),
-        )
-        .unwrap();
-
-        delete_route_state(&mut deps.storage);
-        // ======== * it sends the execution fee to the user calling the function
-        let handle_result = handle(&mut deps, mock_env(mock_token().address, &[]), handle_msg);
+        // = when called by the admin
+        // == when a token is not registered
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetAmmPairs {
+                amm_pairs: vec![AmmPair {
+                    token_a: mock_sscrt().address,
+                    ..amm_pair.clone()
+                }],
+            },
+        );
         assert_eq!(
-            handle_result.unwrap().messages,
-            vec![
-                snip20::send_msg(
-                    config.admin.clone(),
-                    Uint128(MOCK_AMOUNT / 8),
-                    None,
-                    None,
-                    BLOCK_SIZE,
-                    mock_butt().contract_hash,
-                    mock_butt().address,
-                )
-                .unwrap(),
-                snip20::transfer_msg(
-                    mock_user_address(),
-                    Uint128(MOCK_AMOUNT / 8),
-                    None,
-                    BLOCK_SIZE,
-                    mock_token().contract_hash,
-                    mock_token().address,
-                )
-                .unwrap(),
-                snip20::transfer_msg(
-                    config.admin,
-                    creator_order.execution_fee.unwrap(),
-                    None,
-                    BLOCK_SIZE,
-                    mock_sscrt().contract_hash,
-                    mock_sscrt().address,
-                )
-                .unwrap(),
-            ]
+            handle_result.unwrap_err(),
+            StdError::generic_err("Token is not registered.")
+        );
+        // == when both tokens are the same
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetAmmPairs {
+                amm_pairs: vec![AmmPair {
+                    token_b: mock_butt().address,
+                    ..amm_pair.clone()
+                }],
+            },
         );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Pair tokens must be different.")
+        );
+        // == when the pairs are valid
+        handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg).unwrap();
+        // == * it stores the pairs
+        assert_eq!(read_amm_pairs(&deps.storage), vec![amm_pair]);
+        // == * it replaces the stored pairs
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetAmmPairs { amm_pairs: vec![] },
+        )
+        .unwrap();
+        assert_eq!(read_amm_pairs(&deps.storage), vec![]);
     }
 
     #[test]
-    fn test_finalize_route() {
-        let (_init_result, mut deps) = init_helper(true);
-        let env = mock_env(mock_user_address(), &[]);
+    fn test_set_candle_intervals() {
+        let (_init_result, mut deps) = init_helper(false);
 
-        // when route state does not exist
-        // * it raises an error
-        let handle_msg = HandleMsg::FinalizeRoute {};
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::SetCandleIntervals {
+                candle_intervals: vec![60],
+            },
+        );
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::generic_err("No route to finalize")
+            StdError::Unauthorized { backtrace: None }
         );
 
-        // when route state exists
-        // = when there are hops
-        let mut hops: VecDeque<Hop> = VecDeque::new();
-        hops.push_back(Hop {
-            from_token: mock_token(),
-            trade_smart_contract: mock_contract(),
-            position: Some(Uint128(2)),
-        });
-        let route_state: RouteState = RouteState {
-            current_hop: Some(Hop {
-                from_token: mock_token(),
-                trade_smart_contract: mock_contract(),
-                position: Some(Uint128(1)),
-            }),
-            remaining_hops: hops,
-            borrow_token: mock_token(),
-            borrow_amount: Uint128(1_000_000),
-            initiator: mock_user_address(),
-            minimum_acceptable_amount: None,
-            send_excess_to: mock_contract().address,
-        };
-        store_route_state(&mut deps.storage, &route_state).unwrap();
-        // == when it isn't called by the contract
+        // = when called by the admin
+        // == when there are too many intervals
         // == * it raises an error
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetCandleIntervals {
+                candle_intervals: vec![60; MAX_CANDLE_INTERVALS + 1],
+            },
+        );
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::Unauthorized { backtrace: None }
+            StdError::generic_err("Too many candle intervals.")
         );
-        // == when it's called by the contract
+        // == when an interval is zero
         // == * it raises an error
         let handle_result = handle(
             &mut deps,
-            mock_env(mock_contract().address, &[]),
-            handle_msg.clone(),
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetCandleIntervals {
+                candle_intervals: vec![60, 0],
+            },
         );
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::generic_err(format!("Cannot finalize: route still contains hops."))
+            StdError::generic_err("Candle interval must be greater than zero.")
         );
+        // == when intervals are valid
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetCandleIntervals {
+                candle_intervals: vec![60, 900],
+            },
+        )
+        .unwrap();
+        // == * it sets the candle intervals
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(config.candle_intervals, vec![60, 900]);
+    }
 
-        // === when there are no hops but there is a current_hop
-        let hops: VecDeque<Hop> = VecDeque::new();
-        let route_state: RouteState = RouteState {
-            current_hop: Some(Hop {
-                from_token: mock_token(),
-                trade_smart_contract: mock_contract(),
-                position: Some(Uint128(1)),
-            }),
-            remaining_hops: hops,
-            borrow_token: mock_token(),
-            borrow_amount: Uint128(1_000_000),
-            initiator: mock_user_address(),
-            minimum_acceptable_amount: None,
-            send_excess_to: mock_contract().address,
+    #[test]
+    fn test_set_filler_address_public() {
+        let (_init_result, mut deps) = init_helper(false);
+
+        // = * it sets whether the sender's filler address is public
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::SetFillerAddressPublic {
+                address_public: true,
+            },
+        )
+        .unwrap();
+        let filler: Filler = read_filler(
+            &deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+        )
+        .unwrap();
+        assert!(filler.address_public);
+        assert_eq!(read_fillers_count(&deps.storage), 1);
+        assert_eq!(
+            read_filler_address(&deps.storage, 0),
+            Some(mock_user_address())
+        );
+    }
+
+    #[test]
+    fn test_set_filler_disabled() {
+        let (_init_result, mut deps) = init_helper(true);
+        let handle_msg = HandleMsg::SetFillerDisabled {
+            address: mock_user_address(),
+            disabled: true,
         };
-        store_route_state(&mut deps.storage, &route_state).unwrap();
-        // === * it raises an error
+
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
         let handle_result = handle(
             &mut deps,
-            mock_env(mock_contract().address, &[]),
+            mock_env(mock_user_address(), &[]),
             handle_msg.clone(),
         );
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::generic_err(format!("Cannot finalize: route still contains hops."))
+            StdError::Unauthorized { backtrace: None }
         );
-        // ==== when there are no hops and no current_hop
-        let hops: VecDeque<Hop> = VecDeque::new();
-        let route_state: RouteState = RouteState {
-            current_hop: None,
-            remaining_hops: hops,
-            borrow_token: mock_token(),
-            borrow_amount: Uint128(1_000_000),
-            initiator: mock_user_address(),
-            minimum_acceptable_amount: None,
-            send_excess_to: mock_contract().address,
-        };
-        store_route_state(&mut deps.storage, &route_state).unwrap();
 
-        // ==== * it returns an Ok response
+        // = when called by the admin
+        handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg).unwrap();
+        // = * it disables the filler
+        assert!(
+            read_filler(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_user_address()).unwrap()
+            )
+            .unwrap()
+            .disabled
+        );
+
+        // = when called by a filler manager
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::GrantRole {
+                role: Role::FillerManager,
+                address: mock_sscrt().address,
+            },
+        )
+        .unwrap();
         handle(
             &mut deps,
-            mock_env(mock_contract().address, &[]),
-            handle_msg.clone(),
+            mock_env(mock_sscrt().address, &[]),
+            HandleMsg::SetFillerDisabled {
+                address: mock_user_address(),
+                disabled: false,
+            },
         )
         .unwrap();
-        // ==== * it deletes the route state
-        assert_eq!(read_route_state(&deps.storage).unwrap().is_none(), true);
+        // = * it enables the filler
+        assert!(
+            !read_filler(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_user_address()).unwrap()
+            )
+            .unwrap()
+            .disabled
+        );
     }
 
     #[test]
-    fn test_handle_first_hop() {
+    fn test_set_pairs() {
         let (_init_result, mut deps) = init_helper(true);
-        let borrow_amount: Uint128 = Uint128(555);
-        let mut hops: VecDeque<Hop> = VecDeque::new();
-        create_order_helper(&mut deps);
-        let first_hop = Hop {
-            from_token: mock_butt(),
-            trade_smart_contract: mock_contract(),
-            position: Some(Uint128(0)),
-        };
-        hops.push_back(first_hop.clone());
-        let handle_msg = HandleMsg::HandleFirstHop {
-            borrow_amount,
-            hops: hops.clone(),
-            minimum_acceptable_amount: Some(borrow_amount),
+        let mut pair: Pair = mock_pair(mock_butt().address, mock_token().address);
+        pair.minimum_from_amount = Uint128(5);
+        let handle_msg = HandleMsg::SetPairs {
+            pairs: vec![pair.clone()],
         };
-        // when called by an address that is not in the addresses allowed to fill
-        // * it raises an Unauthorized error
+
+        // = when called by a non-admin
+        // = * it raises an Unauthorized error
         let handle_result = handle(
             &mut deps,
             mock_env(mock_user_address(), &[]),
@@ -2436,657 +11116,732 @@ mod tests {
             handle_result.unwrap_err(),
             StdError::Unauthorized { backtrace: None }
         );
-        // when called by an address that is allowed to fill
-        // = when hops is less than 2
-        // = * it raises an error
+
+        // = when called by the admin
+        // == when a token is not registered
+        // == * it raises an error
         let handle_result = handle(
             &mut deps,
-            mock_env(HumanAddr::from(MOCK_ADMIN), &[]),
-            handle_msg.clone(),
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetPairs {
+                pairs: vec![mock_pair(mock_sscrt().address, mock_butt().address)],
+            },
         );
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::generic_err("Route must be 2 hops.")
+            StdError::generic_err("Token is not registered.")
         );
-        // = when there are more than 2 hops
-        hops.push_back(Hop {
-            from_token: mock_butt(),
-            trade_smart_contract: mock_contract(),
-            position: None,
-        });
-        hops.push_back(Hop {
-            from_token: mock_butt(),
-            trade_smart_contract: mock_contract(),
-            position: None,
-        });
+        // == when both tokens are the same
+        // == * it raises an error
         let handle_result = handle(
             &mut deps,
-            mock_env(HumanAddr::from(MOCK_ADMIN), &[]),
-            handle_msg.clone(),
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetPairs {
+                pairs: vec![mock_pair(mock_butt().address, mock_butt().address)],
+            },
         );
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::generic_err("Route must be 2 hops.")
-        );
-        // == when there are 2 hops
-        hops.pop_back();
-        let handle_msg = HandleMsg::HandleFirstHop {
-            borrow_amount,
-            hops: hops.clone(),
-            minimum_acceptable_amount: Some(borrow_amount),
-        };
-        let handle_unwrapped = handle(
-            &mut deps,
-            mock_env(HumanAddr::from(MOCK_ADMIN), &[]),
-            handle_msg.clone(),
-        )
-        .unwrap();
-        let route_state: RouteState = read_route_state(&deps.storage).unwrap().unwrap();
-        // == * it stores the current hop
-        assert_eq!(route_state.current_hop.unwrap(), first_hop);
-        // == * it stores the borrow amount
-        assert_eq!(route_state.borrow_amount, borrow_amount);
-        // == * it stores the borrow token as the first hops from_token
-        assert_eq!(route_state.borrow_token, first_hop.from_token);
-        // == * it stores the address to send left over amount after paying back debt
-        assert_eq!(route_state.initiator, HumanAddr::from(MOCK_ADMIN));
-        // == * it stores the remaining hops
-        hops.pop_front();
-        assert_eq!(route_state.remaining_hops, hops);
-        // === when first hop is to limit order smart contract (second isn't)
-        // === * it stores the send_excess_to as the initiator
-        assert_eq!(route_state.send_excess_to, HumanAddr::from(MOCK_ADMIN));
-        // === * it sends the token with the right message to the swap contract
-        // === * it sends a message to finalize the contract
-        assert_eq!(
-            handle_unwrapped.messages,
-            vec![
-                snip20::send_msg(
-                    first_hop.trade_smart_contract.address,
-                    borrow_amount,
-                    Some(
-                        to_binary(&ReceiveMsg::FillOrder {
-                            position: first_hop.position.unwrap(),
-                        })
-                        .unwrap()
-                    ),
-                    None,
-                    BLOCK_SIZE,
-                    first_hop.from_token.contract_hash,
-                    first_hop.from_token.address,
-                )
-                .unwrap(),
-                CosmosMsg::Wasm(WasmMsg::Execute {
-                    contract_addr: mock_contract().address,
-                    callback_code_hash: mock_contract().contract_hash,
-                    msg: to_binary(&HandleMsg::FinalizeRoute {}).unwrap(),
-                    send: vec![],
-                })
-            ]
+            StdError::generic_err("Pair tokens must be different.")
         );
-        // === when first hop is to swap contract
-        let mut hops: VecDeque<Hop> = VecDeque::new();
-        let first_hop = Hop {
-            from_token: mock_butt(),
-            position: None,
-            trade_smart_contract: mock_contract(),
-        };
-        hops.push_back(first_hop.clone());
-        hops.push_back(Hop {
-            from_token: mock_butt(),
-            trade_smart_contract: mock_contract(),
-            position: Some(Uint128(0)),
-        });
-        let handle_msg = HandleMsg::HandleFirstHop {
-            borrow_amount,
-            hops: hops.clone(),
-            minimum_acceptable_amount: Some(borrow_amount),
-        };
-        let handle_unwrapped = handle(
-            &mut deps,
-            mock_env(HumanAddr::from(MOCK_ADMIN), &[]),
-            handle_msg.clone(),
-        )
-        .unwrap();
-        // === * it sends the token with the right message to the swap contract
-        // === * it sends a message to finalize the contract
+        // == when pair already exists
+        handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg).unwrap();
+        // == * it updates the pair
         assert_eq!(
-            handle_unwrapped.messages,
-            vec![
-                snip20::send_msg(
-                    first_hop.trade_smart_contract.address,
-                    borrow_amount,
-                    Some(
-                        to_binary(&Snip20Swap::Swap {
-                            expected_return: None,
-                            to: Some(mock_contract().address),
-                        })
-                        .unwrap()
-                    ),
-                    None,
-                    BLOCK_SIZE,
-                    first_hop.from_token.contract_hash,
-                    first_hop.from_token.address,
-                )
-                .unwrap(),
-                CosmosMsg::Wasm(WasmMsg::Execute {
-                    contract_addr: mock_contract().address,
-                    callback_code_hash: mock_contract().contract_hash,
-                    msg: to_binary(&HandleMsg::FinalizeRoute {}).unwrap(),
-                    send: vec![],
-                })
-            ]
+            read_pair(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_butt().address).unwrap(),
+                &deps.api.canonical_address(&mock_token().address).unwrap()
+            )
+            .unwrap(),
+            pair
         );
-        // === when both hops are for limit orders
-        let receive_msg = ReceiveMsg::CreateOrder {
-            to_amount: Uint128(MOCK_AMOUNT),
-            to_token: mock_token().address,
-        };
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr::from("secretgary"),
-            from: HumanAddr::from("secretgary"),
-            amount: Uint128(MOCK_AMOUNT),
-            msg: Some(to_binary(&receive_msg).unwrap()),
-        };
+        // == * it does not add the pair to the list again
+        assert_eq!(read_pair_token_addresses(&deps.storage).len(), 2);
+
+        // = when called by a token registrar
         handle(
             &mut deps,
-            mock_env(mock_butt().address, &[]),
-            handle_msg.clone(),
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::GrantRole {
+                role: Role::TokenRegistrar,
+                address: mock_user_address(),
+            },
         )
         .unwrap();
-        let mut hops: VecDeque<Hop> = VecDeque::new();
-        hops.push_back(Hop {
-            from_token: mock_butt(),
-            trade_smart_contract: mock_contract(),
-            position: Some(Uint128(0)),
-        });
-        hops.push_back(Hop {
-            from_token: mock_butt(),
-            trade_smart_contract: mock_contract(),
-            position: Some(Uint128(1)),
-        });
-        // === * it sets the send_excess_to on the route as the creator of the newest limit order
-        let handle_msg = HandleMsg::HandleFirstHop {
-            borrow_amount,
-            hops: hops.clone(),
-            minimum_acceptable_amount: Some(borrow_amount),
-        };
         handle(
             &mut deps,
-            mock_env(HumanAddr::from(MOCK_ADMIN), &[]),
-            handle_msg.clone(),
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::RegisterTokens {
+                tokens: vec![mock_sscrt()],
+                viewing_key: MOCK_VIEWING_KEY.to_string(),
+            },
         )
         .unwrap();
-        let route_state: RouteState = read_route_state(&deps.storage).unwrap().unwrap();
-        // === * it stores the send_excess_to as the initiator
-        assert_eq!(route_state.send_excess_to, HumanAddr::from("secretgary"));
-    }
-
-    #[test]
-    fn test_handle_hop() {
-        let (_init_result, mut deps) = init_helper(true);
-        let borrow_amount: Uint128 = Uint128(MOCK_AMOUNT);
-        let borrow_token: SecretContract = mock_butt();
-        let minimum_acceptable_amount: Uint128 = borrow_amount + Uint128(1);
-        create_order_helper(&mut deps);
-        create_order_helper(&mut deps);
-
-        // when route state does not exist
-        // * it raises an error
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_contract().address,
-            from: mock_contract().address,
-            amount: Uint128(MOCK_AMOUNT),
-            msg: None,
-        };
-        let handle_result = handle(
+        handle(
             &mut deps,
-            mock_env(mock_token().address, &[]),
-            handle_msg.clone(),
-        );
-        assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::generic_err("cannot find route")
-        );
-
-        // when route state exists
-        let mut hops: VecDeque<Hop> = VecDeque::new();
-        hops.push_back(Hop {
-            from_token: mock_token(),
-            trade_smart_contract: mock_contract(),
-            position: Some(Uint128(1)),
-        });
-        let route_state: RouteState = RouteState {
-            current_hop: Some(Hop {
-                from_token: mock_butt(),
-                trade_smart_contract: mock_contract(),
-                position: Some(Uint128(2)),
-            }),
-            remaining_hops: hops,
-            borrow_token: borrow_token.clone(),
-            borrow_amount,
-            initiator: mock_user_address(),
-            minimum_acceptable_amount: Some(borrow_amount),
-            send_excess_to: mock_contract().address,
-        };
-        store_route_state(&mut deps.storage, &route_state).unwrap();
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::SetPairs {
+                pairs: vec![mock_pair(mock_sscrt().address, mock_butt().address)],
+            },
+        )
+        .unwrap();
+        // = * it adds the new pair
+        assert_eq!(
+            read_pair_token_addresses(&deps.storage),
+            vec![
+                (mock_butt().address, mock_token().address),
+                (mock_token().address, mock_butt().address),
+                (mock_sscrt().address, mock_butt().address)
+            ]
+        );
+    }
 
-        // = when not called by the current hop's trade smart contract
-        // = * it raises an error
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_butt().address,
-            from: mock_butt().address,
-            amount: Uint128(MOCK_AMOUNT),
-            msg: None,
+    #[test]
+    fn test_set_pause_flags() {
+        let (_init_result, mut deps) = init_helper(true);
+        create_order_helper(&mut deps);
+        let pause_flags = PauseFlags {
+            create_order: false,
+            fill_order: false,
+            route: false,
+            set_execution_fee: false,
+            withdraw_only: true,
+        };
+        let handle_msg = HandleMsg::SetPauseFlags {
+            pause_flags: pause_flags.clone(),
         };
+
+        // = when called by someone who is not the admin or a pauser
+        // = * it raises an Unauthorized error
         let handle_result = handle(
             &mut deps,
-            mock_env(mock_token().address, &[]),
+            mock_env(mock_user_address(), &[]),
             handle_msg.clone(),
         );
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::generic_err("Route called from wrong trade smart contract.")
+            StdError::Unauthorized { backtrace: None }
         );
 
-        // = when from current hop's trade smart contract
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_contract().address,
-            from: mock_contract().address,
-            amount: Uint128(MOCK_AMOUNT),
-            msg: None,
+        // = when called by a pauser
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::GrantRole {
+                role: Role::Pauser,
+                address: mock_user_address(),
+            },
+        )
+        .unwrap();
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        // = * it reports the pause flags in the config
+        let res = query(&deps, QueryMsg::Config {}).unwrap();
+        let config: Config = match from_binary(&res).unwrap() {
+            QueryAnswer::Config { config, .. } => config,
+            _ => panic!("unexpected"),
         };
-        // == when there are hops
-        // === when called by a token different from the next hops from token
-        // === * it raises an error
+        assert_eq!(config.pause_flags, pause_flags);
+
+        // == when withdraw only
+        // == * it does not allow orders to be created
         let handle_result = handle(
             &mut deps,
             mock_env(mock_butt().address, &[]),
-            handle_msg.clone(),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(MOCK_AMOUNT),
+                msg: Some(
+                    to_binary(&ReceiveMsg::CreateOrder {
+                        to_amount: Uint128(MOCK_AMOUNT),
+                        to_token: mock_token().address,
+                    })
+                    .unwrap(),
+                ),
+            },
         );
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::generic_err("Route called by wrong token.")
+            StdError::generic_err("Order creation is paused.")
         );
-        // === when called by the from token of the next hop
+        // == * it does not allow execution fees to be set
         let handle_result = handle(
             &mut deps,
-            mock_env(mock_token().address, &[]),
-            handle_msg.clone(),
+            mock_env(mock_sscrt().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: mock_execution_fee(),
+                msg: Some(to_binary(&ReceiveMsg::SetExecutionFeeForOrder {}).unwrap()),
+            },
         );
-        // === * it stores the route state with the appropriate info
-        let route_state: RouteState = read_route_state(&deps.storage).unwrap().unwrap();
         assert_eq!(
-            route_state,
-            RouteState {
-                current_hop: Some(Hop {
-                    from_token: mock_token(),
-                    trade_smart_contract: mock_contract(),
-                    position: Some(Uint128(1)),
-                }),
-                borrow_token: borrow_token.clone(),
-                remaining_hops: VecDeque::new(),
-                borrow_amount,
-                initiator: mock_user_address(),
-                minimum_acceptable_amount: Some(borrow_amount),
-                send_excess_to: mock_contract().address,
-            }
+            handle_result.unwrap_err(),
+            StdError::generic_err("Setting execution fee is paused.")
         );
-
-        // ==== when the next hop has a position value
-        // ===== when amount received is equal to or less than the next order's unfilled amount
-        // ===== * it sends the amount received to the next hop trade smart contract with the correct details
-        assert_eq!(
-            handle_result.unwrap().messages,
-            vec![snip20::send_msg(
-                mock_contract().address,
-                Uint128(MOCK_AMOUNT),
-                Some(
+        // == * it does not allow orders to be filled
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            HandleMsg::Receive {
+                sender: HumanAddr::from(MOCK_ADMIN),
+                from: HumanAddr::from(MOCK_ADMIN),
+                amount: Uint128(1),
+                msg: Some(
                     to_binary(&ReceiveMsg::FillOrder {
-                        position: Uint128(1)
+                        position: Uint128(0),
                     })
-                    .unwrap()
+                    .unwrap(),
                 ),
-                None,
-                BLOCK_SIZE,
-                mock_token().contract_hash,
-                mock_token().address,
-            )
-            .unwrap()]
+            },
         );
-
-        // ===== when amount received is greater than the next order's unfilled amount
-        let mut hops: VecDeque<Hop> = VecDeque::new();
-        hops.push_back(Hop {
-            from_token: mock_token(),
-            trade_smart_contract: mock_contract(),
-            position: Some(Uint128(1)),
-        });
-        let route_state: RouteState = RouteState {
-            current_hop: Some(Hop {
-                from_token: mock_butt(),
-                trade_smart_contract: mock_contract(),
-                position: Some(Uint128(2)),
-            }),
-            remaining_hops: hops,
-            borrow_token: borrow_token.clone(),
-            borrow_amount,
-            initiator: mock_user_address(),
-            minimum_acceptable_amount: Some(borrow_amount),
-            send_excess_to: mock_contract().address,
-        };
-        store_route_state(&mut deps.storage, &route_state).unwrap();
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_contract().address,
-            from: mock_contract().address,
-            amount: Uint128(MOCK_AMOUNT + 1),
-            msg: None,
-        };
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Filling is paused.")
+        );
+        // == * it does not allow routes to be started
         let handle_result = handle(
             &mut deps,
-            mock_env(mock_token().address, &[]),
-            handle_msg.clone(),
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::HandleFirstHop {
+                borrow_amount: Uint128(1),
+                hops: VecDeque::new(),
+                minimum_acceptable_amount: None,
+            },
         );
-
-        // ===== * it sends the next order's unfilled amount
         assert_eq!(
-            handle_result.unwrap().messages,
-            vec![snip20::send_msg(
-                mock_contract().address,
-                Uint128(MOCK_AMOUNT),
-                Some(
-                    to_binary(&ReceiveMsg::FillOrder {
-                        position: Uint128(1)
-                    })
-                    .unwrap()
-                ),
-                None,
-                BLOCK_SIZE,
-                mock_token().contract_hash,
-                mock_token().address,
-            )
-            .unwrap()]
+            handle_result.unwrap_err(),
+            StdError::generic_err("Routing is paused.")
         );
+        // == * it allows orders to be cancelled
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::CancelOrder {
+                from_token_address: mock_butt().address,
+                position: Uint128(0),
+            },
+        )
+        .unwrap();
 
-        // ==== when the next hop does not have a position value
-        let mut hops: VecDeque<Hop> = VecDeque::new();
-        hops.push_back(Hop {
-            from_token: mock_token(),
-            trade_smart_contract: mock_butt(),
-            position: None,
-        });
-        let route_state: RouteState = RouteState {
-            current_hop: Some(Hop {
-                from_token: mock_butt(),
-                trade_smart_contract: mock_contract(),
-                position: Some(Uint128(2)),
-            }),
-            remaining_hops: hops,
-            borrow_token: borrow_token.clone(),
-            borrow_amount,
-            initiator: mock_user_address(),
-            minimum_acceptable_amount: Some(borrow_amount),
-            send_excess_to: mock_contract().address,
-        };
-        store_route_state(&mut deps.storage, &route_state).unwrap();
-        // ==== * it sends the amount received to the next hop trade smart contract with the correct details
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_contract().address,
-            from: mock_contract().address,
-            amount: Uint128(MOCK_AMOUNT),
-            msg: None,
+        // = when called by the admin
+        // = * it sets the pause flags
+        let pause_flags = PauseFlags {
+            create_order: false,
+            fill_order: true,
+            route: false,
+            set_execution_fee: false,
+            withdraw_only: false,
         };
-        let handle_result = handle(
+        handle(
             &mut deps,
-            mock_env(mock_token().address, &[]),
-            handle_msg.clone(),
-        );
-        assert_eq!(
-            handle_result.unwrap().messages,
-            vec![snip20::send_msg(
-                mock_butt().address,
-                Uint128(MOCK_AMOUNT),
-                Some(
-                    to_binary(&Snip20Swap::Swap {
-                        expected_return: None,
-                        to: Some(mock_contract().address)
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::SetPauseFlags {
+                pause_flags: pause_flags.clone(),
+            },
+        )
+        .unwrap();
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(config.pause_flags, pause_flags);
+        // == * it only pauses what is flagged
+        create_order_helper(&mut deps);
+    }
+
+    #[test]
+    fn test_simulate_fill() {
+        let (_init_result, mut deps) = init_helper(true);
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        let contract_canonical_address: CanonicalAddr = deps
+            .api
+            .canonical_address(&mock_contract().address)
+            .unwrap();
+        let simulate_fill = |deps: &Extern<MockStorage, MockApi, MockQuerier>,
+                             amount: u128|
+         -> StdResult<(Fill, BookOrder)> {
+            let res = query(
+                deps,
+                QueryMsg::SimulateFill {
+                    position: Uint128(0),
+                    amount: Uint128(amount),
+                },
+            )?;
+            match from_binary(&res).unwrap() {
+                QueryAnswer::SimulateFill { fill, order, .. } => Ok((fill, order)),
+                _ => panic!("unexpected"),
+            }
+        };
+        let fill_msg = |amount: u128| -> HandleMsg {
+            HandleMsg::Receive {
+                sender: config.admin.clone(),
+                from: config.admin.clone(),
+                amount: Uint128(amount),
+                msg: Some(
+                    to_binary(&ReceiveMsg::FillOrder {
+                        position: Uint128(0),
                     })
-                    .unwrap()
+                    .unwrap(),
                 ),
-                None,
-                BLOCK_SIZE,
-                mock_token().contract_hash,
-                mock_token().address,
-            )
-            .unwrap()]
-        );
-
-        // == when there are are no hops
-        let hops: VecDeque<Hop> = VecDeque::new();
-        let route_state: RouteState = RouteState {
-            current_hop: Some(Hop {
-                from_token: mock_butt(),
-                trade_smart_contract: mock_contract(),
-                position: Some(Uint128(2)),
-            }),
-            remaining_hops: hops.clone(),
-            borrow_token: borrow_token.clone(),
-            borrow_amount,
-            initiator: mock_user_address(),
-            minimum_acceptable_amount: Some(minimum_acceptable_amount),
-            send_excess_to: mock_contract().address,
+            }
         };
-        store_route_state(&mut deps.storage, &route_state).unwrap();
-        // === when not called by the borrowed token
-        let handle_result = handle(
+
+        // = when the order does not exist
+        // = * it raises an error
+        assert!(simulate_fill(&deps, 1).is_err());
+
+        // = when the order exists
+        create_order_helper(&mut deps);
+        let net_to_amount: u128 = order_at_position(&deps.storage, &contract_canonical_address, 0)
+            .unwrap()
+            .net_to_amount
+            .u128();
+        // == when amount is zero
+        // == * it raises an error
+        assert_eq!(
+            simulate_fill(&deps, 0).unwrap_err(),
+            StdError::generic_err("Amount must be greater than zero.")
+        );
+        // == when amount is greater than the unfilled amount
+        // == * it raises an error
+        assert_eq!(
+            simulate_fill(&deps, net_to_amount + 1).unwrap_err(),
+            StdError::generic_err("Amount is greater than unfilled amount.")
+        );
+        // == when amount is a part of the unfilled amount
+        // == * it returns what the fill would move and the resulting order
+        let (fill, order) = simulate_fill(&deps, net_to_amount / 3).unwrap();
+        handle(
             &mut deps,
             mock_env(mock_token().address, &[]),
-            handle_msg.clone(),
+            fill_msg(net_to_amount / 3),
+        )
+        .unwrap();
+        let contract_order: Order =
+            order_at_position(&deps.storage, &contract_canonical_address, 0).unwrap();
+        assert_eq!(fill.from_amount, contract_order.from_amount_filled);
+        assert_eq!(fill.net_to_amount, Uint128(net_to_amount / 3));
+        assert_eq!(fill.execution_fee, None);
+        assert_eq!(order, contract_order.into_book_order(None).unwrap());
+        // == when amount is the rest of the unfilled amount
+        // == * it releases the rest of the from amount
+        let contract_order: Order =
+            order_at_position(&deps.storage, &contract_canonical_address, 0).unwrap();
+        let (fill, order) = simulate_fill(&deps, net_to_amount - net_to_amount / 3).unwrap();
+        assert_eq!(
+            fill.from_amount,
+            (contract_order.from_amount - contract_order.from_amount_filled).unwrap()
         );
-        // === * it raises an error
+        assert_eq!(order.from_amount_remaining, Uint128(0));
+        assert_eq!(order.net_to_amount_remaining, Uint128(0));
+        // == * it does not change the order
         assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::generic_err("Route called by wrong token.")
+            order_at_position(&deps.storage, &contract_canonical_address, 0).unwrap(),
+            contract_order
         );
-        // === when called by the borrowed token
-        // ==== when amount sent in is less than the minimum acceptable amount
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_contract().address,
-            from: mock_contract().address,
-            amount: borrow_amount,
-            msg: None,
-        };
-        let handle_result = handle(
+        // == when the fill would get none of the from amount
+        // == * it raises an error
+        handle(
             &mut deps,
-            mock_env(borrow_token.address.clone(), &[]),
-            handle_msg.clone(),
+            mock_env(mock_butt().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(1),
+                msg: Some(
+                    to_binary(&ReceiveMsg::CreateOrder {
+                        to_amount: Uint128(MOCK_AMOUNT),
+                        to_token: mock_token().address,
+                    })
+                    .unwrap(),
+                ),
+            },
+        )
+        .unwrap();
+        let query_result = query(
+            &deps,
+            QueryMsg::SimulateFill {
+                position: Uint128(1),
+                amount: Uint128(1),
+            },
         );
-        // ==== * it raises an error
         assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::generic_err("Operation fell short of minimum_acceptable_amount.")
+            query_result.unwrap_err(),
+            StdError::generic_err("Amount is too small to fill.")
         );
+    }
 
-        // ==== when amount sent in is less than the borrowed amount
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_contract().address,
-            from: mock_contract().address,
-            amount: (borrow_amount - Uint128(1)).unwrap(),
-            msg: None,
+    #[test]
+    fn test_simulate_route() {
+        let (_init_result, mut deps) = init_helper(true);
+        create_order_helper(&mut deps);
+        let receive_msg = ReceiveMsg::CreateOrder {
+            to_amount: Uint128(MOCK_AMOUNT / 2),
+            to_token: mock_butt().address,
         };
-        let handle_result = handle(
+        handle(
             &mut deps,
-            mock_env(borrow_token.address.clone(), &[]),
-            handle_msg.clone(),
+            mock_env(mock_token().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(MOCK_AMOUNT),
+                msg: Some(to_binary(&receive_msg).unwrap()),
+            },
+        )
+        .unwrap();
+        let limit_order_hop = |from_token: SecretContract, position: u128| -> Hop {
+            Hop {
+                from_token,
+                trade_smart_contract: mock_contract(),
+                position: Some(Uint128(position)),
+            }
+        };
+        let simulate_route =
+            |borrow_amount: u128,
+             hops: Vec<Hop>,
+             minimum_acceptable_amount: Option<Uint128>|
+             -> (Vec<HopSimulation>, Option<Uint128>, Uint128, Option<String>) {
+                let res = query(
+                    &deps,
+                    QueryMsg::SimulateRoute {
+                        borrow_amount: Uint128(borrow_amount),
+                        hops: hops.into_iter().collect(),
+                        minimum_acceptable_amount,
+                    },
+                )
+                .unwrap();
+                match from_binary(&res).unwrap() {
+                    QueryAnswer::SimulateRoute {
+                        hops,
+                        final_amount,
+                        excess,
+                        revert_reason,
+                        ..
+                    } => (hops, final_amount, excess, revert_reason),
+                    _ => panic!("unexpected"),
+                }
+            };
+
+        // = when the route does not have 2 hops
+        // = * it returns the revert reason
+        let (hops, final_amount, _, revert_reason) =
+            simulate_route(1, vec![limit_order_hop(mock_butt(), 1)], None);
+        assert_eq!(hops, vec![]);
+        assert_eq!(final_amount, None);
+        assert_eq!(revert_reason, Some("Route must be 2 hops.".to_string()));
+
+        // = when the route is through limit orders
+        // == when the route clears the borrow amount
+        // == * it returns the amounts of each hop, the final amount and the excess
+        let contract_canonical_address: CanonicalAddr = deps
+            .api
+            .canonical_address(&mock_contract().address)
+            .unwrap();
+        let route: Vec<Hop> = vec![
+            limit_order_hop(mock_butt(), 1),
+            limit_order_hop(mock_token(), 0),
+        ];
+        let (hops, final_amount, excess, revert_reason) =
+            simulate_route(MOCK_AMOUNT / 4, route.clone(), None);
+        let mut order_one: Order =
+            order_at_position(&deps.storage, &contract_canonical_address, 1).unwrap();
+        let hop_one_amount_out: Uint128 = order_one
+            .fill(Uint128(MOCK_AMOUNT / 4))
+            .unwrap()
+            .from_amount;
+        assert_eq!(
+            hops[0],
+            HopSimulation {
+                amount_in: Uint128(MOCK_AMOUNT / 4),
+                amount_out: hop_one_amount_out,
+                execution_fee: None,
+            }
         );
-        // ==== * it raises an error
+        // == * it only sends the unfilled amount into later limit order hops
+        let order_zero: Order =
+            order_at_position(&deps.storage, &contract_canonical_address, 0).unwrap();
         assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::generic_err("Operation fell short of borrow_amount.")
+            hops[1].amount_in,
+            hop_one_amount_out.min(order_zero.net_to_amount)
         );
-        // ==== when amount sent in is equal to the borrowed amount
-        let hops: VecDeque<Hop> = VecDeque::new();
-        let route_state: RouteState = RouteState {
-            current_hop: Some(Hop {
-                from_token: mock_butt(),
-                trade_smart_contract: mock_contract(),
-                position: Some(Uint128(2)),
-            }),
-            remaining_hops: hops.clone(),
-            borrow_token: borrow_token.clone(),
-            borrow_amount,
-            initiator: mock_user_address(),
-            minimum_acceptable_amount: None,
-            send_excess_to: mock_contract().address,
-        };
-        store_route_state(&mut deps.storage, &route_state).unwrap();
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_contract().address,
-            from: mock_contract().address,
-            amount: borrow_amount,
-            msg: None,
-        };
-        let handle_result = handle(
-            &mut deps,
-            mock_env(borrow_token.address.clone(), &[]),
-            handle_msg.clone(),
+        assert_eq!(final_amount, Some(hops[1].amount_out));
+        assert_eq!(
+            excess,
+            (hops[1].amount_out - Uint128(MOCK_AMOUNT / 4)).unwrap()
         );
-        // ==== * it stores the current hop as None
-        let route_state: RouteState = read_route_state(&deps.storage).unwrap().unwrap();
-        // ==== * it stores the rest of the route state appropriately
+        assert_eq!(revert_reason, None);
+        // == when the route falls short of the minimum acceptable amount
+        // == * it returns the revert reason
+        let (_, final_amount, _, revert_reason) =
+            simulate_route(MOCK_AMOUNT / 4, route, Some(Uint128(MOCK_AMOUNT * 10)));
+        assert!(final_amount.is_some());
         assert_eq!(
-            route_state,
-            RouteState {
-                current_hop: None,
-                borrow_token: borrow_token.clone(),
-                remaining_hops: hops,
-                borrow_amount,
-                initiator: mock_user_address(),
-                minimum_acceptable_amount: None,
-                send_excess_to: mock_contract().address,
-            }
+            revert_reason,
+            Some("Operation fell short of minimum_acceptable_amount.".to_string())
         );
-        // ==== * it does not send any messages
-        assert_eq!(handle_result.unwrap().messages, vec![]);
-        // ==== when amount sent in is greater than the borrowed amount
-        let hops: VecDeque<Hop> = VecDeque::new();
-        let route_state: RouteState = RouteState {
-            current_hop: Some(Hop {
-                from_token: mock_butt(),
-                trade_smart_contract: mock_contract(),
-                position: Some(Uint128(2)),
-            }),
-            remaining_hops: hops.clone(),
-            borrow_token: borrow_token.clone(),
-            borrow_amount,
-            initiator: mock_user_address(),
-            minimum_acceptable_amount: Some(borrow_amount),
-            send_excess_to: mock_contract().address,
-        };
-        store_route_state(&mut deps.storage, &route_state).unwrap();
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_contract().address,
-            from: mock_contract().address,
-            amount: borrow_amount + Uint128(1),
-            msg: None,
-        };
-        let handle_result = handle(
-            &mut deps,
-            mock_env(borrow_token.address.clone(), &[]),
-            handle_msg.clone(),
+        // == when the borrow amount is greater than the first order's unfilled amount
+        // == * it returns the revert reason
+        let (_, _, _, revert_reason) = simulate_route(
+            MOCK_AMOUNT,
+            vec![
+                limit_order_hop(mock_butt(), 1),
+                limit_order_hop(mock_token(), 0),
+            ],
+            None,
         );
-        // ==== * it sends the excess after paying the borrowed amount to the send_excess_to address
         assert_eq!(
-            handle_result.unwrap().messages,
-            vec![snip20::transfer_msg(
-                route_state.send_excess_to,
-                Uint128(1),
-                None,
-                BLOCK_SIZE,
-                borrow_token.contract_hash.clone(),
-                borrow_token.address.clone(),
-            )
-            .unwrap()]
+            revert_reason,
+            Some("Amount is greater than unfilled amount.".to_string())
+        );
+        // == when a hop sends the wrong token to an order
+        // == * it returns the revert reason
+        let (_, _, _, revert_reason) = simulate_route(
+            1,
+            vec![
+                limit_order_hop(mock_butt(), 0),
+                limit_order_hop(mock_token(), 1),
+            ],
+            None,
+        );
+        assert_eq!(
+            revert_reason,
+            Some("To token does not match the token sent in.".to_string())
         );
+
+        // = when a hop is through an AMM pair
+        // = * it queries the pair's simulation
+        let (hops, final_amount, _, revert_reason) = simulate_route(
+            MOCK_AMOUNT / 4,
+            vec![
+                limit_order_hop(mock_butt(), 1),
+                Hop {
+                    from_token: mock_token(),
+                    trade_smart_contract: mock_sscrt(),
+                    position: None,
+                },
+            ],
+            None,
+        );
+        assert_eq!(hops.len(), 1);
+        assert_eq!(final_amount, None);
+        // The mock querier doesn't support wasm queries
+        assert!(revert_reason.is_some());
     }
 
     #[test]
-    fn test_orders_by_positions() {
+    fn test_solvency() {
         let (_init_result, mut deps) = init_helper(true);
+        let query_solvency = |deps: &Extern<MockStorage, MockApi, MockQuerier>| -> TokenSolvency {
+            let res = query(
+                deps,
+                QueryMsg::Solvency {
+                    key: MOCK_VIEWING_KEY.to_string(),
+                    viewing_key: MOCK_VIEWING_KEY.to_string(),
+                },
+            )
+            .unwrap();
+            match from_binary(&res).unwrap() {
+                QueryAnswer::Solvency {
+                    token_solvencies,
+                    tokens,
+                } => {
+                    assert_eq!(token_solvencies.len(), 2);
+                    assert_eq!(tokens[0].address, token_solvencies[0].token);
+                    token_solvencies[0].clone()
+                }
+                _ => panic!("unexpected"),
+            }
+        };
 
-        // when user's address and butt viewing key combo is correct
-        // = when user does not have any orders yet
-        // = * it raises an error
-        let mut res = query(
-            &deps,
-            QueryMsg::OrdersByPositions {
-                address: mock_user_address(),
-                key: MOCK_VIEWING_KEY.to_string(),
-                positions: vec![Uint128(0)],
-            },
-        );
+        // = when nothing is owed
+        // = * it reports the whole balance as surplus
         assert_eq!(
-            res.unwrap_err(),
-            NotFound {
-                kind: "cw_secret_network_limit_orders::state::Order".to_string(),
-                backtrace: None
+            query_solvency(&deps),
+            TokenSolvency {
+                token: mock_butt().address,
+                balance: Uint128(MOCK_AMOUNT),
+                sum_balance: Uint128(0),
+                unfilled_order_amount: Uint128(0),
+                bonded_amount: Uint128(0),
+                committed_amount: Uint128(0),
+                execution_fee_amount: Uint128(0),
+                surplus: Uint128(MOCK_AMOUNT),
+                deficit: Uint128(0),
             }
         );
 
-        // = when user has orders
-        create_order_helper(&mut deps);
-        create_order_helper(&mut deps);
-        create_order_helper(&mut deps);
+        // = when the balance covers the open orders
+        // = * it reports no surplus or deficit
         create_order_helper(&mut deps);
+        let token_solvency: TokenSolvency = query_solvency(&deps);
+        assert_eq!(token_solvency.sum_balance, Uint128(MOCK_AMOUNT));
+        assert_eq!(token_solvency.unfilled_order_amount, Uint128(MOCK_AMOUNT));
+        assert_eq!(token_solvency.surplus, Uint128(0));
+        assert_eq!(token_solvency.deficit, Uint128(0));
+
+        // = when the balance does not cover the open orders and bonds
+        // = * it reports the deficit
+        handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(MOCK_AMOUNT),
+                msg: Some(to_binary(&ReceiveMsg::BondFiller {}).unwrap()),
+            },
+        )
+        .unwrap();
+        let token_solvency: TokenSolvency = query_solvency(&deps);
+        assert_eq!(token_solvency.sum_balance, Uint128(MOCK_AMOUNT * 2));
+        assert_eq!(token_solvency.bonded_amount, Uint128(MOCK_AMOUNT));
+        assert_eq!(token_solvency.surplus, Uint128(0));
+        assert_eq!(token_solvency.deficit, Uint128(MOCK_AMOUNT));
+
+        // = when the order is cancelled
+        // = * it no longer counts the order
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::CancelOrder {
+                from_token_address: mock_butt().address,
+                position: Uint128(0),
+            },
+        )
+        .unwrap();
+        let token_solvency: TokenSolvency = query_solvency(&deps);
+        assert_eq!(token_solvency.unfilled_order_amount, Uint128(0));
+        assert_eq!(token_solvency.sum_balance, Uint128(MOCK_AMOUNT));
+
+        // = when an order is committed to
+        // = * it counts the committed amount
+        commit_order_helper(&mut deps);
+        let token_solvency: TokenSolvency = query_solvency(&deps);
+        assert_eq!(token_solvency.committed_amount, Uint128(MOCK_AMOUNT));
+        assert_eq!(token_solvency.sum_balance, Uint128(MOCK_AMOUNT * 2));
+
+        // = when an order is partially filled
         create_order_helper(&mut deps);
-        // == when position requested is unavailable
-        res = query(
-            &deps,
-            QueryMsg::OrdersByPositions {
-                address: mock_user_address(),
-                key: MOCK_VIEWING_KEY.to_string(),
-                positions: vec![Uint128(1), Uint128(2), Uint128(3), Uint128(5)],
+        handle(
+            &mut deps,
+            mock_env(mock_token().address, &[]),
+            HandleMsg::Receive {
+                sender: MOCK_ADMIN.into(),
+                from: MOCK_ADMIN.into(),
+                amount: Uint128(MOCK_AMOUNT / 4),
+                msg: Some(
+                    to_binary(&ReceiveMsg::FillOrder {
+                        position: Uint128(1),
+                    })
+                    .unwrap(),
+                ),
+            },
+        )
+        .unwrap();
+        // = * it counts the remaining amount of the order
+        let token_solvency: TokenSolvency = query_solvency(&deps);
+        assert_eq!(
+            token_solvency.unfilled_order_amount,
+            Uint128(MOCK_AMOUNT * 3 / 4)
+        );
+    }
+
+    #[test]
+    fn test_unbond_filler() {
+        let (_init_result, mut deps) = init_helper(true);
+        let mut env = mock_env(mock_user_address(), &[]);
+        let handle_msg = HandleMsg::UnbondFiller {
+            amount: Uint128(MOCK_AMOUNT),
+        };
+
+        // = when filler does not exist
+        // = * it raises an error
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Filler does not exist.")
+        );
+
+        // = when filler exists
+        handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(MOCK_AMOUNT),
+                msg: Some(to_binary(&ReceiveMsg::BondFiller {}).unwrap()),
+            },
+        )
+        .unwrap();
+        // == when amount is zero
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::UnbondFiller { amount: Uint128(0) },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::generic_err("Amount must be greater than zero.")
+        );
+        // == when amount is greater than the bonded amount
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::UnbondFiller {
+                amount: Uint128(MOCK_AMOUNT + 1),
             },
         );
         assert_eq!(
-            res.unwrap_err(),
-            NotFound {
-                kind: "cw_secret_network_limit_orders::state::Order".to_string(),
-                backtrace: None
-            }
+            handle_result.unwrap_err(),
+            StdError::generic_err("Amount is greater than bonded amount.")
         );
-        // == when position requested is available
-        res = query(
-            &deps,
-            QueryMsg::OrdersByPositions {
-                address: mock_user_address(),
-                key: MOCK_VIEWING_KEY.to_string(),
-                positions: vec![Uint128(1), Uint128(3), Uint128(4)],
+        // == when amount is less than or equal to the bonded amount
+        handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::UnbondFiller {
+                amount: Uint128(MOCK_AMOUNT / 2),
             },
-        );
-        // == * it returns the humanized orders at those positions
-        let query_answer: QueryAnswer = from_binary(&res.unwrap()).unwrap();
-        match query_answer {
-            QueryAnswer::Orders { orders, total } => {
-                assert_eq!(total, None);
-                assert_eq!(orders[0].creator, mock_user_address());
-                assert_eq!(orders[0].position, Uint128(1));
-                assert_eq!(orders[1].position, Uint128(3));
-                assert_eq!(orders[2].position, Uint128(4));
-            }
-            _ => panic!("unexpected"),
-        };
+        )
+        .unwrap();
+        env.block.height += 1;
+        handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::UnbondFiller {
+                amount: Uint128(MOCK_AMOUNT / 2),
+            },
+        )
+        .unwrap();
+        // == * it moves the amount from bonded to unbonding
+        // == * it restarts the unbonding period
+        let filler: Filler = read_filler(
+            &deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(filler.bonded_amount, Uint128(0));
+        assert_eq!(filler.unbonding_amount, Uint128(MOCK_AMOUNT));
+        assert_eq!(filler.unbonding_available_at_block_height, env.block.height);
     }
 
     #[test]
-    fn test_register_tokens() {
-        let (_init_result, mut deps) = init_helper(false);
-
-        // When tokens are in the parameter
-        let handle_msg = HandleMsg::RegisterTokens {
-            tokens: vec![mock_butt(), mock_token()],
-            viewing_key: MOCK_VIEWING_KEY.to_string(),
+    fn test_update_token() {
+        let (_init_result, mut deps) = init_helper(true);
+        let handle_msg = HandleMsg::UpdateToken {
+            address: mock_token().address,
+            contract_hash: "new-contract-hash".to_string(),
+            decimals: Some(6),
         };
+
         // = when called by a non-admin
         // = * it raises an Unauthorized error
         let handle_result = handle(
@@ -3100,169 +11855,112 @@ mod tests {
         );
 
         // = when called by the admin
-        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg.clone());
-        let handle_result_unwrapped = handle_result.unwrap();
-        // == when tokens are not registered
-        // == * it stores the registered tokens
-        assert_eq!(
-            read_registered_token(
-                &deps.storage,
-                &deps.api.canonical_address(&mock_butt().address).unwrap()
-            )
-            .is_some(),
-            true
-        );
-        assert_eq!(
-            read_registered_token(
-                &deps.storage,
-                &deps.api.canonical_address(&mock_token().address).unwrap()
-            )
-            .is_some(),
-            true
+        // == when token is not registered
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::UpdateToken {
+                address: mock_sscrt().address,
+                contract_hash: "new-contract-hash".to_string(),
+                decimals: None,
+            },
         );
-
-        // == * it registers the contract with the tokens
-        // == * it sets the viewing key for the contract with the tokens
         assert_eq!(
-            handle_result_unwrapped.messages,
-            vec![
-                snip20::register_receive_msg(
-                    mock_contract().contract_hash.clone(),
-                    None,
-                    BLOCK_SIZE,
-                    mock_butt().contract_hash,
-                    mock_butt().address,
-                )
-                .unwrap(),
-                snip20::set_viewing_key_msg(
-                    MOCK_VIEWING_KEY.to_string(),
-                    None,
-                    BLOCK_SIZE,
-                    mock_butt().contract_hash,
-                    mock_butt().address,
-                )
-                .unwrap(),
-                snip20::register_receive_msg(
-                    mock_contract().contract_hash,
-                    None,
-                    BLOCK_SIZE,
-                    mock_token().contract_hash,
-                    mock_token().address,
-                )
-                .unwrap(),
-                snip20::set_viewing_key_msg(
-                    MOCK_VIEWING_KEY.to_string(),
-                    None,
-                    BLOCK_SIZE,
-                    mock_token().contract_hash,
-                    mock_token().address,
-                )
-                .unwrap()
-            ]
+            handle_result.unwrap_err(),
+            StdError::generic_err("Token is not registered.")
         );
-
-        // === context when tokens are registered
-        let mut registered_token: RegisteredToken = read_registered_token(
+        // == when token is registered
+        handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg).unwrap();
+        // == * it updates the contract hash and decimals
+        let registered_token: RegisteredToken = read_registered_token(
             &deps.storage,
             &deps.api.canonical_address(&mock_token().address).unwrap(),
         )
         .unwrap();
-        registered_token.sum_balance = Uint128(MOCK_AMOUNT);
-        write_registered_token(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_token().address).unwrap(),
-            &registered_token,
-        )
-        .unwrap();
-        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg);
-        let handle_result_unwrapped = handle_result.unwrap();
-        // === * it does not change the registered token's sum_balance
-        let registered_token: RegisteredToken = read_registered_token(
-            &deps.storage,
-            &deps.api.canonical_address(&mock_token().address).unwrap(),
+        assert_eq!(
+            registered_token.contract_hash,
+            "new-contract-hash".to_string()
+        );
+        assert_eq!(registered_token.decimals, Some(6));
+        // == when decimals are not given
+        handle(
+            &mut deps,
+            mock_env(MOCK_ADMIN, &[]),
+            HandleMsg::UpdateToken {
+                address: mock_token().address,
+                contract_hash: "new-contract-hash".to_string(),
+                decimals: None,
+            },
         )
         .unwrap();
-        assert_eq!(registered_token.sum_balance, Uint128(MOCK_AMOUNT));
-        // === * it sets the viewing key for the contract with the tokens
+        // == * it keeps the decimals
         assert_eq!(
-            handle_result_unwrapped.messages,
-            vec![
-                snip20::set_viewing_key_msg(
-                    MOCK_VIEWING_KEY.to_string(),
-                    None,
-                    BLOCK_SIZE,
-                    mock_butt().contract_hash,
-                    mock_butt().address,
-                )
-                .unwrap(),
-                snip20::set_viewing_key_msg(
-                    MOCK_VIEWING_KEY.to_string(),
-                    None,
-                    BLOCK_SIZE,
-                    mock_token().contract_hash,
-                    mock_token().address,
-                )
-                .unwrap()
-            ]
+            read_registered_token(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_token().address).unwrap()
+            )
+            .unwrap()
+            .decimals,
+            Some(6)
         );
     }
 
     #[test]
-    fn test_rescue_tokens() {
+    fn test_withdraw_filler_bond() {
         let (_init_result, mut deps) = init_helper(true);
-        let handle_msg = HandleMsg::RescueTokens {
-            denom: Some("uscrt".to_string()),
-            key: Some(MOCK_VIEWING_KEY.to_string()),
-            token_address: Some(mock_butt().address),
-        };
-        // = when called by a non-admin
-        // = * it raises an Unauthorized error
-        let handle_result = handle(
-            &mut deps,
-            mock_env(mock_user_address(), &[]),
-            handle_msg.clone(),
-        );
+        let mut env = mock_env(mock_user_address(), &[]);
+        let handle_msg = HandleMsg::WithdrawFillerBond {};
+
+        // = when filler does not exist
+        // = * it raises an error
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::Unauthorized { backtrace: None }
+            StdError::generic_err("Filler does not exist.")
         );
 
-        // = when called by the admin
-        // == when only denom is specified
-        let handle_msg = HandleMsg::RescueTokens {
-            denom: Some("uscrt".to_string()),
-            key: None,
-            token_address: None,
-        };
-        // === when the contract does not have the coin in it
-        // === * it sends a transfer with the balance of the coin for the contract
-        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg.clone());
-        let handle_result_unwrapped = handle_result.unwrap();
+        // = when filler exists
+        handle(
+            &mut deps,
+            mock_env(mock_butt().address, &[]),
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(MOCK_AMOUNT),
+                msg: Some(to_binary(&ReceiveMsg::BondFiller {}).unwrap()),
+            },
+        )
+        .unwrap();
+        // == when nothing is unbonding
+        // == * it raises an error
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
         assert_eq!(
-            handle_result_unwrapped.messages,
-            vec![CosmosMsg::Bank(BankMsg::Send {
-                from_address: mock_contract().address,
-                to_address: HumanAddr(MOCK_ADMIN.to_string()),
-                amount: vec![Coin {
-                    denom: "uscrt".to_string(),
-                    amount: Uint128(0)
-                }],
-            })]
+            handle_result.unwrap_err(),
+            StdError::generic_err("Nothing to withdraw.")
         );
-
-        // == when only token address and key are specified
-        let handle_msg = HandleMsg::RescueTokens {
-            denom: None,
-            key: Some(MOCK_VIEWING_KEY.to_string()),
-            token_address: Some(mock_butt().address),
-        };
-        // == * it sends the excess amount of token
-        let handle_result = handle(&mut deps, mock_env(MOCK_ADMIN, &[]), handle_msg.clone());
-        let handle_result_unwrapped = handle_result.unwrap();
+        // == when the unbonding period has not passed
+        // == * it raises an error
+        let filler_address_canonical: CanonicalAddr =
+            deps.api.canonical_address(&mock_user_address()).unwrap();
+        let mut filler: Filler = read_filler(&deps.storage, &filler_address_canonical).unwrap();
+        filler.bonded_amount = Uint128(0);
+        filler.unbonding_amount = Uint128(MOCK_AMOUNT);
+        filler.unbonding_available_at_block_height = env.block.height + 1;
+        write_filler(&mut deps.storage, &filler_address_canonical, &filler).unwrap();
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
         assert_eq!(
-            handle_result_unwrapped.messages,
+            handle_result.unwrap_err(),
+            StdError::generic_err("Bond is still unbonding.")
+        );
+        // == when the unbonding period has passed
+        env.block.height += 1;
+        let handle_result = handle(&mut deps, env, handle_msg);
+        // == * it sends the unbonded BUTT to the filler
+        assert_eq!(
+            handle_result.unwrap().messages,
             vec![snip20::transfer_msg(
-                HumanAddr::from(MOCK_ADMIN),
+                mock_user_address(),
                 Uint128(MOCK_AMOUNT),
                 None,
                 BLOCK_SIZE,
@@ -3271,45 +11969,22 @@ mod tests {
             )
             .unwrap()]
         );
-    }
-
-    #[test]
-    fn test_update_config() {
-        let (_init_result, mut deps) = init_helper(false);
-        let new_addresses_allowed_to_fill = vec![mock_user_address()];
-        let handle_msg = HandleMsg::UpdateConfig {
-            addresses_allowed_to_fill: Some(new_addresses_allowed_to_fill.clone()),
-            execution_fee: Some(Uint128(MOCK_AMOUNT)),
-        };
-        let env = mock_env(mock_user_address(), &[]);
-        // = when called by a non-admin
-        // = * it raises an Unauthorized error
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
-        assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::Unauthorized { backtrace: None }
-        );
-
-        // = when called by the admin
-        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        // == * it resets the unbonding amount
         assert_eq!(
-            config.addresses_allowed_to_fill,
-            vec![config.admin, env.contract.address.clone()]
+            read_filler(&deps.storage, &filler_address_canonical)
+                .unwrap()
+                .unbonding_amount,
+            Uint128(0)
         );
-        assert_eq!(config.execution_fee, mock_execution_fee());
-        handle(
-            &mut deps,
-            mock_env(HumanAddr::from(MOCK_ADMIN), &[]),
-            handle_msg,
-        )
-        .unwrap();
-        // = * it updates the addresses_allowed_to_fill and adds admin and contract address
-        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        // == * it decreases the sum balance for BUTT
         assert_eq!(
-            config.addresses_allowed_to_fill,
-            vec![mock_user_address(), env.contract.address, config.admin]
+            read_registered_token(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_butt().address).unwrap()
+            )
+            .unwrap()
+            .sum_balance,
+            Uint128(0)
         );
-        // = * it updates the execution_fee
-        assert_eq!(config.execution_fee, Uint128(MOCK_AMOUNT))
     }
 }
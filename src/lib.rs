@@ -1,6 +1,7 @@
 mod constants;
 pub mod contract;
 pub mod msg;
+mod pagination;
 pub mod state;
 mod validations;
 
@@ -1,35 +1,85 @@
-use crate::state::{ActivityRecord, Hop, HumanizedOrder, SecretContract};
+use crate::state::{
+    ActivityKind, ActivityRecord, AmmPair, BookOrder, Candle, Commitment, Config, Fill, Filler,
+    Hop, HumanizedOrder, OpenFilling, Order, Pair, PairStats, PauseFlags, PendingConfigChange,
+    RegisteredToken, Role, SecretContract, TokenAmount,
+};
 use cosmwasm_std::{Binary, HumanAddr, Uint128};
 use schemars::JsonSchema;
+use secret_toolkit::permit::Permit;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InitMsg {
     pub butt: SecretContract,
+    pub config_change_delay_in_blocks: Option<u64>,
     pub execution_fee: Uint128,
+    pub importing: Option<bool>,
     pub sscrt: SecretContract,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
+    AcceptAdmin {},
+    CancelConfigChange {
+        id: Uint128,
+    },
     CancelOrder {
         from_token_address: HumanAddr,
         position: Uint128,
     },
+    CompleteImport {
+        key: String,
+    },
+    DisableToken {
+        address: HumanAddr,
+    },
+    ExecuteConfigChange {
+        id: Uint128,
+    },
     FinalizeRoute {},
+    ForceCancelOrders {
+        token: HumanAddr,
+        limit: Uint128,
+    },
+    GrantRole {
+        role: Role,
+        address: HumanAddr,
+    },
+    HandOverEscrow {
+        key: String,
+        recipient: HumanAddr,
+    },
     HandleFirstHop {
         borrow_amount: Uint128,
         hops: VecDeque<Hop>,
         minimum_acceptable_amount: Option<Uint128>,
     },
+    ImportState {
+        state_page: StatePage,
+    },
+    ProposeAdmin {
+        address: HumanAddr,
+    },
+    ProposeConfigChange {
+        addresses_allowed_to_fill: Option<Vec<HumanAddr>>,
+        config_change_delay_in_blocks: Option<u64>,
+        execution_fee: Option<Uint128>,
+        open_filling: Option<OpenFilling>,
+    },
     Receive {
         sender: HumanAddr,
         from: HumanAddr,
         amount: Uint128,
         msg: Option<Binary>,
     },
+    ReclaimCommitment {
+        position: Uint128,
+    },
+    RefreshTokenMetadata {
+        address: HumanAddr,
+    },
     RegisterTokens {
         tokens: Vec<SecretContract>,
         viewing_key: String,
@@ -39,56 +89,326 @@ pub enum HandleMsg {
         key: Option<String>,
         token_address: Option<HumanAddr>,
     },
-    UpdateConfig {
-        addresses_allowed_to_fill: Option<Vec<HumanAddr>>,
-        execution_fee: Option<Uint128>,
+    RevealOrder {
+        position: Uint128,
+        to_amount: Uint128,
+        to_token: HumanAddr,
+        salt: String,
+    },
+    RevokePermit {
+        permit_name: String,
+    },
+    RevokeRole {
+        role: Role,
+        address: HumanAddr,
+    },
+    SetFillerAddressPublic {
+        address_public: bool,
+    },
+    SetFillerDisabled {
+        address: HumanAddr,
+        disabled: bool,
+    },
+    SetAmmPairs {
+        amm_pairs: Vec<AmmPair>,
+    },
+    SetCandleIntervals {
+        candle_intervals: Vec<u64>,
+    },
+    SetPairs {
+        pairs: Vec<Pair>,
+    },
+    SetPauseFlags {
+        pause_flags: PauseFlags,
+    },
+    UnbondFiller {
+        amount: Uint128,
+    },
+    UpdateToken {
+        address: HumanAddr,
+        contract_hash: String,
+        decimals: Option<u8>,
+    },
+    WithdrawFillerBond {},
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleAnswer {
+    CommitOrder {
+        position: Uint128,
+    },
+    ForceCancelOrders {
+        cancelled: Uint128,
+        next_position: Uint128,
+        complete: bool,
+    },
+    HandOverEscrow {
+        balances: Vec<TokenAmount>,
+        tokens: Vec<TokenMetadata>,
     },
 }
 
 #[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryAnswer {
+    BestRoute {
+        route: Option<RouteQuote>,
+    },
     ActivityRecords {
         activity_records: Vec<ActivityRecord>,
         total: Option<Uint128>,
+        next_start_after: Option<Uint128>,
+        tokens: Vec<TokenMetadata>,
+    },
+    Candles {
+        candles: Vec<Candle>,
+        total: Option<Uint128>,
+        next_start_after: Option<Uint128>,
+        tokens: Vec<TokenMetadata>,
+    },
+    Commitments {
+        commitments: Vec<Commitment>,
+        total: Option<Uint128>,
+        next_start_after: Option<Uint128>,
+        tokens: Vec<TokenMetadata>,
+    },
+    Config {
+        config: Config,
+        tokens: Vec<TokenMetadata>,
+    },
+    ExportState {
+        state_page: StatePage,
+        total: Uint128,
+        next_start_after: Option<Uint128>,
+        tokens: Vec<TokenMetadata>,
+    },
+    FillerStats {
+        filler: Filler,
+        tokens: Vec<TokenMetadata>,
+    },
+    Fillers {
+        fillers: Vec<Filler>,
+        total: Option<Uint128>,
+        next_start_after: Option<Uint128>,
+        tokens: Vec<TokenMetadata>,
+    },
+    OrderBook {
+        orders: Vec<BookOrder>,
+        total: Option<Uint128>,
+        next_start_after: Option<Uint128>,
+        tokens: Vec<TokenMetadata>,
     },
     Orders {
         orders: Vec<HumanizedOrder>,
         total: Option<Uint128>,
+        next_start_after: Option<Uint128>,
+        tokens: Vec<TokenMetadata>,
+    },
+    PairStats {
+        pair_stats: PairStats,
+        tokens: Vec<TokenMetadata>,
+    },
+    Pairs {
+        pairs: Vec<Pair>,
+        total: Option<Uint128>,
+        next_start_after: Option<Uint128>,
+        tokens: Vec<TokenMetadata>,
+    },
+    PendingConfigChanges {
+        pending_config_changes: Vec<PendingConfigChange>,
+        total: Option<Uint128>,
+        next_start_after: Option<Uint128>,
+    },
+    RegisteredTokens {
+        registered_tokens: Vec<RegisteredToken>,
+        total: Option<Uint128>,
+        next_start_after: Option<Uint128>,
+    },
+    Role {
+        role: Role,
+        addresses: Vec<HumanAddr>,
+    },
+    SimulateFill {
+        fill: Fill,
+        order: BookOrder,
+        tokens: Vec<TokenMetadata>,
+    },
+    SimulateRoute {
+        hops: Vec<HopSimulation>,
+        final_amount: Option<Uint128>,
+        excess: Uint128,
+        revert_reason: Option<String>,
+        tokens: Vec<TokenMetadata>,
+    },
+    Solvency {
+        token_solvencies: Vec<TokenSolvency>,
+        tokens: Vec<TokenMetadata>,
     },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
+    BestRoute {
+        token: HumanAddr,
+        borrow_amount: Uint128,
+        max_hops: Option<u8>,
+    },
     CancelRecords {
         key: String,
-        page: Uint128,
-        page_size: Uint128,
+        filters: Option<ActivityFilters>,
+        pagination: Option<Pagination>,
+    },
+    Candles {
+        pair: TokenPair,
+        interval: u64,
+        from_time: u64,
+        to_time: u64,
+        pagination: Option<Pagination>,
+    },
+    Commitments {
+        address: HumanAddr,
+        key: String,
+        pagination: Option<Pagination>,
     },
     FillRecords {
         key: String,
-        page: Uint128,
-        page_size: Uint128,
+        filters: Option<ActivityFilters>,
+        pagination: Option<Pagination>,
     },
     Config {},
+    ExportState {
+        key: String,
+        section: StateSection,
+        pagination: Option<Pagination>,
+    },
+    FillerStats {
+        address: HumanAddr,
+    },
+    Fillers {
+        pagination: Option<Pagination>,
+    },
+    MyActivity {
+        address: HumanAddr,
+        key: String,
+        filters: ActivityFilters,
+        pagination: Option<Pagination>,
+    },
     Orders {
         address: HumanAddr,
         key: String,
-        page: Uint128,
-        page_size: Uint128,
+        pagination: Option<Pagination>,
+    },
+    OrderBook {
+        pair: Option<TokenPair>,
+        significant_figures: Option<u8>,
+        pagination: Option<Pagination>,
     },
     OrdersByPositions {
         address: HumanAddr,
         key: String,
         positions: Vec<Uint128>,
     },
+    PairStats {
+        from_token: HumanAddr,
+        to_token: HumanAddr,
+    },
+    Pairs {
+        pagination: Option<Pagination>,
+    },
+    PendingConfigChanges {
+        pagination: Option<Pagination>,
+    },
+    RegisteredTokens {
+        pagination: Option<Pagination>,
+    },
+    Role {
+        role: Role,
+    },
+    SimulateFill {
+        position: Uint128,
+        amount: Uint128,
+    },
+    SimulateRoute {
+        borrow_amount: Uint128,
+        hops: VecDeque<Hop>,
+        minimum_acceptable_amount: Option<Uint128>,
+    },
+    Solvency {
+        key: String,
+        viewing_key: String,
+    },
+    WithPermit {
+        permit: Permit,
+        query: QueryWithPermit,
+    },
+}
+impl QueryMsg {
+    pub fn pagination(&self) -> Option<&Pagination> {
+        match self {
+            QueryMsg::CancelRecords { pagination, .. }
+            | QueryMsg::Candles { pagination, .. }
+            | QueryMsg::Commitments { pagination, .. }
+            | QueryMsg::FillRecords { pagination, .. }
+            | QueryMsg::ExportState { pagination, .. }
+            | QueryMsg::Fillers { pagination }
+            | QueryMsg::MyActivity { pagination, .. }
+            | QueryMsg::Orders { pagination, .. }
+            | QueryMsg::OrderBook { pagination, .. }
+            | QueryMsg::Pairs { pagination }
+            | QueryMsg::PendingConfigChanges { pagination }
+            | QueryMsg::RegisteredTokens { pagination }
+            | QueryMsg::WithPermit {
+                query: QueryWithPermit::MyActivity { pagination, .. },
+                ..
+            } => pagination.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+// start_after is the position of the last item of the previous page.
+// Answers are always padded to a multiple of BLOCK_SIZE. With bucketed they are padded to a power
+// of two number of blocks, so that their length says even less about the number of items.
+// Errors aren't padded and queries without pagination can't be bucketed.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct Pagination {
+    pub start_after: Option<Uint128>,
+    pub limit: Option<Uint128>,
+    pub order: Option<SortOrder>,
+    pub bucketed: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryWithPermit {
+    MyActivity {
+        filters: ActivityFilters,
+        pagination: Option<Pagination>,
+    },
+}
+
+// Block height and time ranges are inclusive
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct ActivityFilters {
+    pub activity: Option<ActivityKind>,
+    pub order_position: Option<Uint128>,
+    pub pair: Option<TokenPair>,
+    pub from_block_height: Option<u64>,
+    pub to_block_height: Option<u64>,
+    pub from_block_time: Option<u64>,
+    pub to_block_time: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ReceiveMsg {
+    BondFiller {},
     SetExecutionFeeForOrder {},
+    CommitOrder {
+        hash: Binary,
+    },
     CreateOrder {
         to_amount: Uint128,
         to_token: HumanAddr,
@@ -98,6 +418,96 @@ pub enum ReceiveMsg {
     },
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StateSection {
+    Activity { address: HumanAddr },
+    CancelRecords,
+    Candles { pair: TokenPair, interval: u64 },
+    Commitments,
+    FillRecords,
+    Fillers,
+    Orders,
+    PairStats,
+    RegisteredTokens,
+}
+
+// Orders are the contract's copies, the creator copies are rebuilt on import.
+// Activity is a user's activity records, the admin's are under cancel and fill records.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StatePage {
+    Activity {
+        address: HumanAddr,
+        activity_records: Vec<ActivityRecord>,
+    },
+    CancelRecords {
+        cancel_records: Vec<ActivityRecord>,
+    },
+    Candles {
+        pair: TokenPair,
+        interval: u64,
+        candles: Vec<Candle>,
+    },
+    Commitments {
+        commitments: Vec<Commitment>,
+    },
+    FillRecords {
+        fill_records: Vec<ActivityRecord>,
+    },
+    Fillers {
+        fillers: Vec<Filler>,
+    },
+    Orders {
+        orders: Vec<Order>,
+    },
+    PairStats {
+        pair_stats: Vec<PairStats>,
+    },
+    RegisteredTokens {
+        registered_tokens: Vec<RegisteredToken>,
+    },
+}
+
+// The balance must cover the sum balance and the escrowed execution fees, which are not part of
+// it. The sum balance should equal the unfilled amounts of open orders plus filler bonds plus the
+// amounts of open commitments.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenSolvency {
+    pub token: HumanAddr,
+    pub balance: Uint128,
+    pub sum_balance: Uint128,
+    pub unfilled_order_amount: Uint128,
+    pub bonded_amount: Uint128,
+    pub committed_amount: Uint128,
+    pub execution_fee_amount: Uint128,
+    pub surplus: Uint128,
+    pub deficit: Uint128,
+}
+
+// What is cached of a registered token's token_info. Answers that mention tokens include the
+// metadata of each token once, in the order they first appear.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenMetadata {
+    pub address: HumanAddr,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenPair {
+    pub from_token: HumanAddr,
+    pub to_token: HumanAddr,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Snip20Swap {
@@ -106,3 +516,51 @@ pub enum Snip20Swap {
         to: Option<HumanAddr>,
     },
 }
+
+// handle_msg is the HandleFirstHop to send, without a minimum acceptable amount
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RouteQuote {
+    pub hops: Vec<HopSimulation>,
+    pub final_amount: Uint128,
+    pub excess: Uint128,
+    pub excess_to_filler: Uint128,
+    pub handle_msg: HandleMsg,
+    pub tokens: Vec<TokenMetadata>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HopSimulation {
+    pub amount_in: Uint128,
+    pub amount_out: Uint128,
+    pub execution_fee: Option<Uint128>,
+}
+
+// Simulation query of SecretSwap style AMM pairs
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AmmPairQueryMsg {
+    Simulation { offer_asset: AmmAsset },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AmmAsset {
+    pub info: AmmAssetInfo,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AmmAssetInfo {
+    Token {
+        contract_addr: HumanAddr,
+        token_code_hash: String,
+        viewing_key: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AmmSimulationResponse {
+    pub return_amount: Uint128,
+    pub spread_amount: Uint128,
+    pub commission_amount: Uint128,
+}
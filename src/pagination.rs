@@ -0,0 +1,67 @@
+use crate::constants::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::msg::{Pagination, SortOrder};
+
+// Lists are stored by position, so the cursor is the position of the last item of the previous
+// page. Positions outside of the list give an empty page rather than an error.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Page {
+    pub start_after: Option<u128>,
+    pub limit: u128,
+    pub order: SortOrder,
+}
+
+impl Page {
+    pub fn new(pagination: Option<Pagination>, default_order: SortOrder) -> Self {
+        let pagination: Pagination = pagination.unwrap_or_default();
+        Self {
+            start_after: pagination.start_after.map(|start_after| start_after.u128()),
+            limit: pagination
+                .limit
+                .map_or(DEFAULT_PAGE_SIZE, |limit| limit.u128())
+                .min(MAX_PAGE_SIZE),
+            order: pagination.order.unwrap_or(default_order),
+        }
+    }
+
+    pub fn is_after_cursor(&self, position: u128) -> bool {
+        match (self.start_after, self.order) {
+            (None, _) => true,
+            (Some(start_after), SortOrder::Ascending) => position > start_after,
+            (Some(start_after), SortOrder::Descending) => position < start_after,
+        }
+    }
+
+    // Positions of the page within start up to end and the cursor for the next page, if any
+    pub fn positions(&self, start: u128, end: u128) -> (Vec<u128>, Option<u128>) {
+        let (low, high): (u128, u128) = match self.order {
+            SortOrder::Ascending => {
+                let low: u128 = match self.start_after {
+                    Some(start_after) => start.max(start_after.saturating_add(1)),
+                    None => start,
+                };
+                (low, end.min(low.saturating_add(self.limit)))
+            }
+            SortOrder::Descending => {
+                let high: u128 = match self.start_after {
+                    Some(start_after) => end.min(start_after),
+                    None => end,
+                };
+                (start.max(high.saturating_sub(self.limit)), high)
+            }
+        };
+        if low >= high {
+            return (vec![], None);
+        }
+
+        let (positions, has_more): (Vec<u128>, bool) = match self.order {
+            SortOrder::Ascending => ((low..high).collect(), high < end),
+            SortOrder::Descending => ((low..high).rev().collect(), low > start),
+        };
+        let next_start_after: Option<u128> = if has_more {
+            positions.last().copied()
+        } else {
+            None
+        };
+        (positions, next_start_after)
+    }
+}
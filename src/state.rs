@@ -1,45 +1,767 @@
-use crate::constants::{PREFIX_REGISTERED_TOKENS, ROUTE_STATE_KEY};
-use cosmwasm_std::{Api, CanonicalAddr, HumanAddr, StdResult, Storage, Uint128};
+use crate::constants::{
+    AMM_PAIRS_KEY, COMMITMENTS_COUNT_KEY, CONFIG_CHANGES_COUNT_KEY, CONTRACT_ADDRESS_KEY,
+    CONTRACT_CODE_HASH_KEY, FILLERS_COUNT_KEY, IMPORT_COMPLETE_KEY, PAIR_TOKEN_ADDRESSES_KEY,
+    PREFIX_CANDLES, PREFIX_CANDLES_COUNT, PREFIX_COMMITMENTS, PREFIX_FILLERS,
+    PREFIX_FILLERS_ALLOWED, PREFIX_FILLER_ADDRESSES, PREFIX_INDEXES, PREFIX_OPEN_COMMITMENTS,
+    PREFIX_OPEN_ORDERS, PREFIX_PAIRS, PREFIX_PAIR_STATS, PREFIX_PENDING_CONFIG_CHANGES,
+    PREFIX_REGISTERED_TOKENS, PREFIX_ROLES, PREFIX_TOKEN_TOTALS, PRICE_SCALE,
+    REGISTERED_TOKEN_ADDRESSES_KEY, ROLLING_VOLUME_BUCKET_IN_SECONDS,
+    ROLLING_VOLUME_PERIOD_IN_SECONDS, ROUTE_STATE_KEY,
+};
+use crate::msg::SortOrder;
+use crate::pagination::Page;
+use cosmwasm_std::{
+    Api, Binary, CanonicalAddr, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage, Uint128,
+};
 use cosmwasm_storage::{singleton, singleton_read, PrefixedStorage, ReadonlyPrefixedStorage};
+use primitive_types::U256;
 use schemars::JsonSchema;
+use secret_toolkit::crypto::sha_256;
 use secret_toolkit::storage::{TypedStore, TypedStoreMut};
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
-// For tracking cancelled and filled
-// activity (0 => cancelled, 1 => filled)
+// For tracking order activity
+// admin_initiated is set when the admin force cancels an order
+// Records are kept for the admin and for the order creator.
+// order_position is the position of the order in the list of whoever the record is kept for.
+// The filler is only included in the creator's records when the filler has made its address public.
+// Fills record the amounts filled by that fill, the price (calculated the same way as in PairStats),
+// the share of the order's fee, the execution fee paid to the filler and whether it was routed
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, JsonSchema)]
 pub struct ActivityRecord {
     pub order_position: Uint128,
     pub position: Uint128,
-    pub activity: u8,
+    pub activity: ActivityKind,
+    pub from_token: HumanAddr,
+    pub to_token: HumanAddr,
+    pub admin_initiated: bool,
     pub result_from_amount_filled: Option<Uint128>,
     pub result_net_to_amount_filled: Option<Uint128>,
+    pub from_amount_filled_delta: Option<Uint128>,
+    pub net_to_amount_filled_delta: Option<Uint128>,
+    pub price: Option<Uint128>,
+    pub fee: Option<Uint128>,
+    pub execution_fee: Option<Uint128>,
+    pub execution_fee_paid: Option<Uint128>,
+    pub filler: Option<HumanAddr>,
+    pub routed: bool,
+    pub refund_amount: Option<Uint128>,
+    pub execution_fee_refund_amount: Option<Uint128>,
     pub updated_at_block_height: u64,
     pub updated_at_block_time: u64,
 }
+impl ActivityRecord {
+    pub fn new(
+        activity: ActivityKind,
+        order_position: Uint128,
+        from_token: HumanAddr,
+        to_token: HumanAddr,
+        updated_at_block_height: u64,
+        updated_at_block_time: u64,
+    ) -> Self {
+        Self {
+            order_position,
+            position: Uint128(0),
+            activity,
+            from_token,
+            to_token,
+            admin_initiated: false,
+            result_from_amount_filled: None,
+            result_net_to_amount_filled: None,
+            from_amount_filled_delta: None,
+            net_to_amount_filled_delta: None,
+            price: None,
+            fee: None,
+            execution_fee: None,
+            execution_fee_paid: None,
+            filler: None,
+            routed: false,
+            refund_amount: None,
+            execution_fee_refund_amount: None,
+            updated_at_block_height,
+            updated_at_block_time,
+        }
+    }
+}
+
+// Amended and Expired are reserved for order amendments and expiry
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Amended,
+    Cancelled,
+    Created,
+    Expired,
+    FeeSet,
+    Filled,
+}
+
+// === AMM pairs ===
+// Pairs that routes can be found through, alongside the contract's own orders
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AmmPair {
+    pub contract: SecretContract,
+    pub token_a: HumanAddr,
+    pub token_b: HumanAddr,
+}
+
+pub fn read_amm_pairs<S: Storage>(storage: &S) -> Vec<AmmPair> {
+    TypedStore::attach(storage)
+        .may_load(AMM_PAIRS_KEY)
+        .unwrap()
+        .unwrap_or_default()
+}
+
+pub fn write_amm_pairs<S: Storage>(storage: &mut S, amm_pairs: &[AmmPair]) -> StdResult<()> {
+    TypedStoreMut::attach(storage).store(AMM_PAIRS_KEY, &amm_pairs.to_vec())
+}
+
+// === Candles ===
+// Fills are aggregated into candles for each of the config's candle intervals (in seconds).
+// start_time is the block time rounded down to a multiple of the interval.
+// Prices are calculated the same way as in PairStats.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Candle {
+    pub start_time: u64,
+    pub open: Uint128,
+    pub high: Uint128,
+    pub low: Uint128,
+    pub close: Uint128,
+    pub from_volume: Uint128,
+    pub to_volume: Uint128,
+}
+
+pub fn read_candle<S: Storage>(
+    storage: &S,
+    from_token_address: &CanonicalAddr,
+    to_token_address: &CanonicalAddr,
+    interval: u64,
+    position: u64,
+) -> Option<Candle> {
+    let candles_storage = ReadonlyPrefixedStorage::multilevel(
+        &[
+            PREFIX_CANDLES,
+            from_token_address.as_slice(),
+            to_token_address.as_slice(),
+            &interval.to_le_bytes(),
+        ],
+        storage,
+    );
+    let candles_storage = TypedStore::attach(&candles_storage);
+    candles_storage.may_load(&position.to_le_bytes()).unwrap()
+}
+
+pub fn read_candles_count<S: Storage>(
+    storage: &S,
+    from_token_address: &CanonicalAddr,
+    to_token_address: &CanonicalAddr,
+    interval: u64,
+) -> u64 {
+    let candles_count_storage = ReadonlyPrefixedStorage::multilevel(
+        &[
+            PREFIX_CANDLES_COUNT,
+            from_token_address.as_slice(),
+            to_token_address.as_slice(),
+        ],
+        storage,
+    );
+    let candles_count_storage = TypedStore::attach(&candles_count_storage);
+    candles_count_storage
+        .may_load(&interval.to_le_bytes())
+        .unwrap()
+        .unwrap_or(0)
+}
+
+pub fn write_candle<S: Storage>(
+    storage: &mut S,
+    from_token_address: &CanonicalAddr,
+    to_token_address: &CanonicalAddr,
+    interval: u64,
+    position: u64,
+    candle: &Candle,
+) -> StdResult<()> {
+    let mut candles_storage = PrefixedStorage::multilevel(
+        &[
+            PREFIX_CANDLES,
+            from_token_address.as_slice(),
+            to_token_address.as_slice(),
+            &interval.to_le_bytes(),
+        ],
+        storage,
+    );
+    let mut candles_storage = TypedStoreMut::attach(&mut candles_storage);
+    candles_storage.store(&position.to_le_bytes(), candle)
+}
+
+pub fn write_candles_count<S: Storage>(
+    storage: &mut S,
+    from_token_address: &CanonicalAddr,
+    to_token_address: &CanonicalAddr,
+    interval: u64,
+    count: u64,
+) -> StdResult<()> {
+    let mut candles_count_storage = PrefixedStorage::multilevel(
+        &[
+            PREFIX_CANDLES_COUNT,
+            from_token_address.as_slice(),
+            to_token_address.as_slice(),
+        ],
+        storage,
+    );
+    let mut candles_count_storage = TypedStoreMut::attach(&mut candles_count_storage);
+    candles_count_storage.store(&interval.to_le_bytes(), &count)
+}
+
+// === Commitments ===
+// Funds escrowed for an order whose to token and to amount are only revealed after
+// REVEAL_DELAY_IN_BLOCKS. A commitment is closed once it has been revealed or reclaimed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Commitment {
+    pub position: Uint128,
+    pub creator: CanonicalAddr,
+    pub from_token: HumanAddr,
+    pub from_amount: Uint128,
+    pub hash: Binary,
+    pub committed_at_block_height: u64,
+    pub closed: bool,
+}
+
+// The sha256 of "<to_token>:<to_amount>:<salt>" that the creator commits to
+pub fn commitment_hash(to_token: &HumanAddr, to_amount: Uint128, salt: &str) -> Binary {
+    Binary::from(&sha_256(format!("{}:{}:{}", to_token, to_amount, salt).as_bytes())[..])
+}
+
+pub fn read_commitment<S: Storage>(storage: &S, position: u128) -> Option<Commitment> {
+    let commitments_storage = ReadonlyPrefixedStorage::new(PREFIX_COMMITMENTS, storage);
+    let commitments_storage = TypedStore::attach(&commitments_storage);
+    commitments_storage
+        .may_load(&position.to_le_bytes())
+        .unwrap()
+}
+
+// Open commitments are indexed per creator by their position
+pub fn insert_open_commitment<S: Storage>(
+    storage: &mut S,
+    commitment: &Commitment,
+) -> StdResult<()> {
+    index_insert(
+        storage,
+        &[PREFIX_OPEN_COMMITMENTS, commitment.creator.as_slice()],
+        commitment.position.u128(),
+    )
+}
+
+pub fn read_open_commitment_positions<S: Storage>(
+    storage: &S,
+    creator: &CanonicalAddr,
+    page: &Page,
+) -> (Vec<u128>, Option<u128>) {
+    index_page(
+        storage,
+        &[PREFIX_OPEN_COMMITMENTS, creator.as_slice()],
+        page,
+    )
+}
+
+pub fn read_open_commitments_count<S: Storage>(storage: &S, creator: &CanonicalAddr) -> u128 {
+    index_len(storage, &[PREFIX_OPEN_COMMITMENTS, creator.as_slice()])
+}
+
+pub fn remove_open_commitment<S: Storage>(
+    storage: &mut S,
+    commitment: &Commitment,
+) -> StdResult<()> {
+    index_remove(
+        storage,
+        &[PREFIX_OPEN_COMMITMENTS, commitment.creator.as_slice()],
+        commitment.position.u128(),
+    )
+}
+
+pub fn read_commitments_count<S: Storage>(storage: &S) -> u128 {
+    TypedStore::attach(storage)
+        .may_load(COMMITMENTS_COUNT_KEY)
+        .unwrap()
+        .unwrap_or(0)
+}
+
+pub fn write_commitment<S: Storage>(storage: &mut S, commitment: &Commitment) -> StdResult<()> {
+    let mut commitments_storage = PrefixedStorage::new(PREFIX_COMMITMENTS, storage);
+    let mut commitments_storage = TypedStoreMut::attach(&mut commitments_storage);
+    commitments_storage.store(&commitment.position.u128().to_le_bytes(), commitment)
+}
+
+pub fn write_commitments_count<S: Storage>(storage: &mut S, count: u128) -> StdResult<()> {
+    TypedStoreMut::attach(storage).store(COMMITMENTS_COUNT_KEY, &count)
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
     pub admin: HumanAddr,
     pub addresses_allowed_to_fill: Vec<HumanAddr>,
     pub butt: SecretContract,
+    pub candle_intervals: Vec<u64>,
+    pub config_change_delay_in_blocks: u64,
     pub execution_fee: Uint128,
+    pub open_filling: OpenFilling,
+    pub pause_flags: PauseFlags,
+    pub pending_admin: Option<HumanAddr>,
     pub sscrt: SecretContract,
 }
 
+// When enabled, anyone with at least bond_amount BUTT bonded can fill.
+// Unbonded BUTT can be withdrawn after unbonding_period_in_blocks
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct OpenFilling {
+    pub enabled: bool,
+    pub bond_amount: Uint128,
+    pub unbonding_period_in_blocks: u64,
+}
+
+// Withdraw only pauses everything except cancelling orders
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct PauseFlags {
+    pub create_order: bool,
+    pub fill_order: bool,
+    pub route: bool,
+    pub set_execution_fee: bool,
+    pub withdraw_only: bool,
+}
+impl PauseFlags {
+    pub fn create_order_paused(&self) -> bool {
+        self.create_order || self.withdraw_only
+    }
+
+    pub fn fill_order_paused(&self) -> bool {
+        self.fill_order || self.withdraw_only
+    }
+
+    pub fn route_paused(&self) -> bool {
+        self.route || self.withdraw_only
+    }
+
+    pub fn set_execution_fee_paused(&self) -> bool {
+        self.set_execution_fee || self.withdraw_only
+    }
+}
+
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, JsonSchema)]
 pub struct SecretContract {
     pub address: HumanAddr,
     pub contract_hash: String,
 }
 
+// Queries don't have access to env, so the contract's own address is stored at init
+pub fn read_contract_address<S: Storage>(storage: &S) -> StdResult<HumanAddr> {
+    TypedStore::attach(storage).load(CONTRACT_ADDRESS_KEY)
+}
+
+pub fn read_contract_code_hash<S: Storage>(storage: &S) -> StdResult<String> {
+    TypedStore::attach(storage).load(CONTRACT_CODE_HASH_KEY)
+}
+
+pub fn write_contract_address<S: Storage>(
+    storage: &mut S,
+    contract_address: &HumanAddr,
+) -> StdResult<()> {
+    TypedStoreMut::attach(storage).store(CONTRACT_ADDRESS_KEY, contract_address)
+}
+
+pub fn write_contract_code_hash<S: Storage>(
+    storage: &mut S,
+    contract_code_hash: &str,
+) -> StdResult<()> {
+    TypedStoreMut::attach(storage).store(CONTRACT_CODE_HASH_KEY, &contract_code_hash.to_string())
+}
+
+// === Config changes ===
+// Sensitive config changes are queued and can only be executed after a delay
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingConfigChange {
+    pub id: Uint128,
+    pub addresses_allowed_to_fill: Option<Vec<HumanAddr>>,
+    pub execution_fee: Option<Uint128>,
+    pub open_filling: Option<OpenFilling>,
+    pub config_change_delay_in_blocks: Option<u64>,
+    pub proposed_at_block_height: u64,
+    pub executable_at_block_height: u64,
+}
+
+pub fn read_config_changes_count<S: Storage>(storage: &S) -> u128 {
+    TypedStore::attach(storage)
+        .may_load(CONFIG_CHANGES_COUNT_KEY)
+        .unwrap()
+        .unwrap_or(0)
+}
+
+pub fn read_pending_config_change<S: Storage>(
+    storage: &S,
+    id: u128,
+) -> Option<PendingConfigChange> {
+    let pending_config_changes_storage =
+        ReadonlyPrefixedStorage::new(PREFIX_PENDING_CONFIG_CHANGES, storage);
+    let pending_config_changes_storage = TypedStore::attach(&pending_config_changes_storage);
+    pending_config_changes_storage
+        .may_load(&id.to_le_bytes())
+        .unwrap()
+}
+
+// Ids of the pending config changes of the page and the cursor for the next page, if any
+pub fn read_pending_config_change_ids<S: Storage>(
+    storage: &S,
+    page: &Page,
+) -> (Vec<u128>, Option<u128>) {
+    index_page(storage, &[PREFIX_PENDING_CONFIG_CHANGES], page)
+}
+
+pub fn read_pending_config_changes_count<S: Storage>(storage: &S) -> u128 {
+    index_len(storage, &[PREFIX_PENDING_CONFIG_CHANGES])
+}
+
+pub fn remove_pending_config_change<S: Storage>(storage: &mut S, id: u128) -> StdResult<()> {
+    let mut pending_config_changes_storage =
+        PrefixedStorage::new(PREFIX_PENDING_CONFIG_CHANGES, storage);
+    let mut pending_config_changes_storage: TypedStoreMut<PendingConfigChange, _> =
+        TypedStoreMut::attach(&mut pending_config_changes_storage);
+    pending_config_changes_storage.remove(&id.to_le_bytes());
+    index_remove(storage, &[PREFIX_PENDING_CONFIG_CHANGES], id)
+}
+
+pub fn write_config_changes_count<S: Storage>(storage: &mut S, count: u128) -> StdResult<()> {
+    TypedStoreMut::attach(storage).store(CONFIG_CHANGES_COUNT_KEY, &count)
+}
+
+// Pending config changes are indexed by id, so that they can be listed without keeping them in one
+// growing list
+pub fn write_pending_config_change<S: Storage>(
+    storage: &mut S,
+    pending_config_change: &PendingConfigChange,
+) -> StdResult<()> {
+    let id: u128 = pending_config_change.id.u128();
+    let mut pending_config_changes_storage =
+        PrefixedStorage::new(PREFIX_PENDING_CONFIG_CHANGES, storage);
+    let mut pending_config_changes_storage =
+        TypedStoreMut::attach(&mut pending_config_changes_storage);
+    pending_config_changes_storage.store(&id.to_le_bytes(), pending_config_change)?;
+    index_insert(storage, &[PREFIX_PENDING_CONFIG_CHANGES], id)
+}
+
+// === Fillers ===
+// Disabled fillers can't fill through open filling but can still withdraw their bond.
+// Fillers choose whether their address is shown to the creators of the orders they fill.
+// Execution fees are paid in SSCRT.
+// Volumes include both the token sent in and the token received for each fill.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct Filler {
+    pub address: HumanAddr,
+    pub address_public: bool,
+    pub bonded_amount: Uint128,
+    pub disabled: bool,
+    pub execution_fees_earned: Uint128,
+    pub last_active_block_height: u64,
+    pub total_fills: Uint128,
+    pub total_routes: Uint128,
+    pub unbonding_amount: Uint128,
+    pub unbonding_available_at_block_height: u64,
+    pub volumes: Vec<TokenAmount>,
+}
+impl Filler {
+    pub fn add_volume(&mut self, token: &HumanAddr, amount: Uint128) -> StdResult<()> {
+        match self
+            .volumes
+            .iter_mut()
+            .find(|volume| &volume.token == token)
+        {
+            Some(volume) => volume.amount = add_volume(volume.amount, amount)?,
+            None => self.volumes.push(TokenAmount {
+                token: token.clone(),
+                amount,
+            }),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenAmount {
+    pub token: HumanAddr,
+    pub amount: Uint128,
+}
+
+pub fn read_filler<S: Storage>(storage: &S, filler_address: &CanonicalAddr) -> Option<Filler> {
+    let fillers_storage = ReadonlyPrefixedStorage::new(PREFIX_FILLERS, storage);
+    let fillers_storage = TypedStore::attach(&fillers_storage);
+    fillers_storage.may_load(filler_address.as_slice()).unwrap()
+}
+
+// Addresses of fillers are positioned in the order they were first seen
+pub fn read_filler_address<S: Storage>(storage: &S, position: u128) -> Option<HumanAddr> {
+    let filler_addresses_storage = ReadonlyPrefixedStorage::new(PREFIX_FILLER_ADDRESSES, storage);
+    let filler_addresses_storage = TypedStore::attach(&filler_addresses_storage);
+    filler_addresses_storage
+        .may_load(&position.to_le_bytes())
+        .unwrap()
+}
+
+// Whether the address is in the config's addresses_allowed_to_fill
+pub fn read_filler_allowed<S: Storage>(storage: &S, filler_address: &CanonicalAddr) -> bool {
+    let allowed_storage =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_FILLERS, PREFIX_FILLERS_ALLOWED], storage);
+    let allowed_storage = TypedStore::attach(&allowed_storage);
+    allowed_storage
+        .may_load(filler_address.as_slice())
+        .unwrap()
+        .unwrap_or(false)
+}
+
+pub fn read_fillers_count<S: Storage>(storage: &S) -> u128 {
+    TypedStore::attach(storage)
+        .may_load(FILLERS_COUNT_KEY)
+        .unwrap()
+        .unwrap_or(0)
+}
+
+pub fn write_filler<S: Storage>(
+    storage: &mut S,
+    filler_address: &CanonicalAddr,
+    filler: &Filler,
+) -> StdResult<()> {
+    let mut fillers_storage = PrefixedStorage::new(PREFIX_FILLERS, storage);
+    let mut fillers_storage = TypedStoreMut::attach(&mut fillers_storage);
+    fillers_storage.store(filler_address.as_slice(), filler)
+}
+
+pub fn write_filler_address<S: Storage>(
+    storage: &mut S,
+    position: u128,
+    filler_address: &HumanAddr,
+) -> StdResult<()> {
+    let mut filler_addresses_storage = PrefixedStorage::new(PREFIX_FILLER_ADDRESSES, storage);
+    let mut filler_addresses_storage = TypedStoreMut::attach(&mut filler_addresses_storage);
+    filler_addresses_storage.store(&position.to_le_bytes(), filler_address)
+}
+
+pub fn write_filler_allowed<S: Storage>(
+    storage: &mut S,
+    filler_address: &CanonicalAddr,
+    allowed: bool,
+) -> StdResult<()> {
+    let mut allowed_storage =
+        PrefixedStorage::multilevel(&[PREFIX_FILLERS, PREFIX_FILLERS_ALLOWED], storage);
+    let mut allowed_storage = TypedStoreMut::attach(&mut allowed_storage);
+    allowed_storage.store(filler_address.as_slice(), &allowed)
+}
+
+pub fn write_fillers_count<S: Storage>(storage: &mut S, count: u128) -> StdResult<()> {
+    TypedStoreMut::attach(storage).store(FILLERS_COUNT_KEY, &count)
+}
+
+// === Indexes ===
+// Open items, e.g. pending config changes, are kept in a doubly linked list of their positions so
+// that they can be paged through without going over closed ones. Positions must be inserted in
+// increasing order. Removed positions keep their links, so that a cursor on a removed position
+// still leads to the next open one. Those links end at the tail the index had when they were
+// removed, so open positions added since then are found by seeking from the current tail.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+struct IndexEntry {
+    prev: Option<u128>,
+    next: Option<u128>,
+    removed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+struct IndexEnds {
+    head: Option<u128>,
+    tail: Option<u128>,
+    len: u128,
+}
+
+const INDEX_ENDS_KEY: &[u8] = b"ends";
+
+fn read_index_entry<S: ReadonlyStorage>(
+    storage: &S,
+    index: &[&[u8]],
+    position: u128,
+) -> Option<IndexEntry> {
+    let index_storage =
+        ReadonlyPrefixedStorage::multilevel(&[&[PREFIX_INDEXES], index].concat(), storage);
+    TypedStore::attach(&index_storage)
+        .may_load(&position.to_le_bytes())
+        .unwrap()
+}
+
+fn read_index_ends<S: ReadonlyStorage>(storage: &S, index: &[&[u8]]) -> IndexEnds {
+    let index_storage =
+        ReadonlyPrefixedStorage::multilevel(&[&[PREFIX_INDEXES], index].concat(), storage);
+    TypedStore::attach(&index_storage)
+        .may_load(INDEX_ENDS_KEY)
+        .unwrap()
+        .unwrap_or_default()
+}
+
+fn write_index_entry<S: Storage>(
+    storage: &mut S,
+    index: &[&[u8]],
+    position: u128,
+    entry: &IndexEntry,
+) -> StdResult<()> {
+    let mut index_storage =
+        PrefixedStorage::multilevel(&[&[PREFIX_INDEXES], index].concat(), storage);
+    TypedStoreMut::attach(&mut index_storage).store(&position.to_le_bytes(), entry)
+}
+
+fn write_index_ends<S: Storage>(
+    storage: &mut S,
+    index: &[&[u8]],
+    ends: &IndexEnds,
+) -> StdResult<()> {
+    let mut index_storage =
+        PrefixedStorage::multilevel(&[&[PREFIX_INDEXES], index].concat(), storage);
+    TypedStoreMut::attach(&mut index_storage).store(INDEX_ENDS_KEY, ends)
+}
+
+pub fn index_insert<S: Storage>(storage: &mut S, index: &[&[u8]], position: u128) -> StdResult<()> {
+    let mut ends: IndexEnds = read_index_ends(storage, index);
+    if let Some(tail) = ends.tail {
+        if tail >= position {
+            return Err(StdError::generic_err(
+                "Positions must be added to an index in increasing order.",
+            ));
+        }
+        let mut tail_entry: IndexEntry = read_index_entry(storage, index, tail).unwrap();
+        tail_entry.next = Some(position);
+        write_index_entry(storage, index, tail, &tail_entry)?;
+    } else {
+        ends.head = Some(position);
+    }
+    write_index_entry(
+        storage,
+        index,
+        position,
+        &IndexEntry {
+            prev: ends.tail,
+            next: None,
+            removed: false,
+        },
+    )?;
+    ends.tail = Some(position);
+    ends.len += 1;
+    write_index_ends(storage, index, &ends)
+}
+
+pub fn index_len<S: ReadonlyStorage>(storage: &S, index: &[&[u8]]) -> u128 {
+    read_index_ends(storage, index).len
+}
+
+// Positions of the page and the cursor for the next page, if any
+pub fn index_page<S: ReadonlyStorage>(
+    storage: &S,
+    index: &[&[u8]],
+    page: &Page,
+) -> (Vec<u128>, Option<u128>) {
+    let step = |entry: &IndexEntry| match page.order {
+        SortOrder::Ascending => entry.next,
+        SortOrder::Descending => entry.prev,
+    };
+    let mut position: Option<u128> = match page.start_after {
+        Some(start_after) => match read_index_entry(storage, index, start_after) {
+            Some(entry) if !entry.removed => step(&entry),
+            Some(entry) => {
+                let mut position: Option<u128> = step(&entry);
+                while let Some(current) = position {
+                    let entry: IndexEntry = read_index_entry(storage, index, current).unwrap();
+                    if !entry.removed {
+                        break;
+                    }
+                    position = step(&entry);
+                }
+                match (position, page.order) {
+                    (None, SortOrder::Ascending) => index_seek(storage, index, page),
+                    _ => position,
+                }
+            }
+            None => index_seek(storage, index, page),
+        },
+        None => {
+            let ends: IndexEnds = read_index_ends(storage, index);
+            match page.order {
+                SortOrder::Ascending => ends.head,
+                SortOrder::Descending => ends.tail,
+            }
+        }
+    };
+    let mut positions: Vec<u128> = vec![];
+    while let Some(current) = position {
+        let entry: IndexEntry = read_index_entry(storage, index, current).unwrap();
+        if !entry.removed {
+            if positions.len() as u128 == page.limit {
+                let next_start_after: Option<u128> = positions.last().copied();
+                return (positions, next_start_after);
+            }
+            positions.push(current);
+        }
+        position = step(&entry);
+    }
+    (positions, None)
+}
+
+// The first open position after the page's cursor, going back from the tail
+fn index_seek<S: ReadonlyStorage>(storage: &S, index: &[&[u8]], page: &Page) -> Option<u128> {
+    let mut position: Option<u128> = read_index_ends(storage, index).tail;
+    let mut first: Option<u128> = None;
+    while let Some(current) = position {
+        match page.order {
+            SortOrder::Ascending if page.is_after_cursor(current) => first = Some(current),
+            SortOrder::Ascending => break,
+            SortOrder::Descending if page.is_after_cursor(current) => return Some(current),
+            SortOrder::Descending => {}
+        }
+        position = read_index_entry(storage, index, current).unwrap().prev;
+    }
+    first
+}
+
+pub fn index_remove<S: Storage>(storage: &mut S, index: &[&[u8]], position: u128) -> StdResult<()> {
+    let mut entry: IndexEntry = match read_index_entry(storage, index, position) {
+        Some(entry) if !entry.removed => entry,
+        _ => return Ok(()),
+    };
+    let mut ends: IndexEnds = read_index_ends(storage, index);
+    match entry.prev {
+        Some(prev) => {
+            let mut prev_entry: IndexEntry = read_index_entry(storage, index, prev).unwrap();
+            prev_entry.next = entry.next;
+            write_index_entry(storage, index, prev, &prev_entry)?;
+        }
+        None => ends.head = entry.next,
+    }
+    match entry.next {
+        Some(next) => {
+            let mut next_entry: IndexEntry = read_index_entry(storage, index, next).unwrap();
+            next_entry.prev = entry.prev;
+            write_index_entry(storage, index, next, &next_entry)?;
+        }
+        None => ends.tail = entry.prev,
+    }
+    entry.removed = true;
+    write_index_entry(storage, index, position, &entry)?;
+    ends.len -= 1;
+    write_index_ends(storage, index, &ends)
+}
+
 // === Registered tokens ===
+// name, symbol and decimals are cached from the token's token_info when it is registered and
+// can be refreshed with refresh_token_metadata. decimals can also be set with update_token.
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, JsonSchema)]
 pub struct RegisteredToken {
     pub address: HumanAddr,
     pub contract_hash: String,
     pub sum_balance: Uint128,
+    pub disabled: bool,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
 }
 
 pub fn read_registered_token<S: Storage>(
@@ -53,6 +775,14 @@ pub fn read_registered_token<S: Storage>(
         .unwrap()
 }
 
+// Addresses of registered tokens in the order they were registered
+pub fn read_registered_token_addresses<S: Storage>(storage: &S) -> Vec<HumanAddr> {
+    TypedStore::attach(storage)
+        .may_load(REGISTERED_TOKEN_ADDRESSES_KEY)
+        .unwrap()
+        .unwrap_or_default()
+}
+
 pub fn write_registered_token<S: Storage>(
     storage: &mut S,
     token_address: &CanonicalAddr,
@@ -63,7 +793,93 @@ pub fn write_registered_token<S: Storage>(
     registered_tokens_storage.store(token_address.as_slice(), registered_token)
 }
 
+pub fn write_registered_token_addresses<S: Storage>(
+    storage: &mut S,
+    registered_token_addresses: &[HumanAddr],
+) -> StdResult<()> {
+    TypedStoreMut::attach(storage).store(
+        REGISTERED_TOKEN_ADDRESSES_KEY,
+        &registered_token_addresses.to_vec(),
+    )
+}
+
+// Running totals of what the contract holds for each token on behalf of open orders, commitments,
+// filler bonds and escrowed execution fees, so that solvency doesn't have to go over all of them
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct TokenTotals {
+    pub bonded_amount: Uint128,
+    pub committed_amount: Uint128,
+    pub execution_fee_amount: Uint128,
+    pub unfilled_order_amount: Uint128,
+}
+
+pub fn read_token_totals<S: Storage>(storage: &S, token_address: &HumanAddr) -> TokenTotals {
+    let token_totals_storage = ReadonlyPrefixedStorage::new(PREFIX_TOKEN_TOTALS, storage);
+    let token_totals_storage = TypedStore::attach(&token_totals_storage);
+    token_totals_storage
+        .may_load(token_address.as_str().as_bytes())
+        .unwrap()
+        .unwrap_or_default()
+}
+
+pub fn write_token_totals<S: Storage>(
+    storage: &mut S,
+    token_address: &HumanAddr,
+    token_totals: &TokenTotals,
+) -> StdResult<()> {
+    let mut token_totals_storage = PrefixedStorage::new(PREFIX_TOKEN_TOTALS, storage);
+    let mut token_totals_storage = TypedStoreMut::attach(&mut token_totals_storage);
+    token_totals_storage.store(token_address.as_str().as_bytes(), token_totals)
+}
+
+// === Migration ===
+// State can be imported into a fresh instance until the admin completes the import
+pub fn read_import_complete<S: Storage>(storage: &S) -> bool {
+    TypedStore::attach(storage)
+        .may_load(IMPORT_COMPLETE_KEY)
+        .unwrap()
+        .unwrap_or(false)
+}
+
+pub fn write_import_complete<S: Storage>(storage: &mut S, import_complete: bool) -> StdResult<()> {
+    TypedStoreMut::attach(storage).store(IMPORT_COMPLETE_KEY, &import_complete)
+}
+
 // === Orders ===
+// An open contract order without its creator, for the public order book
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct BookOrder {
+    pub position: Uint128,
+    pub from_token: HumanAddr,
+    pub to_token: HumanAddr,
+    pub from_amount_remaining: Uint128,
+    pub net_to_amount_remaining: Uint128,
+    pub execution_fee: Option<Uint128>,
+}
+
+// What filling an order moves. from_amount is released to the filler, fee is the part of the
+// order's fee covered by the fill and execution_fee is paid to the filler on the first fill.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct Fill {
+    pub from_amount: Uint128,
+    pub net_to_amount: Uint128,
+    pub fee: Uint128,
+    pub execution_fee: Option<Uint128>,
+}
+
+// A cancelled order is Cancelled however much of it was filled
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    Open,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+}
+
+// fill_percentage is in hundredths of a percent, so 10000 is completely filled.
+// limit_price is the net to amount per from amount in whole tokens multiplied by 10^18. It is
+// only given when the decimals of both tokens are known.
 #[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
 pub struct HumanizedOrder {
     pub creator: HumanAddr,
@@ -79,12 +895,18 @@ pub struct HumanizedOrder {
     pub fee: Uint128,
     pub created_at_block_time: u64,
     pub created_at_block_height: u64,
+    pub status: OrderStatus,
+    pub from_amount_remaining: Uint128,
+    pub net_to_amount_remaining: Uint128,
+    pub fill_percentage: Uint128,
+    pub limit_price: Option<Uint128>,
 }
 
 #[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
 pub struct Order {
     pub creator: CanonicalAddr,
     pub execution_fee: Option<Uint128>,
+    pub execution_fee_paid: bool,
     pub position: Uint128,
     pub other_storage_position: Uint128,
     pub from_token: HumanAddr,
@@ -99,7 +921,128 @@ pub struct Order {
     pub created_at_block_height: u64,
 }
 impl Order {
-    pub fn into_humanized<A: Api>(self, api: &A) -> StdResult<HumanizedOrder> {
+    // The last fill takes whatever is left of the from amount so that rounding doesn't leave dust.
+    // Fills that would get none of the from amount are rejected, and the execution fee is only
+    // paid to the first fill.
+    pub fn fill(&mut self, amount: Uint128) -> StdResult<Fill> {
+        if self.cancelled {
+            return Err(StdError::generic_err("Order already cancelled."));
+        }
+        let unfilled_amount: Uint128 = (self.net_to_amount - self.net_to_amount_filled)?;
+        if amount > unfilled_amount {
+            return Err(StdError::generic_err(
+                "Amount is greater than unfilled amount.",
+            ));
+        }
+        let from_amount: Uint128 = if amount == unfilled_amount {
+            (self.from_amount - self.from_amount_filled)?
+        } else {
+            Uint128::from(
+                (U256::from(self.from_amount.u128()) * U256::from(amount.u128())
+                    / U256::from(self.net_to_amount.u128()))
+                .as_u128(),
+            )
+        };
+        if from_amount.is_zero() {
+            return Err(StdError::generic_err("Amount is too small to fill."));
+        }
+
+        let execution_fee: Option<Uint128> = if self.execution_fee_paid {
+            None
+        } else {
+            self.execution_fee
+        };
+        self.execution_fee_paid = self.execution_fee_paid || execution_fee.is_some();
+        self.net_to_amount_filled += amount;
+        self.from_amount_filled += from_amount;
+
+        Ok(Fill {
+            from_amount,
+            net_to_amount: amount,
+            fee: self.fee.multiply_ratio(amount, self.net_to_amount),
+            execution_fee,
+        })
+    }
+
+    // Remaining amounts are rounded down so that they can be filled as they are
+    pub fn into_book_order(self, significant_figures: Option<u8>) -> StdResult<BookOrder> {
+        let round = |amount: Uint128| -> Uint128 {
+            match significant_figures {
+                Some(significant_figures) => {
+                    round_down_to_significant_figures(amount, significant_figures)
+                }
+                None => amount,
+            }
+        };
+        Ok(BookOrder {
+            position: self.position,
+            from_amount_remaining: round((self.from_amount - self.from_amount_filled)?),
+            net_to_amount_remaining: round((self.net_to_amount - self.net_to_amount_filled)?),
+            from_token: self.from_token,
+            to_token: self.to_token,
+            execution_fee: self.execution_fee,
+        })
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.cancelled && self.from_amount_filled < self.from_amount
+    }
+
+    // Execution fees are escrowed in SSCRT until the first fill
+    pub fn escrowed_execution_fee(&self) -> Uint128 {
+        match self.execution_fee {
+            Some(execution_fee) if self.is_open() && !self.execution_fee_paid => execution_fee,
+            _ => Uint128(0),
+        }
+    }
+
+    pub fn unfilled_amount(&self) -> Uint128 {
+        if self.is_open() {
+            Uint128(self.from_amount.u128() - self.from_amount_filled.u128())
+        } else {
+            Uint128(0)
+        }
+    }
+
+    pub fn status(&self) -> OrderStatus {
+        if self.cancelled {
+            OrderStatus::Cancelled
+        } else if self.net_to_amount_filled == self.net_to_amount {
+            OrderStatus::Filled
+        } else if self.net_to_amount_filled.is_zero() {
+            OrderStatus::Open
+        } else {
+            OrderStatus::PartiallyFilled
+        }
+    }
+
+    pub fn into_humanized<A: Api>(
+        self,
+        api: &A,
+        from_decimals: Option<u8>,
+        to_decimals: Option<u8>,
+    ) -> StdResult<HumanizedOrder> {
+        let fill_percentage: Uint128 = if self.net_to_amount.is_zero() {
+            Uint128(0)
+        } else {
+            Uint128::from(
+                (U256::from(self.net_to_amount_filled.u128()) * U256::from(10_000)
+                    / U256::from(self.net_to_amount.u128()))
+                .as_u128(),
+            )
+        };
+        let limit_price: Option<Uint128> = match (from_decimals, to_decimals) {
+            (Some(from_decimals), Some(to_decimals)) => calculate_limit_price(
+                self.from_amount,
+                self.net_to_amount,
+                from_decimals,
+                to_decimals,
+            ),
+            _ => None,
+        };
+        let status: OrderStatus = self.status();
+        let from_amount_remaining: Uint128 = (self.from_amount - self.from_amount_filled)?;
+        let net_to_amount_remaining: Uint128 = (self.net_to_amount - self.net_to_amount_filled)?;
         Ok(HumanizedOrder {
             creator: api.human_address(&self.creator)?,
             execution_fee: self.execution_fee,
@@ -114,10 +1057,332 @@ impl Order {
             fee: self.fee,
             created_at_block_time: self.created_at_block_time,
             created_at_block_height: self.created_at_block_height,
+            status,
+            from_amount_remaining,
+            net_to_amount_remaining,
+            fill_percentage,
+            limit_price,
         })
     }
 }
 
+// Open contract orders are indexed by their contract position, both overall and per pair
+fn open_orders_index<'a>(pair: Option<(&'a HumanAddr, &'a HumanAddr)>) -> Vec<&'a [u8]> {
+    match pair {
+        Some((from_token, to_token)) => vec![
+            PREFIX_OPEN_ORDERS,
+            from_token.as_str().as_bytes(),
+            to_token.as_str().as_bytes(),
+        ],
+        None => vec![PREFIX_OPEN_ORDERS],
+    }
+}
+
+pub fn insert_open_order<S: Storage>(storage: &mut S, contract_order: &Order) -> StdResult<()> {
+    let position: u128 = contract_order.position.u128();
+    index_insert(storage, &open_orders_index(None), position)?;
+    index_insert(
+        storage,
+        &open_orders_index(Some((&contract_order.from_token, &contract_order.to_token))),
+        position,
+    )
+}
+
+pub fn read_open_order_positions<S: Storage>(
+    storage: &S,
+    pair: Option<(&HumanAddr, &HumanAddr)>,
+    page: &Page,
+) -> (Vec<u128>, Option<u128>) {
+    index_page(storage, &open_orders_index(pair), page)
+}
+
+pub fn read_open_orders_count<S: Storage>(
+    storage: &S,
+    pair: Option<(&HumanAddr, &HumanAddr)>,
+) -> u128 {
+    index_len(storage, &open_orders_index(pair))
+}
+
+pub fn remove_open_order<S: Storage>(storage: &mut S, contract_order: &Order) -> StdResult<()> {
+    let position: u128 = contract_order.position.u128();
+    index_remove(storage, &open_orders_index(None), position)?;
+    index_remove(
+        storage,
+        &open_orders_index(Some((&contract_order.from_token, &contract_order.to_token))),
+        position,
+    )
+}
+
+// Moves the price from base units to whole tokens. None when it can't be represented.
+fn calculate_limit_price(
+    from_amount: Uint128,
+    to_amount: Uint128,
+    from_decimals: u8,
+    to_decimals: u8,
+) -> Option<Uint128> {
+    if from_amount.is_zero() {
+        return None;
+    }
+
+    let price: U256 = U256::from(to_amount.u128()) * U256::from(PRICE_SCALE);
+    let from_amount: U256 = U256::from(from_amount.u128());
+    let decimals_factor: U256 = U256::from(10).checked_pow(U256::from(
+        from_decimals.max(to_decimals) - from_decimals.min(to_decimals),
+    ))?;
+    let price: U256 = if from_decimals >= to_decimals {
+        price.checked_mul(decimals_factor)? / from_amount
+    } else {
+        price / from_amount.checked_mul(decimals_factor)?
+    };
+    if price > U256::from(u128::MAX) {
+        return None;
+    }
+    Some(Uint128::from(price.as_u128()))
+}
+
+fn round_down_to_significant_figures(amount: Uint128, significant_figures: u8) -> Uint128 {
+    let digits: u32 = amount.to_string().len() as u32;
+    let significant_figures: u32 = u32::from(significant_figures);
+    if digits <= significant_figures {
+        return amount;
+    }
+
+    let factor: u128 = 10u128.pow(digits - significant_figures);
+    Uint128(amount.u128() / factor * factor)
+}
+
+// === Pairs ===
+// Market settings for orders selling from_token for to_token.
+// The price of an order, net_to_amount per from_amount multiplied by PRICE_SCALE and rounded down
+// as in PairStats, must be a multiple of price_tick_size (zero means no tick size)
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, JsonSchema)]
+pub struct Pair {
+    pub from_token: HumanAddr,
+    pub to_token: HumanAddr,
+    pub enabled: bool,
+    pub minimum_from_amount: Uint128,
+    pub price_tick_size: Uint128,
+}
+impl Pair {
+    pub fn is_on_tick(&self, from_amount: Uint128, net_to_amount: Uint128) -> bool {
+        if self.price_tick_size.is_zero() || from_amount.is_zero() {
+            return true;
+        }
+
+        let price: U256 = U256::from(net_to_amount.u128()) * U256::from(PRICE_SCALE)
+            / U256::from(from_amount.u128());
+        (price % U256::from(self.price_tick_size.u128())).is_zero()
+    }
+}
+
+// Prices are the amount of to_token per from_token, multiplied by PRICE_SCALE.
+// Rolling volumes cover the 24 hours up to the last fill and are kept in hourly buckets.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct PairStats {
+    pub from_token: HumanAddr,
+    pub to_token: HumanAddr,
+    pub cumulative_from_volume: Uint128,
+    pub cumulative_to_volume: Uint128,
+    pub last_fill_block_time: u64,
+    pub last_fill_price: Uint128,
+    pub rolling_from_volume: Uint128,
+    pub rolling_to_volume: Uint128,
+    pub rolling_volume_buckets: Vec<VolumeBucket>,
+    pub trade_count: Uint128,
+    pub vwap: Uint128,
+}
+impl PairStats {
+    pub fn add_fill(
+        &mut self,
+        from_amount: Uint128,
+        to_amount: Uint128,
+        block_time: u64,
+    ) -> StdResult<()> {
+        self.cumulative_from_volume = add_volume(self.cumulative_from_volume, from_amount)?;
+        self.cumulative_to_volume = add_volume(self.cumulative_to_volume, to_amount)?;
+        self.last_fill_block_time = block_time;
+        if let Some(price) = calculate_price(from_amount, to_amount) {
+            self.last_fill_price = price;
+        }
+        if let Some(price) = calculate_price(self.cumulative_from_volume, self.cumulative_to_volume)
+        {
+            self.vwap = price;
+        }
+        self.trade_count += Uint128(1);
+
+        let bucket_start_time: u64 = block_time - block_time % ROLLING_VOLUME_BUCKET_IN_SECONDS;
+        self.rolling_volume_buckets.retain(|bucket| {
+            bucket.start_time + ROLLING_VOLUME_PERIOD_IN_SECONDS > bucket_start_time
+        });
+        match self
+            .rolling_volume_buckets
+            .iter_mut()
+            .find(|bucket| bucket.start_time == bucket_start_time)
+        {
+            Some(bucket) => {
+                bucket.from_volume = add_volume(bucket.from_volume, from_amount)?;
+                bucket.to_volume = add_volume(bucket.to_volume, to_amount)?;
+            }
+            None => self.rolling_volume_buckets.push(VolumeBucket {
+                start_time: bucket_start_time,
+                from_volume: from_amount,
+                to_volume: to_amount,
+            }),
+        }
+        self.rolling_from_volume = self
+            .rolling_volume_buckets
+            .iter()
+            .try_fold(Uint128::zero(), |volume, bucket| {
+                add_volume(volume, bucket.from_volume)
+            })?;
+        self.rolling_to_volume = self
+            .rolling_volume_buckets
+            .iter()
+            .try_fold(Uint128::zero(), |volume, bucket| {
+                add_volume(volume, bucket.to_volume)
+            })?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VolumeBucket {
+    pub start_time: u64,
+    pub from_volume: Uint128,
+    pub to_volume: Uint128,
+}
+
+// None when the from amount is zero or the price can't be represented
+pub fn calculate_price(from_amount: Uint128, to_amount: Uint128) -> Option<Uint128> {
+    if from_amount.is_zero() {
+        return None;
+    }
+
+    let price: U256 =
+        U256::from(to_amount.u128()) * U256::from(PRICE_SCALE) / U256::from(from_amount.u128());
+    if price > U256::from(u128::MAX) {
+        return None;
+    }
+    Some(Uint128::from(price.as_u128()))
+}
+
+pub fn add_volume(volume: Uint128, amount: Uint128) -> StdResult<Uint128> {
+    volume
+        .u128()
+        .checked_add(amount.u128())
+        .map(Uint128)
+        .ok_or_else(|| StdError::generic_err("Reached implementation limit for volume."))
+}
+
+pub fn read_pair<S: Storage>(
+    storage: &S,
+    from_token_address: &CanonicalAddr,
+    to_token_address: &CanonicalAddr,
+) -> Option<Pair> {
+    let pairs_storage = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_PAIRS, from_token_address.as_slice()],
+        storage,
+    );
+    let pairs_storage = TypedStore::attach(&pairs_storage);
+    pairs_storage.may_load(to_token_address.as_slice()).unwrap()
+}
+
+pub fn read_pair_stats<S: Storage>(
+    storage: &S,
+    from_token_address: &CanonicalAddr,
+    to_token_address: &CanonicalAddr,
+) -> Option<PairStats> {
+    let pair_stats_storage = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_PAIR_STATS, from_token_address.as_slice()],
+        storage,
+    );
+    let pair_stats_storage = TypedStore::attach(&pair_stats_storage);
+    pair_stats_storage
+        .may_load(to_token_address.as_slice())
+        .unwrap()
+}
+
+// (from_token, to_token) addresses of pairs in the order they were added
+pub fn read_pair_token_addresses<S: Storage>(storage: &S) -> Vec<(HumanAddr, HumanAddr)> {
+    TypedStore::attach(storage)
+        .may_load(PAIR_TOKEN_ADDRESSES_KEY)
+        .unwrap()
+        .unwrap_or_default()
+}
+
+pub fn write_pair<S: Storage>(
+    storage: &mut S,
+    from_token_address: &CanonicalAddr,
+    to_token_address: &CanonicalAddr,
+    pair: &Pair,
+) -> StdResult<()> {
+    let mut pairs_storage =
+        PrefixedStorage::multilevel(&[PREFIX_PAIRS, from_token_address.as_slice()], storage);
+    let mut pairs_storage = TypedStoreMut::attach(&mut pairs_storage);
+    pairs_storage.store(to_token_address.as_slice(), pair)
+}
+
+pub fn write_pair_stats<S: Storage>(
+    storage: &mut S,
+    from_token_address: &CanonicalAddr,
+    to_token_address: &CanonicalAddr,
+    pair_stats: &PairStats,
+) -> StdResult<()> {
+    let mut pair_stats_storage =
+        PrefixedStorage::multilevel(&[PREFIX_PAIR_STATS, from_token_address.as_slice()], storage);
+    let mut pair_stats_storage = TypedStoreMut::attach(&mut pair_stats_storage);
+    pair_stats_storage.store(to_token_address.as_slice(), pair_stats)
+}
+
+pub fn write_pair_token_addresses<S: Storage>(
+    storage: &mut S,
+    pair_token_addresses: &[(HumanAddr, HumanAddr)],
+) -> StdResult<()> {
+    TypedStoreMut::attach(storage).store(PAIR_TOKEN_ADDRESSES_KEY, &pair_token_addresses.to_vec())
+}
+
+// === Roles ===
+// The admin implicitly holds every role
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    FeeManager,
+    FillerManager,
+    Pauser,
+    TokenRegistrar,
+    Treasury,
+}
+impl Role {
+    pub fn storage_key(&self) -> &[u8] {
+        match self {
+            Role::FeeManager => b"fee_manager",
+            Role::FillerManager => b"filler_manager",
+            Role::Pauser => b"pauser",
+            Role::TokenRegistrar => b"token_registrar",
+            Role::Treasury => b"treasury",
+        }
+    }
+}
+
+pub fn read_role_addresses<S: Storage>(storage: &S, role: Role) -> Vec<HumanAddr> {
+    let roles_storage = ReadonlyPrefixedStorage::new(PREFIX_ROLES, storage);
+    let roles_storage = TypedStore::attach(&roles_storage);
+    roles_storage
+        .may_load(role.storage_key())
+        .unwrap()
+        .unwrap_or_default()
+}
+
+pub fn write_role_addresses<S: Storage>(
+    storage: &mut S,
+    role: Role,
+    addresses: &[HumanAddr],
+) -> StdResult<()> {
+    let mut roles_storage = PrefixedStorage::new(PREFIX_ROLES, storage);
+    let mut roles_storage = TypedStoreMut::attach(&mut roles_storage);
+    roles_storage.store(role.storage_key(), &addresses.to_vec())
+}
+
 // === ROUTE ===
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Hop {
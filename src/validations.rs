@@ -8,6 +8,14 @@ pub fn authorize(allowed: Vec<HumanAddr>, received: &HumanAddr) -> StdResult<()>
     Ok(())
 }
 
+pub fn validate_not_paused(paused: bool, message: &str) -> StdResult<()> {
+    if paused {
+        return Err(StdError::generic_err(message));
+    }
+
+    Ok(())
+}
+
 pub fn validate_human_addr(
     expected: &HumanAddr,
     received: &HumanAddr,